@@ -34,7 +34,226 @@ mod tests {
     use super::*;
     use yiran_json::*;
 
+    mod duration_millis {
+        use super::JsonNode;
+        use std::time::Duration;
+
+        pub fn to_json(duration: &Duration) -> JsonNode {
+            JsonNode::Number(duration.as_millis() as f64)
+        }
+
+        pub fn from_json(json: &JsonNode) -> Duration {
+            match json {
+                JsonNode::Number(n) => Duration::from_millis(*n as u64),
+                _ => panic!("Cannot convert non-number type to duration"),
+            }
+        }
+    }
+
+    fn bytes_to_hex(bytes: &[u8]) -> JsonNode {
+        let hex = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        JsonNode::String(hex)
+    }
+
+    fn bytes_from_hex(json: &JsonNode) -> Vec<u8> {
+        match json {
+            JsonNode::String(s) => (0..s.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+                .collect(),
+            _ => panic!("Cannot convert non-string type to bytes"),
+        }
+    }
+
+    #[derive(Debug, Clone, Default, yiran_json::JsonType, PartialEq)]
+    struct WithHooks {
+        #[json(with = "duration_millis")]
+        timeout: std::time::Duration,
+        #[json(to_json_with = "bytes_to_hex", from_json_with = "bytes_from_hex")]
+        payload: Vec<u8>,
+    }
+
+    #[derive(Debug, Clone, yiran_json::JsonType, PartialEq)]
+    enum HookedEnum {
+        Delay(#[json(with = "duration_millis")] std::time::Duration),
+        Blob {
+            #[json(to_json_with = "bytes_to_hex", from_json_with = "bytes_from_hex")]
+            bytes: Vec<u8>,
+        },
+    }
+
+    #[derive(Debug, Clone, yiran_json::JsonType, PartialEq)]
+    #[json(deny_unknown_fields)]
+    struct StrictConfig {
+        name: String,
+        retries: u32,
+    }
+
+    #[derive(Debug, Clone, yiran_json::JsonType, PartialEq)]
+    #[json(deny_unknown_fields)]
+    enum StrictEnum {
+        Named { level: i32 },
+    }
+
+    #[derive(Debug, Clone, yiran_json::JsonType, PartialEq)]
+    struct Marker;
+
+    #[derive(Debug, Clone, yiran_json::JsonType, PartialEq)]
+    #[json(deny_unknown_fields)]
+    struct StrictMarker;
+
+    #[derive(Debug, Clone, yiran_json::JsonType, PartialEq)]
+    #[json(transparent)]
+    struct UserId(u64);
+
+    #[derive(Debug, Clone, yiran_json::JsonType, PartialEq)]
+    #[json(transparent)]
+    struct Timeout {
+        #[json(with = "duration_millis")]
+        duration: std::time::Duration,
+    }
+
+    #[derive(yiran_json::ToJson)]
+    struct BorrowedView<'a> {
+        name: &'a str,
+        tags: &'a Vec<String>,
+    }
+
+    fn parse_address(json: &JsonNode) -> std::net::Ipv4Addr {
+        String::from_json(json).parse().unwrap()
+    }
+
+    #[derive(Debug, PartialEq, yiran_json::FromJson)]
+    struct ReadOnlyDto {
+        id: u32,
+        #[json(from_json_with = "parse_address")]
+        address: std::net::Ipv4Addr,
+    }
+
+    fn try_parse_address(json: &JsonNode) -> Result<std::net::Ipv4Addr, JsonError> {
+        String::try_from_json(json)?
+            .parse()
+            .map_err(|err| JsonError::new(format!("Invalid address: {}", err)))
+    }
+
+    #[derive(Debug, PartialEq, yiran_json::FromJson)]
+    struct CheckedDto {
+        id: u32,
+        #[json(try_from_json_with = "try_parse_address")]
+        address: std::net::Ipv4Addr,
+    }
+
+    #[derive(Debug, Clone, PartialEq, yiran_json::JsonType, yiran_json::JsonSchema)]
+    struct Wrapper<T> {
+        value: T,
+        items: Vec<T>,
+    }
+
+    fn check_even(value: &u32) -> Result<(), String> {
+        if value.is_multiple_of(2) {
+            Ok(())
+        } else {
+            Err(format!("{} is not even", value))
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, yiran_json::JsonType)]
+    struct SignUp {
+        #[json(range(min = 0, max = 120))]
+        age: i32,
+        #[json(length(min = 3, max = 16), regex = "^[a-z_]+$")]
+        username: String,
+        #[json(non_empty)]
+        tags: Vec<String>,
+        #[json(custom = "check_even")]
+        seats: u32,
+        #[json(range(min = 0.5))]
+        ratio: Option<f64>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, yiran_json::JsonType)]
+    struct Team {
+        lead: SignUp,
+        #[json(length(min = 1))]
+        name: String,
+    }
+
+    #[derive(Debug, Clone, PartialEq, yiran_json::JsonType)]
+    enum Command {
+        Rename(#[json(length(max = 4))] String),
+        Move {
+            #[json(range(max = 10))]
+            steps: u8,
+        },
+    }
+
+    /// A user of the service.
+    #[derive(Debug, Clone, PartialEq, yiran_json::JsonType, yiran_json::JsonSchema)]
+    #[json(deny_unknown_fields)]
+    struct Profile {
+        /// Display name.
+        #[json(length(max = 32))]
+        name: String,
+        age: Option<u8>,
+        role: Role,
+    }
+
+    #[derive(Debug, Clone, PartialEq, yiran_json::JsonType, yiran_json::JsonSchema)]
+    enum Role {
+        /// Full access.
+        Admin,
+        Member(u32),
+    }
+
+    #[derive(Debug, Clone, PartialEq, yiran_json::JsonSchema)]
+    struct TreeNode {
+        label: String,
+        children: Vec<TreeNode>,
+    }
+
+    mod billing {
+        #[derive(Debug, Clone, PartialEq, yiran_json::JsonSchema)]
+        pub struct Config {
+            pub currency: String,
+        }
+    }
+
+    mod shipping {
+        #[derive(Debug, Clone, PartialEq, yiran_json::JsonSchema)]
+        pub struct Config {
+            pub express: bool,
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, yiran_json::JsonSchema)]
+    struct Settings {
+        billing: billing::Config,
+        shipping: shipping::Config,
+    }
+
+    /// Converted by hand, with no `JsonSchema` impl.
+    #[derive(Debug, Clone, PartialEq)]
+    struct Opaque(u8);
+
+    impl ToJson for Opaque {
+        fn to_json(&self) -> JsonNode {
+            JsonNode::Number(self.0 as f64)
+        }
+    }
+
+    impl FromJson for Opaque {
+        fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+            u8::try_from_json(json).map(Opaque)
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, yiran_json::JsonType)]
+    struct HoldsOpaque {
+        opaque: Opaque,
+    }
+
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_tuple() {
         let tp = TpType(42, 3, 3.14);
         let tp_json = tp.to_json();
@@ -43,6 +262,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_enum() {
         use yiran_json::*;
         let first_variant = TestEnum::Variant1();
@@ -70,6 +290,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_struct() {
         let test_struct = TestStruct {
             field1: 42,
@@ -90,6 +311,511 @@ mod tests {
         let another_test_struct2 = TestStruct2::from_json(&test_struct2_json);
         assert_eq!(test_struct2, another_test_struct2);
     }
+
+    #[test]
+    fn test_with_hooks() {
+        let hooked = WithHooks {
+            timeout: std::time::Duration::from_millis(1500),
+            payload: vec![0xde, 0xad, 0xbe, 0xef],
+        };
+        let hooked_json = hooked.to_json();
+        assert_eq!(hooked_json["timeout"], JsonNode::Number(1500.0));
+        assert_eq!(
+            hooked_json["payload"],
+            JsonNode::String("deadbeef".to_string())
+        );
+        assert_eq!(WithHooks::from_json(&hooked_json), hooked);
+
+        let delay = HookedEnum::Delay(std::time::Duration::from_millis(20));
+        assert_eq!(delay.to_json()["value"], JsonNode::Number(20.0));
+        assert_eq!(HookedEnum::from_json(&delay.to_json()), delay);
+
+        let blob = HookedEnum::Blob { bytes: vec![1, 2] };
+        assert_eq!(
+            blob.to_json()["value"]["bytes"],
+            JsonNode::String("0102".to_string())
+        );
+        assert_eq!(HookedEnum::from_json(&blob.to_json()), blob);
+    }
+
+    #[test]
+    fn test_fallible_hook() {
+        let dto = json!({"id": 7, "address": "10.0.0.1"});
+        assert_eq!(
+            CheckedDto::try_from_json(&dto),
+            Ok(CheckedDto {
+                id: 7,
+                address: std::net::Ipv4Addr::new(10, 0, 0, 1),
+            })
+        );
+
+        let bad_address = json!({"id": 7, "address": "10.0.0"});
+        let err = CheckedDto::try_from_json(&bad_address).unwrap_err();
+        assert_eq!(err.pointer(), "/address");
+        assert_eq!(
+            err.message(),
+            "Invalid address: invalid IPv4 address syntax"
+        );
+
+        let wrong_type = json!({"id": 7, "address": 10});
+        let err = CheckedDto::try_from_json(&wrong_type).unwrap_err();
+        assert_eq!(err.pointer(), "/address");
+    }
+
+    #[test]
+    fn test_deny_unknown_fields() {
+        let config = parse_json(r#"{"name": "svc", "retries": 3}"#).unwrap();
+        assert_eq!(
+            StrictConfig::try_from_json(&config),
+            Ok(StrictConfig {
+                name: "svc".to_string(),
+                retries: 3
+            })
+        );
+
+        let typo = parse_json(r#"{"nmae": "svc", "name": "svc", "retries": 3, "zzz": 1}"#).unwrap();
+        let err = StrictConfig::try_from_json(&typo).unwrap_err();
+        assert_eq!(
+            err.message(),
+            r#"Unknown fields "nmae" (did you mean "name"?), "zzz", expected one of "name", "retries""#
+        );
+
+        let nested = json!({"type": "Named", "value": {"level": 1, "levle": 2}});
+        let err = StrictEnum::try_from_json(&nested).unwrap_err();
+        assert_eq!(err.pointer(), "/value");
+        assert!(err.message().contains(r#""levle" (did you mean "level"?)"#));
+
+        let extra = json!({"field1": 1, "field2": 2, "field3": "x", "extra": 0});
+        assert!(TestStruct::try_from_json(&extra).is_ok());
+    }
+
+    #[test]
+    fn test_try_from_json_errors() {
+        let wrong_type =
+            json!({"field1": 42, "field2": {"field1": 1, "field2": "oops", "field3": "x"}});
+        let err = TestStruct2::try_from_json(&wrong_type).unwrap_err();
+        assert_eq!(err.pointer(), "/field2/field2");
+        assert_eq!(err.message(), "Cannot convert non-number type to number");
+
+        let missing = json!({"field1": 42});
+        let err = TestStruct2::try_from_json(&missing).unwrap_err();
+        assert_eq!(err.to_string(), "Missing field \"field2\"");
+
+        let short_tuple = json!([1, 2]);
+        let err = TpType::try_from_json(&short_tuple).unwrap_err();
+        assert_eq!(err.message(), "Missing element at index 2");
+
+        let bad_variant = json!({"type": "Variant9"});
+        let err = TestEnum::try_from_json(&bad_variant).unwrap_err();
+        assert_eq!(err.pointer(), "/type");
+
+        let bad_payload = json!({"type": "Variant3", "value": [1, "x"]});
+        let err = TestEnum::try_from_json(&bad_payload).unwrap_err();
+        assert_eq!(err.pointer(), "/value/1");
+    }
+
+    #[test]
+    fn test_unit_struct() {
+        assert_eq!(Marker.to_json(), JsonNode::Null);
+        assert_eq!(Marker::try_from_json(&JsonNode::Null), Ok(Marker));
+        assert_eq!(Marker::try_from_json(&json!({})), Ok(Marker));
+        assert_eq!(Marker::try_from_json(&json!({"extra": 1})), Ok(Marker));
+        assert!(Marker::try_from_json(&json!([])).is_err());
+
+        assert_eq!(StrictMarker::try_from_json(&json!({})), Ok(StrictMarker));
+        assert!(StrictMarker::try_from_json(&json!({"extra": 1})).is_err());
+    }
+
+    #[test]
+    fn test_transparent() {
+        let id = UserId(42);
+        assert_eq!(id.to_json(), JsonNode::Number(42.0));
+        assert_eq!(UserId::from_json(&id.to_json()), id);
+
+        let timeout = Timeout {
+            duration: std::time::Duration::from_millis(250),
+        };
+        assert_eq!(timeout.to_json(), JsonNode::Number(250.0));
+        assert_eq!(Timeout::from_json(&timeout.to_json()), timeout);
+
+        let err = UserId::try_from_json(&json!("42")).unwrap_err();
+        assert_eq!(err.pointer(), "");
+        assert_eq!(err.message(), "Cannot convert non-number type to number");
+    }
+
+    #[test]
+    fn test_separate_derives() {
+        let tags = vec!["a".to_string(), "b".to_string()];
+        let view = BorrowedView {
+            name: "view",
+            tags: &tags,
+        };
+        assert_eq!(view.to_json(), json!({"name": "view", "tags": ["a", "b"]}));
+
+        let dto = ReadOnlyDto::from_json(&json!({"id": 7, "address": "10.0.0.1"}));
+        assert_eq!(
+            dto,
+            ReadOnlyDto {
+                id: 7,
+                address: std::net::Ipv4Addr::new(10, 0, 0, 1)
+            }
+        );
+
+        let wrapper = Wrapper {
+            value: 1u8,
+            items: vec![2u8, 3u8],
+        };
+        assert_eq!(wrapper.to_json(), json!({"value": 1, "items": [2, 3]}));
+        assert_eq!(Wrapper::<u8>::from_json(&wrapper.to_json()), wrapper);
+    }
+
+    fn valid_sign_up() -> JsonNode {
+        json!({
+            "age": 30,
+            "username": "yi_ran",
+            "tags": ["a"],
+            "seats": 2,
+            "ratio": null
+        })
+    }
+
+    fn invalid_sign_up() -> JsonNode {
+        json!({
+            "age": 130,
+            "username": "Yi",
+            "tags": [],
+            "seats": 3,
+            "ratio": 0.25
+        })
+    }
+
+    #[test]
+    fn test_validation_accepts_valid_values() {
+        let valid = valid_sign_up();
+        assert_eq!(SignUp::try_from_json(&valid).unwrap().username, "yi_ran");
+    }
+
+    #[test]
+    fn test_validation_collects_violations() {
+        let err = SignUp::try_from_json(&invalid_sign_up()).unwrap_err();
+        let violations: Vec<(&str, &str)> = err
+            .violations()
+            .iter()
+            .map(|violation| (violation.pointer(), violation.message()))
+            .collect();
+        assert_eq!(
+            violations,
+            vec![
+                ("/age", "Value 130 is greater than the maximum 120"),
+                ("/username", "Length 2 is less than the minimum 3"),
+                (
+                    "/username",
+                    "Value \"Yi\" does not match the pattern \"^[a-z_]+$\""
+                ),
+                ("/tags", "Value must not be empty"),
+                ("/seats", "3 is not even"),
+                ("/ratio", "Value 0.25 is less than the minimum 0.5"),
+            ]
+        );
+        assert_eq!(err.message(), "6 validation errors");
+    }
+
+    #[test]
+    fn test_validation_in_arrays() {
+        let valid = valid_sign_up();
+        let invalid = invalid_sign_up();
+        let err = Vec::<SignUp>::try_from_json(&json!([#valid, #invalid])).unwrap_err();
+        assert_eq!(err.violations()[0].pointer(), "/1/age");
+        let err = Vec::<SignUp>::try_from_json(&json!([#invalid, #invalid])).unwrap_err();
+        assert_eq!(err.violations().len(), 12);
+        assert_eq!(err.violations()[0].pointer(), "/0/age");
+        assert_eq!(err.violations()[6].pointer(), "/1/age");
+    }
+
+    #[test]
+    fn test_validation_in_nested_structs() {
+        let invalid = invalid_sign_up();
+        let err = Team::try_from_json(&json!({"lead": #invalid, "name": ""})).unwrap_err();
+        let pointers: Vec<&str> = err.violations().iter().map(JsonError::pointer).collect();
+        assert_eq!(
+            pointers,
+            vec![
+                "/lead/age",
+                "/lead/username",
+                "/lead/username",
+                "/lead/tags",
+                "/lead/seats",
+                "/lead/ratio",
+                "/name"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validation_reports_decoding_errors() {
+        let err = Team::try_from_json(&json!({"lead": {"age": "old"}})).unwrap_err();
+        let messages: Vec<String> = err.violations().iter().map(JsonError::to_string).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "/lead/age: Cannot convert non-number type to number",
+                "/lead: Missing field \"username\"",
+                "/lead: Missing field \"tags\"",
+                "/lead: Missing field \"seats\"",
+                "/lead: Missing field \"ratio\"",
+                "Missing field \"name\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validation_without_violations() {
+        assert_eq!(
+            JsonError::from_violations(Vec::new()).to_string(),
+            "Validation failed"
+        );
+    }
+
+    #[test]
+    fn test_validation_in_enum_variants() {
+        let err =
+            Command::try_from_json(&json!({"type": "Rename", "value": "renamed"})).unwrap_err();
+        assert_eq!(err.pointer(), "/value");
+        assert_eq!(err.message(), "Length 7 is greater than the maximum 4");
+        let err =
+            Command::try_from_json(&json!({"type": "Move", "value": {"steps": 11}})).unwrap_err();
+        assert_eq!(err.pointer(), "/value/steps");
+    }
+
+    #[test]
+    fn test_json_schema_struct() {
+        let schema = schema_for::<Profile>();
+        assert_eq!(
+            schema["$schema"],
+            json!("https://json-schema.org/draft/2020-12/schema")
+        );
+        assert_eq!(schema["$ref"], json!("#/$defs/Profile"));
+        assert_eq!(
+            schema["$defs"]["Profile"],
+            json!({
+                "title": "Profile",
+                "description": "A user of the service.",
+                "type": "object",
+                "properties": {
+                    "name": {"type": "string", "maxLength": 32, "description": "Display name."},
+                    "age": {"anyOf": [{"type": "integer", "minimum": 0}, {"type": "null"}]},
+                    "role": {"$ref": "#/$defs/Role"}
+                },
+                "required": ["name", "age", "role"],
+                "additionalProperties": false
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_schema_enum() {
+        let schema = schema_for::<Profile>();
+        assert_eq!(
+            schema["$defs"]["Role"],
+            json!({
+                "title": "Role",
+                "oneOf": [
+                    {
+                        "type": "object",
+                        "properties": {"type": {"const": "Admin"}},
+                        "required": ["type"],
+                        "description": "Full access."
+                    },
+                    {
+                        "type": "object",
+                        "properties": {
+                            "type": {"const": "Member"},
+                            "value": {"type": "integer", "minimum": 0}
+                        },
+                        "required": ["type", "value"]
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_schema_recursive_type() {
+        let schema = schema_for::<TreeNode>();
+        assert_eq!(
+            schema["$defs"]["TreeNode"]["properties"]["children"],
+            json!({"type": "array", "items": {"$ref": "#/$defs/TreeNode"}})
+        );
+    }
+
+    #[test]
+    fn test_json_schema_generic_type() {
+        assert_eq!(
+            schema_for::<(bool, Wrapper<String>)>()["prefixItems"][1]["title"],
+            json!("Wrapper")
+        );
+    }
+
+    #[test]
+    fn test_json_schema_name_collisions() {
+        let schema = schema_for::<Settings>();
+        let properties = &schema["$defs"]["Settings"]["properties"];
+        assert_eq!(properties["billing"], json!({"$ref": "#/$defs/Config"}));
+        let JsonNode::String(shipping) = &properties["shipping"]["$ref"] else {
+            panic!("shipping is not a reference");
+        };
+        assert!(shipping.ends_with(".tests.shipping.Config"));
+        assert!(schema["$defs"]["Config"]["properties"].contains_key("currency"));
+        let shipping_schema = schema.pointer(&shipping["#".len()..]).unwrap();
+        assert!(shipping_schema["properties"].contains_key("express"));
+    }
+
+    #[test]
+    fn test_json_schema_opaque_field() {
+        let holder = HoldsOpaque { opaque: Opaque(7) };
+        assert_eq!(HoldsOpaque::from_json(&holder.to_json()), holder);
+    }
+
+    #[test]
+    fn test_schema_validator_for_type() {
+        let profile = Profile {
+            name: "A".to_string(),
+            age: None,
+            role: Role::Member(3),
+        };
+        let validator = SchemaValidator::for_type::<Profile>();
+        assert!(validator.is_valid(&profile.to_json()));
+        let mut profile_json = profile.to_json();
+        profile_json["role"] = json!({"type": "Owner"});
+        assert!(!validator.is_valid(&profile_json));
+    }
+
+    #[test]
+    fn test_json_macro_expressions() {
+        let name = "Yiran";
+        let scores = vec![3, 1, 2];
+        let key = "nickname";
+        let node = json!({"a": 1});
+
+        let json = json_object! {
+            "offset": -2,
+            "delta": -0.5,
+            "name": name,
+            "upper": name.to_uppercase(),
+            "count": scores.len(),
+            "sum": scores.iter().sum::<i32>(),
+            "scores": scores,
+            "nested": #node,
+            "wrapped": #(node.clone()),
+            #key: "Yi",
+            "empty": [],
+        };
+
+        assert_eq!(json["offset"], JsonNode::Number(-2.0));
+        assert_eq!(json["delta"], JsonNode::Number(-0.5));
+        assert_eq!(json["name"], "Yiran".to_json());
+        assert_eq!(json["upper"], "YIRAN".to_json());
+        assert_eq!(json["count"], 3.to_json());
+        assert_eq!(json["sum"], 6.to_json());
+        assert_eq!(json["scores"], json!([3, 1, 2]));
+        assert_eq!(json["nested"], node);
+        assert_eq!(json["wrapped"], node);
+        assert_eq!(json["nickname"], "Yi".to_json());
+        assert_eq!(json["empty"], JsonNode::Array(Vec::new()));
+
+        assert_eq!(
+            json_array![-1, 2 * 3, null, true,],
+            json!([-1, 6, null, true])
+        );
+        assert_eq!(json!(name,), "Yiran".to_json());
+    }
+
+    #[test]
+    fn test_json_macro_spread_and_conditions() {
+        let base = json!({"model": "m1", "stream": false});
+        let extra: Option<JsonNode> = None;
+        let tags = vec!["a", "b"];
+        let verbose = true;
+        let quiet = false;
+
+        let request = json!({
+            ..#(&base),
+            "stream": true,
+            "debug": #verbose if verbose,
+            "trace": 1 if quiet,
+            ..extra,
+            #[cfg(test)]
+            "test": true,
+            #[cfg(not(test))]
+            "release": true,
+        });
+        assert_eq!(
+            request,
+            json!({"model": "m1", "stream": true, "debug": true, "test": true})
+        );
+
+        let body = json!({"messages": [..#(&tags), "c" if verbose, ..#(&base) if quiet]});
+        assert_eq!(body, json!({"messages": ["a", "b", "c"]}));
+
+        let moved = json_array![0, ..#(json!([1, 2])), ..[3, -4]];
+        assert_eq!(moved, json!([0, 1, 2, 3, -4]));
+
+        let merged = json_object! { ..#base, "model": "m2" };
+        assert_eq!(merged, json!({"model": "m2", "stream": false}));
+    }
+
+    #[test]
+    fn test_compile_time_json() {
+        let defaults = include_json!("tests/fixtures/defaults.json");
+        assert_eq!(
+            defaults,
+            json!({
+                "name": "service",
+                "port": 8080,
+                "ratio": -0.25,
+                "enabled": true,
+                "tags": ["a", "b\u{e9}", "\u{1f600}"],
+                "proxy": null,
+                "limits": {"burst": 1000},
+            })
+        );
+
+        let inline = json_str!(r#"[{"a": "line\nbreak \"quoted\""}, [], {}, 0, -0.5e-1]"#);
+        assert_eq!(
+            inline,
+            json!([{"a": "line\nbreak \"quoted\""}, [], {}, 0, -0.05])
+        );
+        assert_eq!(parse_json(&defaults.dump(0, false)).unwrap(), defaults);
+    }
+
+    #[test]
+    fn test_json_macro_number_literals() {
+        let json = json!({
+            "max_safe": 9007199254740992,
+            "min_safe": -9007199254740992,
+            "large": 1_000_000_000_000,
+            "suffixed": 42u64,
+            "small": -3i8,
+            "hex": 0xff,
+            "single": 1.5f32,
+            "float_suffix": 2f64,
+            "exponent": -1e-3,
+        });
+        assert_eq!(json["max_safe"], JsonNode::Number(9007199254740992.0));
+        assert_eq!(json["min_safe"], JsonNode::Number(-9007199254740992.0));
+        assert_eq!(json["large"], JsonNode::Number(1e12));
+        assert_eq!(json["suffixed"], JsonNode::Number(42.0));
+        assert_eq!(json["small"], JsonNode::Number(-3.0));
+        assert_eq!(json["hex"], JsonNode::Number(255.0));
+        assert_eq!(json["single"], JsonNode::Number(1.5));
+        assert_eq!(json["float_suffix"], JsonNode::Number(2.0));
+        assert_eq!(json["exponent"], JsonNode::Number(-0.001));
+
+        assert_eq!(
+            json_str!("[9007199254740992, -0, 1e2, 0.25]"),
+            json!([9007199254740992u64, 0, 100, 0.25])
+        );
+    }
 }
 
 #[derive(Debug, Clone, Default, yiran_json::JsonType, PartialEq)]
@@ -0,0 +1,323 @@
+use proc_macro_essentials::{proc_macro2, quote, syn};
+use quote::{quote, ToTokens};
+use syn::{Attribute, Expr, Field, LitStr, Path};
+
+/// Options collected from the `#[json(...)]` attributes of a single field.
+#[derive(Default)]
+pub struct FieldAttributes {
+    to_json_with: Option<Path>,
+    from_json_with: Option<FromJsonWith>,
+    as_pairs: Option<Path>,
+    validators: Vec<Validator>,
+}
+
+/// A function decoding a field, given with `from_json_with` (returning the field) or
+/// `try_from_json_with` (returning a `Result<_, JsonError>`).
+enum FromJsonWith {
+    Plain(Path),
+    Fallible(Path),
+}
+
+/// A check from `#[json(...)]` run on a field after it has been decoded.
+enum Validator {
+    Range {
+        min: Option<Expr>,
+        max: Option<Expr>,
+    },
+    Length {
+        min: Option<Expr>,
+        max: Option<Expr>,
+    },
+    Regex(LitStr),
+    NonEmpty,
+    Custom(Path),
+}
+
+impl FieldAttributes {
+    pub fn from_field(field: &Field) -> syn::Result<Self> {
+        let mut attributes = FieldAttributes::default();
+
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("json"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("with") {
+                    let module: Path = meta.value()?.parse::<LitStr>()?.parse()?;
+                    let mut to_json_with = module.clone();
+                    to_json_with.segments.push(syn::parse_quote!(to_json));
+                    let mut from_json_with = module;
+                    from_json_with.segments.push(syn::parse_quote!(from_json));
+                    set_once(&meta, &mut attributes.to_json_with, to_json_with)?;
+                    set_once(
+                        &meta,
+                        &mut attributes.from_json_with,
+                        FromJsonWith::Plain(from_json_with),
+                    )
+                } else if meta.path.is_ident("to_json_with") {
+                    let function: Path = meta.value()?.parse::<LitStr>()?.parse()?;
+                    set_once(&meta, &mut attributes.to_json_with, function)
+                } else if meta.path.is_ident("from_json_with") {
+                    let function: Path = meta.value()?.parse::<LitStr>()?.parse()?;
+                    set_once(
+                        &meta,
+                        &mut attributes.from_json_with,
+                        FromJsonWith::Plain(function),
+                    )
+                } else if meta.path.is_ident("try_from_json_with") {
+                    let function: Path = meta.value()?.parse::<LitStr>()?.parse()?;
+                    set_once(
+                        &meta,
+                        &mut attributes.from_json_with,
+                        FromJsonWith::Fallible(function),
+                    )
+                } else if meta.path.is_ident("as_pairs") {
+                    attributes.as_pairs = Some(meta.path);
+                    Ok(())
+                } else if meta.path.is_ident("range") {
+                    let (min, max) = parse_bounds(&meta)?;
+                    attributes.validators.push(Validator::Range { min, max });
+                    Ok(())
+                } else if meta.path.is_ident("length") {
+                    let (min, max) = parse_bounds(&meta)?;
+                    attributes.validators.push(Validator::Length { min, max });
+                    Ok(())
+                } else if meta.path.is_ident("regex") {
+                    let pattern: LitStr = meta.value()?.parse()?;
+                    if let Err(err) = regex::Regex::new(&pattern.value()) {
+                        return Err(syn::Error::new_spanned(
+                            &pattern,
+                            format!("invalid regex: {}", err),
+                        ));
+                    }
+                    attributes.validators.push(Validator::Regex(pattern));
+                    Ok(())
+                } else if meta.path.is_ident("non_empty") {
+                    attributes.validators.push(Validator::NonEmpty);
+                    Ok(())
+                } else if meta.path.is_ident("custom") {
+                    let function: Path = meta.value()?.parse::<LitStr>()?.parse()?;
+                    attributes.validators.push(Validator::Custom(function));
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown json field attribute"))
+                }
+            })?;
+        }
+
+        if let Some(as_pairs) = &attributes.as_pairs {
+            if attributes.to_json_with.is_some() || attributes.from_json_with.is_some() {
+                return Err(syn::Error::new_spanned(
+                    as_pairs,
+                    "as_pairs cannot be combined with conversion functions",
+                ));
+            }
+        }
+
+        Ok(attributes)
+    }
+
+    pub fn from_fields<'a>(
+        fields: impl IntoIterator<Item = &'a Field>,
+    ) -> syn::Result<Vec<FieldAttributes>> {
+        fields
+            .into_iter()
+            .map(FieldAttributes::from_field)
+            .collect()
+    }
+
+    /// Expression converting `value` (a reference to the field) into a `JsonNode`.
+    pub fn to_json(
+        &self,
+        crate_name: &impl ToTokens,
+        value: proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        match &self.to_json_with {
+            Some(function) => quote! { #function(#value) },
+            None if self.as_pairs.is_some() => quote! { #crate_name::map_to_json_pairs(#value) },
+            None => quote! { #crate_name::ToJson::to_json(#value) },
+        }
+    }
+
+    /// Expression building the field of type `field_type` from `json` (a `&JsonNode`),
+    /// evaluating to a `Result<_, JsonError>`.
+    pub fn try_from_json(
+        &self,
+        crate_name: &impl ToTokens,
+        field_type: &syn::Type,
+        json: proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        match &self.from_json_with {
+            Some(FromJsonWith::Plain(function)) => {
+                quote! { Ok::<_, #crate_name::JsonError>(#function(#json)) }
+            }
+            Some(FromJsonWith::Fallible(function)) => {
+                quote! {
+                    {
+                        let result: Result<_, #crate_name::JsonError> = #function(#json);
+                        result
+                    }
+                }
+            }
+            None if self.as_pairs.is_some() => {
+                quote! { #crate_name::map_try_from_json_pairs::<#field_type, _, _>(#json) }
+            }
+            None => quote! { <#field_type as #crate_name::FromJson>::try_from_json(#json) },
+        }
+    }
+
+    /// Expression building the JSON Schema of the field, with its validators as
+    /// constraints. Fields with conversion functions accept any JSON.
+    pub fn schema(
+        &self,
+        crate_name: &impl ToTokens,
+        field_type: &syn::Type,
+    ) -> proc_macro2::TokenStream {
+        let mut schema = if self.to_json_with.is_some() || self.from_json_with.is_some() {
+            quote! { #crate_name::JsonNode::Boolean(true) }
+        } else if self.as_pairs.is_some() {
+            quote! { #crate_name::schema_for_pairs::<#field_type, _, _>(defs) }
+        } else {
+            quote! { <#field_type as #crate_name::JsonSchema>::json_schema(defs) }
+        };
+        for validator in &self.validators {
+            schema = match validator {
+                Validator::Range { min, max } => {
+                    let min = optional_bound(min, quote! { f64 });
+                    let max = optional_bound(max, quote! { f64 });
+                    quote! { #crate_name::schema_range(#schema, #min, #max) }
+                }
+                Validator::Length { min, max } => {
+                    let min = optional_bound(min, quote! { usize });
+                    let max = optional_bound(max, quote! { usize });
+                    quote! { #crate_name::schema_length(#schema, #min, #max) }
+                }
+                Validator::Regex(pattern) => {
+                    quote! { #crate_name::schema_pattern(#schema, #pattern) }
+                }
+                Validator::NonEmpty => {
+                    quote! { #crate_name::schema_length(#schema, Some(1), None) }
+                }
+                Validator::Custom(_) => schema,
+            };
+        }
+        schema
+    }
+
+    pub fn has_validators(&self) -> bool {
+        !self.validators.is_empty()
+    }
+
+    /// Statements running the validators on `value` (a reference to the decoded field),
+    /// pushing each failure onto `violations` with `pointer` (a chain of `with_key` /
+    /// `with_index` calls) applied.
+    pub fn validate(
+        &self,
+        crate_name: &impl ToTokens,
+        value: &proc_macro2::TokenStream,
+        violations: &proc_macro2::TokenStream,
+        pointer: &proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let checks = self.validators.iter().map(|validator| match validator {
+            Validator::Range { min, max } => {
+                let min = optional_bound(min, quote! { f64 });
+                let max = optional_bound(max, quote! { f64 });
+                quote! { #crate_name::validate_range(#value, #min, #max) }
+            }
+            Validator::Length { min, max } => {
+                let min = optional_bound(min, quote! { usize });
+                let max = optional_bound(max, quote! { usize });
+                quote! { #crate_name::validate_length(#value, #min, #max) }
+            }
+            Validator::Regex(pattern) => quote! {
+                {
+                    static PATTERN: #crate_name::Pattern = #crate_name::Pattern::new(#pattern);
+                    #crate_name::validate_pattern(#value, &PATTERN)
+                }
+            },
+            Validator::NonEmpty => quote! { #crate_name::validate_non_empty(#value) },
+            Validator::Custom(function) => quote! {
+                #function(#value).map_err(#crate_name::JsonError::new)
+            },
+        });
+        quote! {
+            #(
+                if let Err(err) = #checks {
+                    #violations.push(err #pointer);
+                }
+            )*
+        }
+    }
+}
+
+fn parse_bounds(meta: &syn::meta::ParseNestedMeta) -> syn::Result<(Option<Expr>, Option<Expr>)> {
+    let mut min = None;
+    let mut max = None;
+    meta.parse_nested_meta(|bound| {
+        let slot = if bound.path.is_ident("min") {
+            &mut min
+        } else if bound.path.is_ident("max") {
+            &mut max
+        } else {
+            return Err(bound.error("expected `min` or `max`"));
+        };
+        if slot.is_some() {
+            return Err(bound.error("bound is specified more than once"));
+        }
+        *slot = Some(bound.value()?.parse()?);
+        Ok(())
+    })?;
+    if min.is_none() && max.is_none() {
+        return Err(meta.error("expected `min` and/or `max`"));
+    }
+    Ok((min, max))
+}
+
+fn optional_bound(bound: &Option<Expr>, ty: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    match bound {
+        Some(bound) => quote! { Some((#bound) as #ty) },
+        None => quote! { None },
+    }
+}
+
+/// Options collected from the `#[json(...)]` attributes of a struct or enum.
+#[derive(Default)]
+pub struct ContainerAttributes {
+    pub deny_unknown_fields: Option<Path>,
+    pub transparent: Option<Path>,
+}
+
+impl ContainerAttributes {
+    pub fn from_attributes(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut attributes = ContainerAttributes::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("json")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("deny_unknown_fields") {
+                    attributes.deny_unknown_fields = Some(meta.path);
+                    Ok(())
+                } else if meta.path.is_ident("transparent") {
+                    attributes.transparent = Some(meta.path);
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown json container attribute"))
+                }
+            })?;
+        }
+
+        Ok(attributes)
+    }
+}
+
+fn set_once<T>(
+    meta: &syn::meta::ParseNestedMeta,
+    slot: &mut Option<T>,
+    value: T,
+) -> syn::Result<()> {
+    if slot.is_some() {
+        return Err(meta.error("conversion function is specified more than once"));
+    }
+    *slot = Some(value);
+    Ok(())
+}
@@ -0,0 +1,250 @@
+use crate::{MacroJsonEntry, MacroJsonNode, MacroJsonNumber, StringLiteralOrTokenStream};
+
+/// A syntax error in JSON text, with the 1-based line and column where it was found.
+pub struct JsonLiteralError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Parses JSON text into the same tree the `json!` macro builds from tokens, so it can
+/// be expanded with `generate_json_call_site`.
+pub fn parse_json_literal(text: &str) -> Result<MacroJsonNode, JsonLiteralError> {
+    let mut parser = JsonLiteralParser { text, pos: 0 };
+    parser.skip_whitespace();
+    let node = parser.parse_value(0)?;
+    parser.skip_whitespace();
+    if parser.pos < text.len() {
+        return Err(parser.error("Unexpected trailing characters"));
+    }
+    Ok(node)
+}
+
+/// Nesting deeper than this is rejected rather than overflowing the compiler's stack.
+const MAX_DEPTH: usize = 128;
+
+struct JsonLiteralParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl JsonLiteralParser<'_> {
+    fn error(&self, message: impl Into<String>) -> JsonLiteralError {
+        let before = &self.text[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+        JsonLiteralError {
+            message: message.into(),
+            line,
+            column,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<MacroJsonNode, JsonLiteralError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("JSON is nested too deeply"));
+        }
+        match self.peek() {
+            Some('{') => self.parse_object(depth),
+            Some('[') => self.parse_array(depth),
+            Some('"') => Ok(MacroJsonNode::String(self.parse_string()?)),
+            Some('-' | '0'..='9') => self.parse_number(),
+            Some('t') => self.parse_keyword("true", MacroJsonNode::Boolean(true)),
+            Some('f') => self.parse_keyword("false", MacroJsonNode::Boolean(false)),
+            Some('n') => self.parse_keyword("null", MacroJsonNode::Null),
+            Some(c) => Err(self.error(format!("Unexpected character '{}'", c))),
+            None => Err(self.error("Unexpected end of input")),
+        }
+    }
+
+    fn parse_keyword(
+        &mut self,
+        keyword: &str,
+        node: MacroJsonNode,
+    ) -> Result<MacroJsonNode, JsonLiteralError> {
+        if self.text[self.pos..].starts_with(keyword) {
+            self.pos += keyword.len();
+            Ok(node)
+        } else {
+            Err(self.error(format!("Expected '{}'", keyword)))
+        }
+    }
+
+    fn parse_object(&mut self, depth: usize) -> Result<MacroJsonNode, JsonLiteralError> {
+        self.bump();
+        self.skip_whitespace();
+        let mut members = Vec::new();
+        if self.eat('}') {
+            return Ok(MacroJsonNode::Object(members));
+        }
+        loop {
+            if self.peek() != Some('"') {
+                return Err(self.error("Expected a string key"));
+            }
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            if !self.eat(':') {
+                return Err(self.error("Expected ':' after the key"));
+            }
+            self.skip_whitespace();
+            let value = self.parse_value(depth + 1)?;
+            members.push(MacroJsonEntry::single((
+                StringLiteralOrTokenStream::StringLiteral(key),
+                value,
+            )));
+            self.skip_whitespace();
+            if self.eat('}') {
+                return Ok(MacroJsonNode::Object(members));
+            }
+            if !self.eat(',') {
+                return Err(self.error("Expected ',' or '}'"));
+            }
+            self.skip_whitespace();
+        }
+    }
+
+    fn parse_array(&mut self, depth: usize) -> Result<MacroJsonNode, JsonLiteralError> {
+        self.bump();
+        self.skip_whitespace();
+        let mut elements = Vec::new();
+        if self.eat(']') {
+            return Ok(MacroJsonNode::Array(elements));
+        }
+        loop {
+            elements.push(MacroJsonEntry::single(self.parse_value(depth + 1)?));
+            self.skip_whitespace();
+            if self.eat(']') {
+                return Ok(MacroJsonNode::Array(elements));
+            }
+            if !self.eat(',') {
+                return Err(self.error("Expected ',' or ']'"));
+            }
+            self.skip_whitespace();
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonLiteralError> {
+        self.bump();
+        let mut value = String::new();
+        loop {
+            let start = self.pos;
+            match self.bump() {
+                None => return Err(self.error("Unterminated string")),
+                Some('"') => return Ok(value),
+                Some('\\') => value.push(self.parse_escape()?),
+                Some(c) if c < ' ' => {
+                    self.pos = start;
+                    return Err(self.error("Control characters must be escaped in strings"));
+                }
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<char, JsonLiteralError> {
+        match self.bump() {
+            Some('"') => Ok('"'),
+            Some('\\') => Ok('\\'),
+            Some('/') => Ok('/'),
+            Some('b') => Ok('\u{8}'),
+            Some('f') => Ok('\u{c}'),
+            Some('n') => Ok('\n'),
+            Some('r') => Ok('\r'),
+            Some('t') => Ok('\t'),
+            Some('u') => {
+                let high = self.parse_hex4()?;
+                if !(0xD800..0xDC00).contains(&high) {
+                    return char::from_u32(high).ok_or_else(|| self.error("Unpaired surrogate"));
+                }
+                if !self.text[self.pos..].starts_with("\\u") {
+                    return Err(self.error("Unpaired surrogate"));
+                }
+                self.pos += 2;
+                let low = self.parse_hex4()?;
+                if !(0xDC00..0xE000).contains(&low) {
+                    return Err(self.error("Unpaired surrogate"));
+                }
+                let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                char::from_u32(code).ok_or_else(|| self.error("Invalid unicode escape"))
+            }
+            _ => Err(self.error("Invalid escape sequence")),
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, JsonLiteralError> {
+        let digits = self.text[self.pos..]
+            .get(..4)
+            .filter(|digits| digits.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("Expected 4 hex digits"))?;
+        self.pos += 4;
+        Ok(u32::from_str_radix(digits, 16).unwrap())
+    }
+
+    fn parse_number(&mut self) -> Result<MacroJsonNode, JsonLiteralError> {
+        let start = self.pos;
+        let negative = self.eat('-');
+        let digits_start = self.pos;
+        let mut is_integer = true;
+        if !self.eat('0') && !self.eat_digits() {
+            return Err(self.error("Expected a digit"));
+        }
+        if self.eat('.') {
+            is_integer = false;
+            if !self.eat_digits() {
+                return Err(self.error("Expected a digit after the decimal point"));
+            }
+        }
+        if self.eat('e') || self.eat('E') {
+            is_integer = false;
+            if !self.eat('+') {
+                self.eat('-');
+            }
+            if !self.eat_digits() {
+                return Err(self.error("Expected a digit in the exponent"));
+            }
+        }
+        let digits = &self.text[digits_start..self.pos];
+        let number = if is_integer {
+            MacroJsonNumber::integer(digits, negative, "")
+        } else {
+            MacroJsonNumber::float(digits, negative, "")
+        };
+        number.map(MacroJsonNode::Number).map_err(|message| {
+            self.pos = start;
+            self.error(message)
+        })
+    }
+
+    fn eat_digits(&mut self) -> bool {
+        let start = self.pos;
+        while matches!(self.peek(), Some('0'..='9')) {
+            self.pos += 1;
+        }
+        self.pos > start
+    }
+}
@@ -0,0 +1,161 @@
+use proc_macro_essentials::utils::get_call_site_crate_name;
+use proc_macro_essentials::{proc_macro2, quote, syn};
+use quote::{quote, ToTokens};
+use syn::{Attribute, Data, DeriveInput, Fields};
+
+use crate::field_key;
+use crate::json_attributes::{ContainerAttributes, FieldAttributes};
+
+/// Body of the `JsonSchema` impl for a derive input. Non-generic types are stored in
+/// `$defs` under their name; generic types are inlined.
+pub fn json_schema(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let crate_name = get_call_site_crate_name("json");
+    let container_attributes = ContainerAttributes::from_attributes(&input.attrs)?;
+    let deny_unknown_fields = container_attributes.deny_unknown_fields.is_some();
+
+    let schema = match &input.data {
+        Data::Struct(data_struct) if container_attributes.transparent.is_some() => {
+            let field = data_struct.fields.iter().next();
+            match field {
+                Some(field) => FieldAttributes::from_field(field)?.schema(&crate_name, &field.ty),
+                None => quote! { #crate_name::JsonNode::Boolean(true) },
+            }
+        }
+        Data::Struct(data_struct) => {
+            // Unit structs are written as null but also accept an object.
+            fields_schema(&crate_name, &data_struct.fields, deny_unknown_fields)?.unwrap_or_else(
+                || {
+                    quote! {
+                        #crate_name::schema_one_of(vec![
+                            <() as #crate_name::JsonSchema>::json_schema(defs),
+                            #crate_name::schema_for_object(Vec::new(), #deny_unknown_fields),
+                        ])
+                    }
+                },
+            )
+        }
+        Data::Enum(data_enum) => {
+            let variants = data_enum
+                .variants
+                .iter()
+                .map(|variant| {
+                    let variant_name = variant.ident.to_string();
+                    let value =
+                        match fields_schema(&crate_name, &variant.fields, deny_unknown_fields)? {
+                            Some(value) => quote! { Some(#value) },
+                            None => quote! { None },
+                        };
+                    let schema = quote! {
+                        #crate_name::schema_for_variant(#variant_name, #value, #deny_unknown_fields)
+                    };
+                    Ok(annotate(&crate_name, schema, "description", &variant.attrs))
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote! {
+                #crate_name::schema_one_of(vec![#(#variants),*])
+            }
+        }
+        Data::Union(data_union) => {
+            return Err(syn::Error::new_spanned(
+                data_union.union_token,
+                "Unions are unsafe, please use enum instead",
+            ))
+        }
+    };
+
+    let name = input.ident.to_string();
+    let schema = quote! {
+        #crate_name::schema_annotate(#schema, "title", #name)
+    };
+    let schema = annotate(&crate_name, schema, "description", &input.attrs);
+    let schema = if input.generics.type_params().next().is_none() {
+        quote! { defs.reference::<Self>(#name, |defs| #schema) }
+    } else {
+        schema
+    };
+
+    Ok(quote! {
+        fn json_schema(defs: &mut #crate_name::SchemaDefinitions) -> #crate_name::JsonNode {
+            #schema
+        }
+    })
+}
+
+/// Schema of the fields of a struct or of an enum variant's payload, or `None` if
+/// there are no fields to describe.
+fn fields_schema(
+    crate_name: &impl ToTokens,
+    fields: &Fields,
+    deny_unknown_fields: bool,
+) -> syn::Result<Option<proc_macro2::TokenStream>> {
+    let field_attributes = FieldAttributes::from_fields(fields)?;
+    let field_schemas: Vec<proc_macro2::TokenStream> = fields
+        .iter()
+        .zip(&field_attributes)
+        .map(|(field, attributes)| {
+            let schema = attributes.schema(crate_name, &field.ty);
+            annotate(crate_name, schema, "description", &field.attrs)
+        })
+        .collect();
+
+    Ok(match fields {
+        Fields::Named(_) => {
+            let keys = fields.iter().map(field_key);
+            Some(quote! {
+                #crate_name::schema_for_object(
+                    vec![#((#keys, #field_schemas)),*],
+                    #deny_unknown_fields,
+                )
+            })
+        }
+        Fields::Unnamed(_) if field_schemas.len() == 1 => Some(field_schemas[0].clone()),
+        Fields::Unnamed(_) if !field_schemas.is_empty() => Some(quote! {
+            #crate_name::schema_for_tuple(vec![#(#field_schemas),*])
+        }),
+        Fields::Unnamed(_) | Fields::Unit => None,
+    })
+}
+
+/// Adds the doc comment in `attrs`, if any, to `schema` under `keyword`.
+fn annotate(
+    crate_name: &impl ToTokens,
+    schema: proc_macro2::TokenStream,
+    keyword: &str,
+    attrs: &[Attribute],
+) -> proc_macro2::TokenStream {
+    match doc_comment(attrs) {
+        Some(doc) => quote! { #crate_name::schema_annotate(#schema, #keyword, #doc) },
+        None => schema,
+    }
+}
+
+/// The text of the `///` comments in `attrs`, one line per attribute.
+fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(text),
+                        ..
+                    }),
+                ..
+            }) => Some(text.value()),
+            _ => None,
+        })
+        .map(|line| {
+            line.strip_prefix(' ')
+                .unwrap_or(&line)
+                .trim_end()
+                .to_string()
+        })
+        .collect();
+    let doc = lines.join("\n").trim().to_string();
+    if doc.is_empty() {
+        None
+    } else {
+        Some(doc)
+    }
+}
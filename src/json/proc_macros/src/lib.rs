@@ -4,209 +4,394 @@ use proc_macro_essentials::syn::parse::{Parse, ParseStream};
 use proc_macro_essentials::{proc_macro2, quote, syn};
 use quote::{format_ident, quote};
 use std::fmt::format;
+use syn::ext::IdentExt;
+use syn::punctuated::Punctuated;
+use syn::token::Comma;
 use syn::LitInt;
-use syn::{parse_macro_input, Data, DeriveInput, Fields};
+use syn::{parse_macro_input, Data, DeriveInput, Field, Fields, Variant};
 
 use proc_macro_essentials::utils::get_call_site_crate_name;
 
-fn json_struct(input: DeriveInput) -> proc_macro2::TokenStream {
+mod json_attributes;
+mod json_literal;
+mod json_schema;
+
+use json_attributes::{ContainerAttributes, FieldAttributes};
+
+fn json_struct(
+    input: &DeriveInput,
+    container_attributes: &ContainerAttributes,
+    fields: &Punctuated<Field, Comma>,
+) -> syn::Result<JsonImpls> {
     let crate_name = get_call_site_crate_name("json");
     let name = &input.ident;
-    let fields = if let Data::Struct(data_struct) = &input.data {
-        if let Fields::Named(fields_named) = &data_struct.fields {
-            &fields_named.named
-        } else {
-            return json_tuple(input);
-        }
-    } else {
-        unimplemented!()
-    };
+    let field_attributes = FieldAttributes::from_fields(fields)?;
 
-    let to_json_fields = fields.iter().map(|field| {
-        let field_name = &field.ident;
-        quote! {
-            json[stringify!(#field_name)] = self.#field_name.to_json();
-        }
-    });
+    let field_keys: Vec<String> = fields.iter().map(field_key).collect();
 
-    let from_json_fields = fields.iter().map(|field| {
-        let field_name = &field.ident;
-        let field_type = &field.ty;
-        quote! {
-            #field_name: #field_type::from_json(&json[stringify!(#field_name)]),
-        }
-    });
+    let to_json_fields =
+        fields
+            .iter()
+            .zip(&field_attributes)
+            .zip(&field_keys)
+            .map(|((field, attributes), key)| {
+                let field_name = &field.ident;
+                let value = attributes.to_json(&crate_name, quote! { &self.#field_name });
+                quote! {
+                    json[#key] = #value;
+                }
+            });
 
-    let expanded: proc_macro2::TokenStream = quote! {
-        impl #crate_name::FromJson for #name {
-            fn from_json(json: &#crate_name::JsonNode) -> Self {
-                Self{#(#from_json_fields)*}
-            }
-        }
+    let (decode_fields, locals) = decode_and_validate(
+        &crate_name,
+        fields
+            .iter()
+            .zip(&field_attributes)
+            .zip(&field_keys)
+            .map(|((field, attributes), key)| {
+                let value =
+                    decode_object_member(&crate_name, attributes, &field.ty, quote! { json }, key);
+                (attributes, value, quote! { .with_key(#key) })
+            }),
+    );
+    let field_names = fields.iter().map(|field| &field.ident);
+
+    let check_object = check_object(&crate_name, name, quote! { json });
+    let check_unknown_fields = if container_attributes.deny_unknown_fields.is_some() {
+        check_unknown_fields(&crate_name, quote! { json }, &field_keys)
+    } else {
+        quote! {}
+    };
 
-        impl #crate_name::ToJson for #name {
+    Ok(JsonImpls {
+        from_json: quote! {
+            fn try_from_json(json: &#crate_name::JsonNode) -> Result<Self, #crate_name::JsonError> {
+                #check_object
+                #check_unknown_fields
+                #decode_fields
+                Ok(Self{#(#field_names: #locals),*})
+            }
+        },
+        to_json: quote! {
             fn to_json(&self) -> #crate_name::JsonNode {
                 let mut json = #crate_name::JsonNode::Object(std::collections::HashMap::new());
                 #(#to_json_fields)*
                 json
             }
-        }
-    };
-
-    proc_macro2::TokenStream::from(expanded)
+        },
+    })
 }
 
-fn json_tuple(input: DeriveInput) -> proc_macro2::TokenStream {
+fn json_tuple(
+    input: &DeriveInput,
+    container_attributes: &ContainerAttributes,
+    fields: &Punctuated<Field, Comma>,
+) -> syn::Result<JsonImpls> {
     let crate_name = get_call_site_crate_name("json");
     let name = &input.ident;
-    let fields = if let Data::Struct(data_struct) = &input.data {
-        if let Fields::Unnamed(fields_unnamed) = &data_struct.fields {
-            &fields_unnamed.unnamed
-        } else {
-            return json_struct(input);
-        }
-    } else {
-        unimplemented!()
-    };
+    if let Some(deny_unknown_fields) = &container_attributes.deny_unknown_fields {
+        return Err(syn::Error::new_spanned(
+            deny_unknown_fields,
+            "deny_unknown_fields only applies to structs with named fields",
+        ));
+    }
+    let field_attributes = FieldAttributes::from_fields(fields)?;
 
-    let to_json_fields = fields.iter().enumerate().map(|(idx, _)| {
-        let idx_lit = LitInt::new(&idx.to_string(), proc_macro2::Span::call_site());
-        quote! {
-            json.push(self.#idx_lit.to_json());
-        }
-    });
+    let to_json_fields = field_attributes
+        .iter()
+        .enumerate()
+        .map(|(idx, attributes)| {
+            let idx_lit = LitInt::new(&idx.to_string(), proc_macro2::Span::call_site());
+            let value = attributes.to_json(&crate_name, quote! { &self.#idx_lit });
+            quote! {
+                json.push(#value);
+            }
+        });
 
-    let from_json_fields = fields.iter().enumerate().map(|(idx, field)| {
-        let field_type = &field.ty;
-        let idx_lit = LitInt::new(&idx.to_string(), proc_macro2::Span::call_site());
-        quote! {
-            #idx_lit : #field_type::from_json(&json[#idx_lit]),
-        }
-    });
+    let (decode_fields, locals) = decode_and_validate(
+        &crate_name,
+        fields
+            .iter()
+            .zip(&field_attributes)
+            .enumerate()
+            .map(|(idx, (field, attributes))| {
+                let value =
+                    decode_array_element(&crate_name, attributes, &field.ty, quote! { json }, idx);
+                (attributes, value, quote! { .with_index(#idx) })
+            }),
+    );
+    let field_indices =
+        (0..fields.len()).map(|idx| LitInt::new(&idx.to_string(), proc_macro2::Span::call_site()));
 
+    let check_array = check_array(&crate_name, name, quote! { json });
     let fields_len = fields.len();
 
-    let expanded = quote! {
-        impl #crate_name::FromJson for #name {
-            fn from_json(json: &#crate_name::JsonNode) -> Self {
-                Self{#(#from_json_fields)*}
+    Ok(JsonImpls {
+        from_json: quote! {
+            fn try_from_json(json: &#crate_name::JsonNode) -> Result<Self, #crate_name::JsonError> {
+                #check_array
+                #decode_fields
+                Ok(Self{#(#field_indices: #locals),*})
             }
-        }
-
-        impl #crate_name::ToJson for #name {
+        },
+        to_json: quote! {
             fn to_json(&self) -> #crate_name::JsonNode {
                 let mut json = #crate_name::JsonNode::Array(std::vec::Vec::with_capacity(#fields_len));
                 #(#to_json_fields)*
                 json
             }
-        }
-
-    };
-
-    proc_macro2::TokenStream::from(expanded)
+        },
+    })
 }
 
-fn json_enum(input: DeriveInput) -> proc_macro2::TokenStream {
+fn json_unit(
+    input: &DeriveInput,
+    container_attributes: &ContainerAttributes,
+) -> syn::Result<JsonImpls> {
     let crate_name = get_call_site_crate_name("json");
     let name = &input.ident;
-    let variants = if let Data::Enum(data_enum) = &input.data {
-        &data_enum.variants
+    let message = format!("Cannot convert non-null type to {}", name);
+    let check_unknown_fields = if container_attributes.deny_unknown_fields.is_some() {
+        check_unknown_fields(&crate_name, quote! { json }, &[])
     } else {
-        panic!("Only enum is supported");
+        quote! {}
     };
 
-    let from_json_variants = variants.iter().map(|variant| {
-        let variant_name = &variant.ident;
-        let variant_name_str = variant_name.to_string();
-
-        let fields = if let Fields::Unnamed(fields_unnamed) = &variant.fields {
-            &fields_unnamed.unnamed
-        } else if let Fields::Unit = &variant.fields {
-            return quote! {
-                #variant_name_str => {#name::#variant_name}
-            };
-        } else if let Fields::Named(fields_named) = &variant.fields {
-            let named_fields_init_quotes = fields_named.named.iter().map(|field| {
-                let field_name = field.ident.as_ref().unwrap();
-                let field_type = &field.ty;
-                quote! {
-                    #field_name: #field_type::from_json(&json["value"][stringify!(#field_name)])
+    Ok(JsonImpls {
+        from_json: quote! {
+            fn try_from_json(json: &#crate_name::JsonNode) -> Result<Self, #crate_name::JsonError> {
+                match json {
+                    #crate_name::JsonNode::Null => Ok(Self),
+                    #crate_name::JsonNode::Object(_) => {
+                        #check_unknown_fields
+                        Ok(Self)
+                    }
+                    _ => Err(#crate_name::JsonError::new(#message)),
                 }
-            });
-            return quote! {
-                #variant_name_str => {#name::#variant_name{#(#named_fields_init_quotes),*}}
-            };
-        } else {
-            panic!("Not supported because it is not unit, named or unnamed fields");
-        };
+            }
+        },
+        to_json: quote! {
+            fn to_json(&self) -> #crate_name::JsonNode {
+                #crate_name::JsonNode::Null
+            }
+        },
+    })
+}
 
-        if fields.len() == 0 {
-            quote! {
-                #variant_name_str => {#name::#variant_name()}
+fn json_transparent(
+    container_attributes: &ContainerAttributes,
+    transparent: &syn::Path,
+    fields: &Fields,
+) -> syn::Result<JsonImpls> {
+    let crate_name = get_call_site_crate_name("json");
+    if let Some(deny_unknown_fields) = &container_attributes.deny_unknown_fields {
+        return Err(syn::Error::new_spanned(
+            deny_unknown_fields,
+            "deny_unknown_fields cannot be combined with transparent",
+        ));
+    }
+    let field = match fields.iter().collect::<Vec<_>>().as_slice() {
+        [field] => *field,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                transparent,
+                "transparent requires a struct with exactly one field",
+            ))
+        }
+    };
+
+    let attributes = FieldAttributes::from_field(field)?;
+    let member = match &field.ident {
+        Some(ident) => quote! { #ident },
+        None => quote! { 0 },
+    };
+    let to_json = attributes.to_json(&crate_name, quote! { &self.#member });
+    let try_from_json = attributes.try_from_json(&crate_name, &field.ty, quote! { json });
+    let (decode_field, locals) =
+        decode_and_validate(&crate_name, [(&attributes, try_from_json, quote! {})]);
+
+    Ok(JsonImpls {
+        from_json: quote! {
+            fn try_from_json(json: &#crate_name::JsonNode) -> Result<Self, #crate_name::JsonError> {
+                #decode_field
+                Ok(Self { #member: #(#locals)* })
             }
-        } else if fields.len() == 1 {
-            let field = &fields[0];
-            let field_type = &field.ty;
-            quote! {
-                #variant_name_str => {#name::#variant_name(#field_type::from_json(&json["value"]))}
+        },
+        to_json: quote! {
+            fn to_json(&self) -> #crate_name::JsonNode {
+                #to_json
             }
-        } else {
-            let field_init_quotes = fields.iter().enumerate().map(|(idx, field)| {
-                let field_type = &field.ty;
-                let idx_lit = LitInt::new(&idx.to_string(), proc_macro2::Span::call_site());
-                quote! {
-                    #field_type::from_json(&json["value"][#idx_lit])
+        },
+    })
+}
+
+fn json_enum(
+    input: &DeriveInput,
+    container_attributes: &ContainerAttributes,
+    variants: &Punctuated<Variant, Comma>,
+) -> syn::Result<JsonImpls> {
+    let crate_name = get_call_site_crate_name("json");
+    let name = &input.ident;
+    let variant_attributes = variants
+        .iter()
+        .map(|variant| FieldAttributes::from_fields(&variant.fields))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let from_json_variants =
+        variants
+            .iter()
+            .zip(&variant_attributes)
+            .map(|(variant, field_attributes)| {
+                let variant_name = &variant.ident;
+                let variant_name_str = variant_name.to_string();
+
+                let fields = match &variant.fields {
+                    Fields::Unnamed(fields_unnamed) => &fields_unnamed.unnamed,
+                    Fields::Unit => {
+                        return quote! {
+                            #variant_name_str => Ok(#name::#variant_name),
+                        };
+                    }
+                    Fields::Named(fields_named) => {
+                        let field_keys: Vec<String> =
+                            fields_named.named.iter().map(field_key).collect();
+                        let (decode_fields, locals) = decode_and_validate(
+                            &crate_name,
+                            fields_named
+                                .named
+                                .iter()
+                                .zip(field_attributes)
+                                .zip(&field_keys)
+                                .map(|((field, attributes), key)| {
+                                    let value = decode_object_member(
+                                        &crate_name,
+                                        attributes,
+                                        &field.ty,
+                                        quote! { value },
+                                        key,
+                                    );
+                                    (attributes, value, quote! { .with_key(#key) })
+                                }),
+                        );
+                        let field_names = fields_named.named.iter().map(|field| &field.ident);
+                        let check_object =
+                            check_object(&crate_name, variant_name, quote! { value });
+                        let check_unknown_fields =
+                            if container_attributes.deny_unknown_fields.is_some() {
+                                check_unknown_fields(&crate_name, quote! { value }, &field_keys)
+                            } else {
+                                quote! {}
+                            };
+                        return decode_variant_value(
+                            &crate_name,
+                            &variant_name_str,
+                            quote! {
+                                #check_object
+                                #check_unknown_fields
+                                #decode_fields
+                                Ok(#name::#variant_name{#(#field_names: #locals),*})
+                            },
+                        );
+                    }
+                };
+
+                if fields.is_empty() {
+                    quote! {
+                        #variant_name_str => Ok(#name::#variant_name()),
+                    }
+                } else if fields.len() == 1 {
+                    let value = decode_object_member(
+                        &crate_name,
+                        &field_attributes[0],
+                        &fields[0].ty,
+                        quote! { json },
+                        "value",
+                    );
+                    let (decode_field, locals) = decode_and_validate(
+                        &crate_name,
+                        [(&field_attributes[0], value, quote! { .with_key("value") })],
+                    );
+                    quote! {
+                        #variant_name_str => {
+                            #decode_field
+                            Ok(#name::#variant_name(#(#locals)*))
+                        }
+                    }
+                } else {
+                    let (decode_fields, locals) = decode_and_validate(
+                        &crate_name,
+                        fields.iter().zip(field_attributes).enumerate().map(
+                            |(idx, (field, attributes))| {
+                                let value = decode_array_element(
+                                    &crate_name,
+                                    attributes,
+                                    &field.ty,
+                                    quote! { value },
+                                    idx,
+                                );
+                                (attributes, value, quote! { .with_index(#idx) })
+                            },
+                        ),
+                    );
+                    let check_array = check_array(&crate_name, variant_name, quote! { value });
+                    decode_variant_value(
+                        &crate_name,
+                        &variant_name_str,
+                        quote! {
+                            #check_array
+                            #decode_fields
+                            Ok(#name::#variant_name(#(#locals),*))
+                        },
+                    )
                 }
             });
-            quote! {
-                #variant_name_str => {#name::#variant_name(#(#field_init_quotes),*)}
-            }
-        }
-    });
 
-    let to_json_variants = variants.iter().map(|variant| {
+    let to_json_variants = variants.iter().zip(&variant_attributes).map(|(variant, field_attributes)| {
         let variant_name = &variant.ident;
         let variant_name_str = variant_name.to_string();
-        let fields = if let Fields::Unnamed(fields_unnamed) = &variant.fields {
-            &fields_unnamed.unnamed
-        } else if let Fields::Unit = &variant.fields {
-            return quote! {
-                #name::#variant_name => {
-                    json["type"] = #crate_name::JsonNode::String(#variant_name_str.to_string());
-                }
-            };
-        } else if let Fields::Named(fields_named) = &variant.fields {
-            let quote_identifiers = fields_named.named.iter().map(|field| {
-                field.ident.as_ref().unwrap()
-            });
-            let field_init_quotes = fields_named.named.iter().map(|field| {
-                let field_name = field.ident.as_ref().unwrap();
-                quote! {
-                    json["value"][stringify!(#field_name)] = #field_name.to_json();
-                }
-            });
-            return quote! {
-                #name::#variant_name{#(#quote_identifiers),*} => {
-                    json["type"] = #crate_name::JsonNode::String(#variant_name_str.to_string());
-                    json["value"] = #crate_name::JsonNode::Object(std::collections::HashMap::new());
-                    #(#field_init_quotes)*
-                }
-            };
-        }  else {
-            panic!("Only unit and unnamed fields are supported");
+        let fields = match &variant.fields {
+            Fields::Unnamed(fields_unnamed) => &fields_unnamed.unnamed,
+            Fields::Unit => {
+                return quote! {
+                    #name::#variant_name => {
+                        json["type"] = #crate_name::JsonNode::String(#variant_name_str.to_string());
+                    }
+                };
+            }
+            Fields::Named(fields_named) => {
+                let quote_identifiers = fields_named.named.iter().map(|field| {
+                    field.ident.as_ref().unwrap()
+                });
+                let field_init_quotes = fields_named.named.iter().zip(field_attributes).map(|(field, attributes)| {
+                    let field_name = field.ident.as_ref().unwrap();
+                    let key = field_key(field);
+                    let value = attributes.to_json(&crate_name, quote! { #field_name });
+                    quote! {
+                        json["value"][#key] = #value;
+                    }
+                });
+                return quote! {
+                    #name::#variant_name{#(#quote_identifiers),*} => {
+                        json["type"] = #crate_name::JsonNode::String(#variant_name_str.to_string());
+                        json["value"] = #crate_name::JsonNode::Object(std::collections::HashMap::new());
+                        #(#field_init_quotes)*
+                    }
+                };
+            }
         };
 
-        if fields.len() == 0 {
+        if fields.is_empty() {
             quote! {
                 #name::#variant_name() => {
                     json["type"] = #crate_name::JsonNode::String(#variant_name_str.to_string());
                 }
             }
         } else if fields.len() == 1 {
+            let value = field_attributes[0].to_json(&crate_name, quote! { v });
             quote! {
                 #name::#variant_name(v) => {
                     json["type"] = #crate_name::JsonNode::String(#variant_name_str.to_string());
-                    json["value"] = v.to_json();
+                    json["value"] = #value;
                 }
             }
         } else {
@@ -216,10 +401,11 @@ fn json_enum(input: DeriveInput) -> proc_macro2::TokenStream {
                     #var_name
                 }
             });
-            let field_init_quotes = fields.iter().enumerate().map(|(idx, _)| {
+            let field_init_quotes = field_attributes.iter().enumerate().map(|(idx, attributes)| {
                 let var_name = format_ident!("v{}", idx);
+                let value = attributes.to_json(&crate_name, quote! { #var_name });
                 quote! {
-                    json["value"].push(#var_name.to_json());
+                    json["value"].push(#value);
                 }
             });
 
@@ -235,20 +421,34 @@ fn json_enum(input: DeriveInput) -> proc_macro2::TokenStream {
         }
     });
 
-    let expanded = quote! {
-        impl #crate_name::FromJson for #name {
-            fn from_json(json: &#crate_name::JsonNode) -> Self {
-                match &json["type"] {
-                    #crate_name::JsonNode::String(s) => match s.as_str() {
-                        #(#from_json_variants)*
-                        _ => panic!("Invalid variant")
-                    }
-                    _ => panic!("Invalid variant")
+    let check_object = check_object(&crate_name, name, quote! { json });
+    let check_unknown_fields = if container_attributes.deny_unknown_fields.is_some() {
+        check_unknown_fields(
+            &crate_name,
+            quote! { json },
+            &["type".to_string(), "value".to_string()],
+        )
+    } else {
+        quote! {}
+    };
+
+    Ok(JsonImpls {
+        from_json: quote! {
+            fn try_from_json(json: &#crate_name::JsonNode) -> Result<Self, #crate_name::JsonError> {
+                #check_object
+                #check_unknown_fields
+                let variant = match json.get("type") {
+                    Some(#crate_name::JsonNode::String(s)) => s.as_str(),
+                    Some(_) => return Err(#crate_name::JsonError::new("Variant tag must be a string").with_key("type")),
+                    None => return Err(#crate_name::JsonError::missing_field("type")),
+                };
+                match variant {
+                    #(#from_json_variants)*
+                    _ => Err(#crate_name::JsonError::new(format!("Invalid variant \"{}\"", variant)).with_key("type")),
                 }
             }
-        }
-
-        impl #crate_name::ToJson for #name {
+        },
+        to_json: quote! {
             fn to_json(&self) -> #crate_name::JsonNode {
                 let mut json = #crate_name::JsonNode::Object(std::collections::HashMap::new());
                 match self {
@@ -256,22 +456,372 @@ fn json_enum(input: DeriveInput) -> proc_macro2::TokenStream {
                 }
                 json
             }
+        },
+    })
+}
+
+/// The JSON object key a named field is stored under.
+fn field_key(field: &syn::Field) -> String {
+    field.ident.as_ref().unwrap().unraw().to_string()
+}
+
+/// A `Result` decoding the member `key` of the object `json`, reporting a missing
+/// member and prefixing any nested error with `key`.
+fn decode_object_member(
+    crate_name: &impl ToTokens,
+    attributes: &FieldAttributes,
+    field_type: &syn::Type,
+    json: proc_macro2::TokenStream,
+    key: &str,
+) -> proc_macro2::TokenStream {
+    let value = attributes.try_from_json(crate_name, field_type, quote! { member });
+    quote! {
+        match #json.get(#key) {
+            Some(member) => #value.map_err(|err| err.with_key(#key)),
+            None => Err(#crate_name::JsonError::missing_field(#key)),
+        }
+    }
+}
+
+/// A `Result` decoding the element `index` of the array `json`, reporting a missing
+/// element and prefixing any nested error with `index`.
+fn decode_array_element(
+    crate_name: &impl ToTokens,
+    attributes: &FieldAttributes,
+    field_type: &syn::Type,
+    json: proc_macro2::TokenStream,
+    index: usize,
+) -> proc_macro2::TokenStream {
+    let value = attributes.try_from_json(crate_name, field_type, quote! { element });
+    quote! {
+        match #json.get_index(#index) {
+            Some(element) => #value.map_err(|err| err.with_index(#index)),
+            None => Err(#crate_name::JsonError::missing_element(#index)),
+        }
+    }
+}
+
+/// Decodes each field into a local with its decoding expression (a `Result`) and runs
+/// the validators of every field that decoded, returning the errors of all fields at
+/// once with their pointers. Returns the statements and the names of the locals, in
+/// field order.
+fn decode_and_validate<'a>(
+    crate_name: &impl ToTokens,
+    fields: impl IntoIterator<
+        Item = (
+            &'a FieldAttributes,
+            proc_macro2::TokenStream,
+            proc_macro2::TokenStream,
+        ),
+    >,
+) -> (proc_macro2::TokenStream, Vec<Ident>) {
+    let mut decode = proc_macro2::TokenStream::new();
+    let mut locals = Vec::new();
+    let violations = quote! { violations };
+
+    for (idx, (attributes, value, pointer)) in fields.into_iter().enumerate() {
+        let local = format_ident!("__field{}", idx);
+        decode.extend(quote! {
+            let #local = match #value {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    #violations.extend(err.into_violations());
+                    None
+                }
+            };
+        });
+        if attributes.has_validators() {
+            let validate =
+                attributes.validate(crate_name, &quote! { #local }, &violations, &pointer);
+            decode.extend(quote! {
+                if let Some(#local) = &#local {
+                    #validate
+                }
+            });
+        }
+        locals.push(local);
+    }
+
+    let unwrap = locals
+        .iter()
+        .map(|local| quote! { let #local = #local.unwrap(); });
+    let decode = quote! {
+        let mut #violations: Vec<#crate_name::JsonError> = Vec::new();
+        #decode
+        if !#violations.is_empty() {
+            return Err(#crate_name::JsonError::from_violations(#violations));
         }
+        #(#unwrap)*
     };
+    (decode, locals)
+}
+
+/// Match arm for an enum variant whose payload lives under `"value"`. `body` sees the
+/// payload as `value`; errors it returns are prefixed with `/value`.
+fn decode_variant_value(
+    crate_name: &impl ToTokens,
+    variant_name: &str,
+    body: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    quote! {
+        #variant_name => {
+            let value = match json.get("value") {
+                Some(value) => value,
+                None => return Err(#crate_name::JsonError::missing_field("value")),
+            };
+            (|| -> Result<Self, #crate_name::JsonError> { #body })()
+                .map_err(|err| err.with_key("value"))
+        }
+    }
+}
+
+fn check_object(
+    crate_name: &impl ToTokens,
+    name: &Ident,
+    json: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let message = format!("Cannot convert non-object type to {}", name);
+    quote! {
+        if !matches!(#json, #crate_name::JsonNode::Object(_)) {
+            return Err(#crate_name::JsonError::new(#message));
+        }
+    }
+}
+
+fn check_array(
+    crate_name: &impl ToTokens,
+    name: &Ident,
+    json: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let message = format!("Cannot convert non-array type to {}", name);
+    quote! {
+        if !matches!(#json, #crate_name::JsonNode::Array(_)) {
+            return Err(#crate_name::JsonError::new(#message));
+        }
+    }
+}
+
+/// Rejects members of the object `json` whose keys are not in `keys`.
+fn check_unknown_fields(
+    crate_name: &impl ToTokens,
+    json: proc_macro2::TokenStream,
+    keys: &[String],
+) -> proc_macro2::TokenStream {
+    quote! {
+        {
+            const FIELDS: &[&str] = &[#(#keys),*];
+            let unknown: Vec<&str> = #json
+                .obj_iter()
+                .into_iter()
+                .flatten()
+                .map(|(key, _)| key.as_str())
+                .filter(|key| !FIELDS.contains(key))
+                .collect();
+            if !unknown.is_empty() {
+                return Err(#crate_name::JsonError::unknown_fields(unknown, FIELDS));
+            }
+        }
+    }
+}
 
-    proc_macro2::TokenStream::from(expanded)
+/// Bodies of the `FromJson` and `ToJson` impls for a derive input; the derive macros
+/// pick the ones they need and wrap them in impl blocks.
+struct JsonImpls {
+    from_json: proc_macro2::TokenStream,
+    to_json: proc_macro2::TokenStream,
 }
 
-#[proc_macro_derive(JsonType)]
+fn derive_json_impls(input: &DeriveInput) -> syn::Result<JsonImpls> {
+    let container_attributes = ContainerAttributes::from_attributes(&input.attrs)?;
+    match &input.data {
+        Data::Struct(data_struct) => {
+            match (&container_attributes.transparent, &data_struct.fields) {
+                (Some(transparent), fields) => {
+                    json_transparent(&container_attributes, transparent, fields)
+                }
+                (None, Fields::Named(fields_named)) => {
+                    json_struct(input, &container_attributes, &fields_named.named)
+                }
+                (None, Fields::Unnamed(fields_unnamed)) => {
+                    json_tuple(input, &container_attributes, &fields_unnamed.unnamed)
+                }
+                (None, Fields::Unit) => json_unit(input, &container_attributes),
+            }
+        }
+        Data::Enum(_) if container_attributes.transparent.is_some() => {
+            Err(syn::Error::new_spanned(
+                &container_attributes.transparent,
+                "transparent is only supported on structs",
+            ))
+        }
+        Data::Enum(data_enum) => json_enum(input, &container_attributes, &data_enum.variants),
+        Data::Union(data_union) => Err(syn::Error::new_spanned(
+            data_union.union_token,
+            "Unions are unsafe, please use enum instead",
+        )),
+    }
+}
+
+/// Wraps `body` in an impl of `trait_name` for the derive input, requiring the trait
+/// of every type parameter.
+fn impl_json_trait(
+    input: &DeriveInput,
+    trait_name: &str,
+    body: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let crate_name = get_call_site_crate_name("json");
+    let trait_ident = format_ident!("{}", trait_name);
+    let name = &input.ident;
+
+    let mut generics = input.generics.clone();
+    let type_params: Vec<Ident> = generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect();
+    let where_clause = generics.make_where_clause();
+    for param in type_params {
+        where_clause
+            .predicates
+            .push(syn::parse_quote!(#param: #crate_name::#trait_ident));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics #crate_name::#trait_ident for #name #ty_generics #where_clause {
+            #body
+        }
+    }
+}
+
+fn derive_json(
+    item: proc_macro::TokenStream,
+    generate: impl FnOnce(&DeriveInput) -> syn::Result<proc_macro2::TokenStream>,
+) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    proc_macro::TokenStream::from(generate(&input).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+/// Derives both `FromJson` and `ToJson`.
+#[proc_macro_derive(JsonType, attributes(json))]
 pub fn json_type(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    derive_json(item, |input| {
+        let impls = derive_json_impls(input)?;
+        let from_json = impl_json_trait(input, "FromJson", impls.from_json);
+        let to_json = impl_json_trait(input, "ToJson", impls.to_json);
+        Ok(quote! {
+            #from_json
+            #to_json
+        })
+    })
+}
+
+#[proc_macro_derive(ToJson, attributes(json))]
+pub fn to_json(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    derive_json(item, |input| {
+        let impls = derive_json_impls(input)?;
+        Ok(impl_json_trait(input, "ToJson", impls.to_json))
+    })
+}
+
+#[proc_macro_derive(FromJson, attributes(json))]
+pub fn from_json(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    derive_json(item, |input| {
+        let impls = derive_json_impls(input)?;
+        Ok(impl_json_trait(input, "FromJson", impls.from_json))
+    })
+}
+
+#[proc_macro_derive(JsonSchema, attributes(json))]
+pub fn json_schema(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    derive_json(item, |input| {
+        derive_json_impls(input)?;
+        Ok(impl_json_trait(
+            input,
+            "JsonSchema",
+            json_schema::json_schema(input)?,
+        ))
+    })
+}
+
+fn derive_json_key(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let crate_name = get_call_site_crate_name("json");
+    let name = &input.ident;
+    let body = match &input.data {
+        Data::Enum(data_enum) => {
+            if let Some(variant) = data_enum
+                .variants
+                .iter()
+                .find(|variant| !matches!(variant.fields, Fields::Unit))
+            {
+                return Err(syn::Error::new_spanned(
+                    &variant.fields,
+                    "JsonKey can only be derived for enums with unit variants",
+                ));
+            }
+            let variant_names: Vec<&Ident> = data_enum
+                .variants
+                .iter()
+                .map(|variant| &variant.ident)
+                .collect();
+            let variant_name_strs: Vec<String> = variant_names
+                .iter()
+                .map(|ident| ident.to_string())
+                .collect();
+            let message = format!("Invalid {} key \"{{}}\"", name);
+            quote! {
+                fn to_json_key(&self) -> String {
+                    match self {
+                        #(Self::#variant_names => #variant_name_strs.to_string(),)*
+                    }
+                }
+
+                fn from_json_key(key: &str) -> Result<Self, #crate_name::JsonError> {
+                    match key {
+                        #(#variant_name_strs => Ok(Self::#variant_names),)*
+                        _ => Err(#crate_name::JsonError::new(format!(#message, key))),
+                    }
+                }
+            }
+        }
+        Data::Struct(data_struct) if data_struct.fields.len() == 1 => {
+            let field = data_struct.fields.iter().next().unwrap();
+            let field_type = &field.ty;
+            let member = match &field.ident {
+                Some(ident) => quote! { #ident },
+                None => quote! { 0 },
+            };
+            quote! {
+                fn to_json_key(&self) -> String {
+                    #crate_name::JsonKey::to_json_key(&self.#member)
+                }
+
+                fn from_json_key(key: &str) -> Result<Self, #crate_name::JsonError> {
+                    Ok(Self { #member: <#field_type as #crate_name::JsonKey>::from_json_key(key)? })
+                }
+            }
+        }
+        Data::Struct(_) => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "JsonKey can only be derived for structs with exactly one field",
+            ))
+        }
+        Data::Union(data_union) => {
+            return Err(syn::Error::new_spanned(
+                data_union.union_token,
+                "Unions are unsafe, please use enum instead",
+            ))
+        }
+    };
+    Ok(impl_json_trait(input, "JsonKey", body))
+}
+
+/// Derives `JsonKey` for enums with only unit variants and for single-field newtypes.
+#[proc_macro_derive(JsonKey)]
+pub fn json_key(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(item as DeriveInput);
-    if let Data::Struct(_) = &input.data {
-        proc_macro::TokenStream::from(json_struct(input))
-    } else if let Data::Enum(_) = &input.data {
-        proc_macro::TokenStream::from(json_enum(input))
-    } else {
-        panic!("Unions are unsafe, please use enum instead")
-    }
+    let expanded = derive_json_key(&input);
+    proc_macro::TokenStream::from(expanded.unwrap_or_else(syn::Error::into_compile_error))
 }
 
 #[derive(Debug)]
@@ -280,102 +830,336 @@ enum StringLiteralOrTokenStream {
     TokenStream(proc_macro2::TokenStream),
 }
 
+/// An object member or array element, or `..#value` splicing another object or array
+/// in its place.
+enum MacroJsonItem<T> {
+    Single(T),
+    Spread(MacroJsonNode),
+}
+
+/// An entry of an object or array with its `#[cfg(...)]` attributes and `if` guard.
+struct MacroJsonEntry<T> {
+    attributes: std::vec::Vec<proc_macro2::TokenStream>,
+    item: MacroJsonItem<T>,
+    condition: Option<syn::Expr>,
+}
+
+impl<T> MacroJsonEntry<T> {
+    fn single(item: T) -> Self {
+        MacroJsonEntry {
+            attributes: std::vec::Vec::new(),
+            item: MacroJsonItem::Single(item),
+            condition: None,
+        }
+    }
+
+    fn parse_with(
+        input: ParseStream,
+        parse_single: impl FnOnce(ParseStream) -> syn::Result<T>,
+    ) -> syn::Result<Self> {
+        let mut attributes = std::vec::Vec::new();
+        while input.peek(syn::Token![#]) && input.peek2(syn::token::Bracket) {
+            input.parse::<syn::Token![#]>()?;
+            let content;
+            syn::bracketed!(content in input);
+            let meta: syn::Meta = content.parse()?;
+            if !meta.path().is_ident("cfg") {
+                return Err(syn::Error::new_spanned(
+                    meta.path(),
+                    "Only #[cfg(...)] is supported on entries",
+                ));
+            }
+            attributes.push(quote! { #[#meta] });
+        }
+
+        let item = if input.peek(syn::Token![..]) {
+            input.parse::<syn::Token![..]>()?;
+            MacroJsonItem::Spread(MacroJsonNode::parse(input)?)
+        } else {
+            MacroJsonItem::Single(parse_single(input)?)
+        };
+
+        let condition = if input.peek(syn::Token![if]) {
+            input.parse::<syn::Token![if]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        Ok(MacroJsonEntry {
+            attributes,
+            item,
+            condition,
+        })
+    }
+
+    /// Entries without attributes, guards or spreads can be built in a single literal.
+    fn is_single(&self) -> bool {
+        self.attributes.is_empty()
+            && self.condition.is_none()
+            && matches!(self.item, MacroJsonItem::Single(_))
+    }
+
+    /// Builds the statement adding this entry with `generate_single` or `generate_spread`,
+    /// wrapped in the entry's `#[cfg(...)]` attributes and `if` guard.
+    fn generate_statement(
+        self,
+        generate_single: impl FnOnce(T) -> proc_macro2::TokenStream,
+        generate_spread: impl FnOnce(proc_macro2::TokenStream) -> proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let statement = match self.item {
+            MacroJsonItem::Single(single) => generate_single(single),
+            // Spread expressions are passed as they are, so owned values are moved.
+            MacroJsonItem::Spread(MacroJsonNode::TokenStream(token_stream)) => {
+                generate_spread(quote! { (#token_stream) })
+            }
+            MacroJsonItem::Spread(node) => generate_spread(proc_macro2::TokenStream::from(
+                generate_json_call_site(node),
+            )),
+        };
+        let attributes = self.attributes;
+        let statement = match self.condition {
+            Some(condition) => quote! { if #condition { #statement } },
+            None => statement,
+        };
+        quote! { #(#attributes)* { #statement } }
+    }
+}
+
 enum MacroJsonNode {
-    Object(std::vec::Vec<(StringLiteralOrTokenStream, MacroJsonNode)>),
-    Array(std::vec::Vec<MacroJsonNode>),
+    Object(std::vec::Vec<MacroJsonEntry<(StringLiteralOrTokenStream, MacroJsonNode)>>),
+    Array(std::vec::Vec<MacroJsonEntry<MacroJsonNode>>),
     String(String),
-    Number(f64),
+    Number(MacroJsonNumber),
     Boolean(bool),
+    /// A Rust expression, converted with `ToJson`.
     TokenStream(proc_macro2::TokenStream),
     Null,
 }
 
+/// A number literal. Integers keep their exact value and suffix so they are emitted
+/// with the type they were written with.
+enum MacroJsonNumber {
+    Integer { value: i128, suffix: String },
+    Float { value: f64, suffix: String },
+}
+
+impl MacroJsonNumber {
+    /// Checks that the integer fits its suffix and that the `f64` in `JsonNode::Number`
+    /// holds it without rounding.
+    fn integer(digits: &str, negative: bool, suffix: &str) -> Result<Self, String> {
+        if matches!(suffix, "f32" | "f64") {
+            return MacroJsonNumber::float(digits, negative, suffix);
+        }
+        let sign = if negative { "-" } else { "" };
+        let value = format!("{}{}", sign, digits)
+            .parse::<i128>()
+            .map_err(|_| format!("Integer literal {}{} is too large", sign, digits))?;
+        let fits = match suffix {
+            "" => true,
+            "i8" => i8::try_from(value).is_ok(),
+            "i16" => i16::try_from(value).is_ok(),
+            "i32" => i32::try_from(value).is_ok(),
+            "i64" => i64::try_from(value).is_ok(),
+            "i128" => true,
+            "isize" => isize::try_from(value).is_ok(),
+            "u8" => u8::try_from(value).is_ok(),
+            "u16" => u16::try_from(value).is_ok(),
+            "u32" => u32::try_from(value).is_ok(),
+            "u64" => u64::try_from(value).is_ok(),
+            "u128" => value >= 0,
+            "usize" => usize::try_from(value).is_ok(),
+            _ => return Err(format!("Unsupported number suffix `{}`", suffix)),
+        };
+        if !fits {
+            return Err(format!(
+                "Integer literal {} does not fit in {}",
+                value, suffix
+            ));
+        }
+        if value as f64 as i128 != value {
+            return Err(format!(
+                "Integer literal {} cannot be represented exactly as a JSON number; \
+                 the nearest value is {}",
+                value, value as f64 as i128
+            ));
+        }
+        Ok(MacroJsonNumber::Integer {
+            value,
+            suffix: suffix.to_string(),
+        })
+    }
+
+    fn float(digits: &str, negative: bool, suffix: &str) -> Result<Self, String> {
+        if !matches!(suffix, "" | "f32" | "f64") {
+            return Err(format!("Unsupported number suffix `{}`", suffix));
+        }
+        let value: f64 = digits
+            .parse()
+            .map_err(|_| format!("Invalid number literal {}", digits))?;
+        if !value.is_finite() || (suffix == "f32" && !(value as f32).is_finite()) {
+            return Err(format!("Number literal {} is out of range", digits));
+        }
+        Ok(MacroJsonNumber::Float {
+            value: if negative { -value } else { value },
+            suffix: suffix.to_string(),
+        })
+    }
+
+    fn from_lit_int(lit: &syn::LitInt, negative: bool) -> syn::Result<MacroJsonNode> {
+        MacroJsonNumber::integer(lit.base10_digits(), negative, lit.suffix())
+            .map(MacroJsonNode::Number)
+            .map_err(|message| syn::Error::new(lit.span(), message))
+    }
+
+    fn from_lit_float(lit: &syn::LitFloat, negative: bool) -> syn::Result<MacroJsonNode> {
+        MacroJsonNumber::float(lit.base10_digits(), negative, lit.suffix())
+            .map(MacroJsonNode::Number)
+            .map_err(|message| syn::Error::new(lit.span(), message))
+    }
+
+    /// The literal, typed by its suffix, or as `i64` / `f64` when it has none.
+    fn to_literal(&self) -> proc_macro2::TokenStream {
+        let span = proc_macro2::Span::call_site();
+        let (negative, literal) = match self {
+            MacroJsonNumber::Integer { value, suffix } => {
+                let suffix = match suffix.as_str() {
+                    "" if i64::try_from(*value).is_ok() => "i64",
+                    "" => "i128",
+                    suffix => suffix,
+                };
+                let literal =
+                    syn::LitInt::new(&format!("{}{}", value.unsigned_abs(), suffix), span);
+                (*value < 0, literal.into_token_stream())
+            }
+            MacroJsonNumber::Float { value, suffix } => {
+                let suffix = if suffix.is_empty() { "f64" } else { suffix };
+                let literal = syn::LitFloat::new(&format!("{:?}{}", value.abs(), suffix), span);
+                (value.is_sign_negative(), literal.into_token_stream())
+            }
+        };
+        if negative {
+            quote! { -#literal }
+        } else {
+            literal
+        }
+    }
+}
+
+impl MacroJsonNode {
+    /// Keeps JSON literals (including negative numbers) as literals; anything else is an
+    /// expression to interpolate.
+    fn from_expr(expr: syn::Expr) -> syn::Result<Self> {
+        match &expr {
+            syn::Expr::Lit(syn::ExprLit { lit, .. }) => match lit {
+                syn::Lit::Str(lit) => return Ok(MacroJsonNode::String(lit.value())),
+                syn::Lit::Int(lit) => return MacroJsonNumber::from_lit_int(lit, false),
+                syn::Lit::Float(lit) => return MacroJsonNumber::from_lit_float(lit, false),
+                syn::Lit::Bool(lit) => return Ok(MacroJsonNode::Boolean(lit.value)),
+                _ => {}
+            },
+            syn::Expr::Unary(syn::ExprUnary {
+                op: syn::UnOp::Neg(_),
+                expr: operand,
+                ..
+            }) => {
+                if let syn::Expr::Lit(syn::ExprLit { lit, .. }) = operand.as_ref() {
+                    match lit {
+                        syn::Lit::Int(lit) => return MacroJsonNumber::from_lit_int(lit, true),
+                        syn::Lit::Float(lit) => return MacroJsonNumber::from_lit_float(lit, true),
+                        _ => {}
+                    }
+                }
+            }
+            syn::Expr::Path(path) if path.qself.is_none() && path.path.is_ident("null") => {
+                return Ok(MacroJsonNode::Null)
+            }
+            _ => {}
+        }
+        Ok(MacroJsonNode::TokenStream(expr.into_token_stream()))
+    }
+}
+
+/// Parses comma separated elements up to the end of `content`. A single trailing comma
+/// is allowed; a missing element before a comma is an error.
+fn parse_comma_separated<T>(
+    content: ParseStream,
+    mut parse_element: impl FnMut(ParseStream) -> syn::Result<T>,
+) -> syn::Result<std::vec::Vec<T>> {
+    let mut elements = std::vec::Vec::new();
+    while !content.is_empty() {
+        if content.peek(syn::Token![,]) {
+            return Err(syn::Error::new(
+                content.span(),
+                "Expects a value before the comma",
+            ));
+        }
+        elements.push(parse_element(content)?);
+        if content.is_empty() {
+            break;
+        }
+        if !content.peek(syn::Token![,]) {
+            return Err(syn::Error::new(content.span(), "Expects a comma"));
+        }
+        content.parse::<syn::Token![,]>()?;
+    }
+    Ok(elements)
+}
+
+fn parse_object_key(content: ParseStream) -> syn::Result<StringLiteralOrTokenStream> {
+    if content.peek(syn::LitStr) {
+        Ok(StringLiteralOrTokenStream::StringLiteral(
+            content.parse::<syn::LitStr>()?.value(),
+        ))
+    } else if content.peek(syn::Token![#]) {
+        content.parse::<syn::Token![#]>()?;
+
+        let inner_content;
+
+        if content.peek(syn::token::Paren) {
+            syn::parenthesized!(inner_content in content);
+            let token_stream: proc_macro2::TokenStream = inner_content.parse()?;
+            Ok(StringLiteralOrTokenStream::TokenStream(token_stream))
+        } else if content.peek(syn::Ident) {
+            let ident: Ident = content.parse()?;
+            Ok(StringLiteralOrTokenStream::TokenStream(quote! {#ident}))
+        } else {
+            Err(syn::Error::new(
+                content.span(),
+                "identifier after # is not valid",
+            ))
+        }
+    } else {
+        Err(syn::Error::new(content.span(), "Invalid key"))
+    }
+}
+
 impl Parse for MacroJsonNode {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         if input.peek(syn::token::Brace) {
             let content;
             syn::braced!(content in input);
-            let mut obj = std::vec::Vec::new();
-            while !content.is_empty() {
-                let key;
-                if content.peek(syn::LitStr) {
-                    key = StringLiteralOrTokenStream::StringLiteral(
-                        content.parse::<syn::LitStr>()?.value(),
-                    );
-                } else if content.peek(syn::Token![#]) {
-                    content.parse::<syn::Token![#]>()?;
-
-                    let inner_content;
-
-                    if content.peek(syn::token::Paren) {
-                        syn::parenthesized!(inner_content in content);
-                        let token_stream: proc_macro2::TokenStream = inner_content.parse()?;
-                        key = StringLiteralOrTokenStream::TokenStream(token_stream);
-                    } else if content.peek(syn::Ident) {
-                        let ident: Ident = content.parse()?;
-                        key = StringLiteralOrTokenStream::TokenStream(quote! {#ident});
-                    } else {
-                        return Err(syn::Error::new(
-                            content.span(),
-                            "identifier after # is not valid",
-                        ));
-                    }
-                } else {
-                    return Err(syn::Error::new(content.span(), "Invalid key"));
-                }
-
-                if !content.peek(syn::Token![:]) {
-                    return Err(syn::Error::new(content.span(), "Expects a colon"));
-                }
-
-                content.parse::<syn::Token![:]>()?;
-                let value = MacroJsonNode::parse(&content)?;
-                obj.push((key, value));
+            let obj = parse_comma_separated(&content, |content| {
+                MacroJsonEntry::parse_with(content, |content| {
+                    let key = parse_object_key(content)?;
 
-                if content.peek(syn::Token![,]) {
-                    content.parse::<syn::Token![,]>()?;
-                } else {
-                    if !content.is_empty() {
-                        return Err(syn::Error::new(content.span(), "Expects a comma"));
+                    if !content.peek(syn::Token![:]) {
+                        return Err(syn::Error::new(content.span(), "Expects a colon"));
                     }
-                }
-            }
+
+                    content.parse::<syn::Token![:]>()?;
+                    let value = MacroJsonNode::parse(content)?;
+                    Ok((key, value))
+                })
+            })?;
             Ok(MacroJsonNode::Object(obj))
         } else if input.peek(syn::token::Bracket) {
             let content;
             syn::bracketed!(content in input);
-            let mut arr = std::vec::Vec::new();
-            while !content.is_empty() {
-                let value = MacroJsonNode::parse(&content)?;
-                arr.push(value);
-                if content.peek(syn::Token![,]) {
-                    content.parse::<syn::Token![,]>()?;
-                } else {
-                    if !content.is_empty() {
-                        return Err(syn::Error::new(content.span(), "Expects a comma"));
-                    }
-                }
-            }
+            let arr = parse_comma_separated(&content, |content| {
+                MacroJsonEntry::parse_with(content, MacroJsonNode::parse)
+            })?;
             Ok(MacroJsonNode::Array(arr))
-        } else if input.peek(syn::LitStr) {
-            let lit: syn::LitStr = input.parse()?;
-            Ok(MacroJsonNode::String(lit.value()))
-        } else if input.peek(syn::LitFloat) {
-            let lit: syn::LitFloat = input.parse()?;
-            Ok(MacroJsonNode::Number(lit.base10_parse()?))
-        } else if input.peek(syn::LitInt) {
-            let lit: syn::LitInt = input.parse()?;
-            Ok(MacroJsonNode::Number(lit.base10_parse()?))
-        } else if input.peek(syn::Ident) {
-            let ident: syn::Ident = input.parse()?;
-            if ident == "null" {
-                Ok(MacroJsonNode::Null)
-            } else {
-                Err(syn::Error::new(ident.span(), "Invalid identifier"))
-            }
-        } else if input.peek(syn::LitBool) {
-            let lit: syn::LitBool = input.parse()?;
-            Ok(MacroJsonNode::Boolean(lit.value))
         } else if input.peek(syn::Token![#]) {
             // could be a single token or a token stream with ()
             input.parse::<syn::Token![#]>()?;
@@ -394,12 +1178,27 @@ impl Parse for MacroJsonNode {
                     "Identifier after # is not valid",
                 ))
             }
+        } else if input.is_empty() || input.peek(syn::Token![,]) {
+            Err(syn::Error::new(input.span(), "Expects a value"))
         } else {
-            Err(syn::Error::new(
-                input.span(),
-                format!("Invalid token at: {:?}", input),
-            ))
+            MacroJsonNode::from_expr(input.parse()?)
+        }
+    }
+}
+
+/// The whole input of `json!`, which may end with a trailing comma.
+struct MacroJsonInput(MacroJsonNode);
+
+impl Parse for MacroJsonInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut nodes = parse_comma_separated(input, MacroJsonNode::parse)?;
+        if nodes.len() != 1 {
+            return Err(syn::Error::new(
+                proc_macro2::Span::call_site(),
+                "Expects a single JSON value",
+            ));
         }
+        Ok(MacroJsonInput(nodes.remove(0)))
     }
 }
 
@@ -414,9 +1213,9 @@ fn generate_json_call_site(compiler_json: MacroJsonNode) -> proc_macro::TokenStr
             proc_macro::TokenStream::from(expanded)
         }
         MacroJsonNode::Number(n) => {
-            let number_literal = proc_macro2::Literal::f64_unsuffixed(n);
+            let number_literal = n.to_literal();
             let expanded = quote! {
-                #number_literal.to_json()
+                (#number_literal).to_json()
             };
             proc_macro::TokenStream::from(expanded)
         }
@@ -432,61 +1231,165 @@ fn generate_json_call_site(compiler_json: MacroJsonNode) -> proc_macro::TokenStr
             };
             proc_macro::TokenStream::from(expanded)
         }
-        MacroJsonNode::Array(arr) => {
-            let arr = arr
-                .into_iter()
-                .map(|node| proc_macro2::TokenStream::from(generate_json_call_site(node)));
+        MacroJsonNode::Array(arr) if arr.iter().all(MacroJsonEntry::is_single) => {
+            let arr = arr.into_iter().map(|entry| match entry.item {
+                MacroJsonItem::Single(node) => {
+                    proc_macro2::TokenStream::from(generate_json_call_site(node))
+                }
+                MacroJsonItem::Spread(_) => unreachable!(),
+            });
             let expanded = quote! {
                 #crate_name::JsonNode::Array(std::vec::Vec::<#crate_name::JsonNode>::from([#(#arr),*]))
             };
             proc_macro::TokenStream::from(expanded)
         }
-        MacroJsonNode::Object(map) => {
-            let arr = map.into_iter().map(|(key, value)| {
-                let key_token_stream = match key {
-                    StringLiteralOrTokenStream::StringLiteral(s) => {
-                        let key = proc_macro2::Literal::string(&s);
-                        quote! {
-                            #key.to_string()
-                        }
-                    }
-                    StringLiteralOrTokenStream::TokenStream(token_stream) => {
+        MacroJsonNode::Array(arr) => {
+            let statements = arr.into_iter().map(|entry| {
+                entry.generate_statement(
+                    |node| {
+                        let value = proc_macro2::TokenStream::from(generate_json_call_site(node));
+                        quote! { __json_array.push(#value); }
+                    },
+                    |value| {
                         quote! {
-                            #token_stream
+                            #crate_name::JsonArraySpread::spread_into_array(#value, &mut __json_array);
                         }
+                    },
+                )
+            });
+            let expanded = quote! {
+                {
+                    let mut __json_array = std::vec::Vec::<#crate_name::JsonNode>::new();
+                    #(#statements)*
+                    #crate_name::JsonNode::Array(__json_array)
+                }
+            };
+            proc_macro::TokenStream::from(expanded)
+        }
+        MacroJsonNode::Object(map) if map.iter().all(MacroJsonEntry::is_single) => {
+            let arr = map.into_iter().map(|entry| match entry.item {
+                MacroJsonItem::Single((key, value)) => {
+                    let key_token_stream = generate_json_key(&key);
+                    let value = proc_macro2::TokenStream::from(generate_json_call_site(value));
+                    quote! {
+                        (#key_token_stream, #value)
                     }
-                };
-                let value = proc_macro2::TokenStream::from(generate_json_call_site(value));
-                quote! {
-                    (#key_token_stream, #value)
                 }
+                MacroJsonItem::Spread(_) => unreachable!(),
             });
             let expanded = quote! {
                 #crate_name::JsonNode::Object(std::collections::HashMap::<String, #crate_name::JsonNode>::from([#(#arr),*]))
             };
             proc_macro::TokenStream::from(expanded)
         }
+        MacroJsonNode::Object(map) => {
+            let statements = map.into_iter().map(|entry| {
+                entry.generate_statement(
+                    |(key, value)| {
+                        let key_token_stream = generate_json_key(&key);
+                        let value = proc_macro2::TokenStream::from(generate_json_call_site(value));
+                        quote! { __json_object.insert(#key_token_stream, #value); }
+                    },
+                    |value| {
+                        quote! {
+                            #crate_name::JsonObjectSpread::spread_into_object(#value, &mut __json_object);
+                        }
+                    },
+                )
+            });
+            let expanded = quote! {
+                {
+                    let mut __json_object =
+                        std::collections::HashMap::<String, #crate_name::JsonNode>::new();
+                    #(#statements)*
+                    #crate_name::JsonNode::Object(__json_object)
+                }
+            };
+            proc_macro::TokenStream::from(expanded)
+        }
         MacroJsonNode::TokenStream(token_stream) => {
             let expanded = quote! {
-                #token_stream
+                #crate_name::ToJson::to_json(&(#token_stream))
             };
             proc_macro::TokenStream::from(expanded)
         }
     }
 }
 
+fn generate_json_key(key: &StringLiteralOrTokenStream) -> proc_macro2::TokenStream {
+    match key {
+        StringLiteralOrTokenStream::StringLiteral(s) => {
+            let key = proc_macro2::Literal::string(s);
+            quote! {
+                #key.to_string()
+            }
+        }
+        StringLiteralOrTokenStream::TokenStream(token_stream) => {
+            quote! {
+                std::string::ToString::to_string(&(#token_stream))
+            }
+        }
+    }
+}
+
 fn json_parse_impl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let compiler_json = syn::parse_macro_input!(input as MacroJsonNode);
+    let MacroJsonInput(compiler_json) = syn::parse_macro_input!(input as MacroJsonInput);
+    proc_macro::TokenStream::from(expand_json(compiler_json, quote! {}))
+}
+
+/// The block evaluating to `compiler_json`, with `prelude` statements before it.
+fn expand_json(
+    compiler_json: MacroJsonNode,
+    prelude: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
     let result = proc_macro2::TokenStream::from(generate_json_call_site(compiler_json));
     let crate_name = get_call_site_crate_name("json");
-    proc_macro::TokenStream::from(quote! {
+    quote! {
         {
+            #prelude
             use #crate_name::{FromJson, ToJson};
             #result
         }
+    }
+}
+
+/// Parses the JSON text in `literal`, reporting syntax errors at the literal with the
+/// line and column in `source`.
+fn parse_json_text(literal: &syn::LitStr, text: &str, source: &str) -> syn::Result<MacroJsonNode> {
+    json_literal::parse_json_literal(text).map_err(|err| {
+        syn::Error::new(
+            literal.span(),
+            format!(
+                "{} at line {}, column {} of {}",
+                err.message, err.line, err.column, source
+            ),
+        )
     })
 }
 
+fn include_json_impl(path: &syn::LitStr) -> syn::Result<proc_macro2::TokenStream> {
+    let mut full_path = std::path::PathBuf::from(path.value());
+    if full_path.is_relative() {
+        if let Some(manifest_dir) = std::env::var_os("CARGO_MANIFEST_DIR") {
+            full_path = std::path::Path::new(&manifest_dir).join(full_path);
+        }
+    }
+    let text = std::fs::read_to_string(&full_path).map_err(|err| {
+        syn::Error::new(
+            path.span(),
+            format!("Cannot read {}: {}", full_path.display(), err),
+        )
+    })?;
+    let node = parse_json_text(path, &text, &path.value())?;
+
+    // Keeps the file a dependency of the crate so edits trigger a rebuild.
+    let full_path = full_path.to_string_lossy();
+    Ok(expand_json(
+        node,
+        quote! { const _: &[u8] = include_bytes!(#full_path); },
+    ))
+}
+
 #[proc_macro]
 pub fn json(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     json_parse_impl(input)
@@ -515,3 +1418,22 @@ pub fn json_array(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     };
     json_parse_impl(proc_macro::TokenStream::from(expanded))
 }
+
+/// Parses a JSON file at compile time and expands to the `JsonNode` it contains.
+/// Relative paths are resolved from the crate's `Cargo.toml` directory.
+#[proc_macro]
+pub fn include_json(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let path = parse_macro_input!(input as syn::LitStr);
+    proc_macro::TokenStream::from(
+        include_json_impl(&path).unwrap_or_else(syn::Error::into_compile_error),
+    )
+}
+
+/// Parses a JSON string literal at compile time and expands to the `JsonNode` it contains.
+#[proc_macro]
+pub fn json_str(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let literal = parse_macro_input!(input as syn::LitStr);
+    let expanded = parse_json_text(&literal, &literal.value(), "the JSON string")
+        .map(|node| expand_json(node, quote! {}));
+    proc_macro::TokenStream::from(expanded.unwrap_or_else(syn::Error::into_compile_error))
+}
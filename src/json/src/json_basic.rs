@@ -1,3 +1,5 @@
+use crate::json_error::{collect_results, JsonError};
+use crate::json_key::{collect_object_entries, object_from_entries, JsonKey};
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::hash::Hash;
@@ -128,8 +130,15 @@ impl IndexMut<usize> for JsonNode {
     }
 }
 
-pub trait FromJson {
-    fn from_json(json: &JsonNode) -> Self;
+pub trait FromJson: Sized {
+    fn try_from_json(json: &JsonNode) -> Result<Self, JsonError>;
+
+    fn from_json(json: &JsonNode) -> Self {
+        match Self::try_from_json(json) {
+            Ok(value) => value,
+            Err(err) => panic!("{}", err),
+        }
+    }
 }
 
 pub trait ToJson {
@@ -137,10 +146,10 @@ pub trait ToJson {
 }
 
 impl FromJson for String {
-    fn from_json(json: &JsonNode) -> Self {
+    fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
         match json {
-            JsonNode::String(s) => s.clone(),
-            _ => panic!("Cannot convert non-string type to string"),
+            JsonNode::String(s) => Ok(s.clone()),
+            _ => Err(JsonError::new("Cannot convert non-string type to string")),
         }
     }
 }
@@ -155,10 +164,14 @@ impl<T> FromJson for Vec<T>
 where
     T: FromJson,
 {
-    fn from_json(json: &JsonNode) -> Self {
+    fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
         match json {
-            JsonNode::Array(arr) => arr.iter().map(|x| T::from_json(x)).collect(),
-            _ => panic!("Cannot convert non-array type to array"),
+            JsonNode::Array(arr) => collect_results(
+                arr.iter()
+                    .enumerate()
+                    .map(|(idx, x)| T::try_from_json(x).map_err(|err| err.with_index(idx))),
+            ),
+            _ => Err(JsonError::new("Cannot convert non-array type to array")),
         }
     }
 }
@@ -185,27 +198,23 @@ where
     }
 }*/
 
-impl<T> FromJson for HashMap<String, T>
+impl<K, V> FromJson for HashMap<K, V>
 where
-    T: FromJson,
+    K: JsonKey + Eq + Hash,
+    V: FromJson,
 {
-    fn from_json(json: &JsonNode) -> Self {
-        match json {
-            JsonNode::Object(obj) => obj
-                .iter()
-                .map(|(k, v)| (k.clone(), T::from_json(&v)))
-                .collect(),
-            _ => panic!("Cannot convert non-object type to object"),
-        }
+    fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+        collect_object_entries(json)
     }
 }
 
-impl<T> ToJson for HashMap<String, T>
+impl<K, V> ToJson for HashMap<K, V>
 where
-    T: ToJson,
+    K: JsonKey,
+    V: ToJson,
 {
     fn to_json(&self) -> JsonNode {
-        JsonNode::Object(self.iter().map(|(k, v)| (k.clone(), v.to_json())).collect())
+        object_from_entries(self)
     }
 }
 
@@ -223,10 +232,10 @@ where
 }
 
 impl FromJson for bool {
-    fn from_json(json: &JsonNode) -> Self {
+    fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
         match json {
-            JsonNode::Boolean(b) => b.clone(),
-            _ => panic!("Cannot convert non-boolean type to boolean"),
+            JsonNode::Boolean(b) => Ok(*b),
+            _ => Err(JsonError::new("Cannot convert non-boolean type to boolean")),
         }
     }
 }
@@ -238,10 +247,10 @@ impl ToJson for bool {
 }
 
 impl FromJson for f64 {
-    fn from_json(json: &JsonNode) -> Self {
+    fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
         match json {
-            JsonNode::Number(n) => n.clone(),
-            _ => panic!("Cannot convert non-number type to number"),
+            JsonNode::Number(n) => Ok(*n),
+            _ => Err(JsonError::new("Cannot convert non-number type to number")),
         }
     }
 }
@@ -252,6 +261,24 @@ impl ToJson for f64 {
     }
 }
 
+impl FromJson for f32 {
+    fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+        match json {
+            JsonNode::Number(n) if n.is_finite() && (*n as f32).is_infinite() => Err(
+                JsonError::new(format!("Number {} is out of range for f32", n)),
+            ),
+            JsonNode::Number(n) => Ok(*n as f32),
+            _ => Err(JsonError::new("Cannot convert non-number type to number")),
+        }
+    }
+}
+
+impl ToJson for f32 {
+    fn to_json(&self) -> JsonNode {
+        JsonNode::Number(*self as f64)
+    }
+}
+
 pub struct JsonObjIterRef<'a> {
     map_iter: std::collections::hash_map::Iter<'a, String, JsonNode>,
 }
@@ -413,6 +440,20 @@ impl JsonNode {
         }
     }
 
+    pub fn get(&self, key: &str) -> Option<&JsonNode> {
+        match self {
+            JsonNode::Object(obj) => obj.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn get_index(&self, index: usize) -> Option<&JsonNode> {
+        match self {
+            JsonNode::Array(arr) => arr.get(index),
+            _ => None,
+        }
+    }
+
     pub fn contains_key(&self, key: &str) -> bool {
         match self {
             JsonNode::Object(obj) => obj.contains_key(key),
@@ -420,19 +461,69 @@ impl JsonNode {
         }
     }
 
+    /// The value at an RFC 6901 JSON pointer such as `/items/0/name`; `""` is the value
+    /// itself.
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonNode> {
+        let tokens = pointer_tokens(pointer).ok()?;
+        tokens.iter().try_fold(self, |node, token| match node {
+            JsonNode::Object(obj) => obj.get(token),
+            JsonNode::Array(arr) => arr.get(array_index(token)?),
+            _ => None,
+        })
+    }
+
     pub fn set_null(&mut self) {
         *self = JsonNode::Null;
     }
 }
 
+pub(crate) fn pointer_tokens(pointer: &str) -> Result<Vec<String>, JsonError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !pointer.starts_with('/') {
+        return Err(JsonError::new(format!(
+            "Invalid JSON pointer \"{}\"",
+            pointer
+        )));
+    }
+    Ok(pointer
+        .split('/')
+        .skip(1)
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+/// The array index a pointer token names: digits without leading zeros.
+pub(crate) fn array_index(token: &str) -> Option<usize> {
+    token
+        .parse::<usize>()
+        .ok()
+        .filter(|index| index.to_string() == token)
+}
+
 macro_rules! impl_from_and_to_json_for_number {
     ($($t:ty),*) => {
         $(
             impl FromJson for $t {
-                fn from_json(json: &JsonNode) -> Self {
+                fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
                     match json {
-                        JsonNode::Number(n) => n.clone() as $t,
-                        _ => panic!("Cannot convert non-number type to number"),
+                        // `MAX as f64 + 1.0` rounds to the first value out of range
+                        // (2^64 for `u64`), which `MAX as f64` alone may equal.
+                        JsonNode::Number(n)
+                            if n.fract() == 0.0
+                                && *n >= <$t>::MIN as f64
+                                && *n < <$t>::MAX as f64 + 1.0 =>
+                        {
+                            Ok(*n as $t)
+                        }
+                        JsonNode::Number(n) => Err(JsonError::new(format!(
+                            "Expected an integer from {} to {}, found {}",
+                            <$t>::MIN,
+                            <$t>::MAX,
+                            n
+                        ))),
+                        _ => Err(JsonError::new("Cannot convert non-number type to number")),
                     }
                 }
             }
@@ -446,12 +537,67 @@ macro_rules! impl_from_and_to_json_for_number {
     };
 }
 
-impl_from_and_to_json_for_number!(
-    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32
-);
+impl_from_and_to_json_for_number!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
 
-impl ToJson for &str {
+impl ToJson for str {
     fn to_json(&self) -> JsonNode {
         JsonNode::String(self.to_string())
     }
 }
+
+impl<T> ToJson for &T
+where
+    T: ToJson + ?Sized,
+{
+    fn to_json(&self) -> JsonNode {
+        (**self).to_json()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers_decode_whole_numbers_in_range() {
+        assert_eq!(u8::try_from_json(&JsonNode::Number(255.0)), Ok(255));
+        assert_eq!(i8::try_from_json(&JsonNode::Number(-128.0)), Ok(-128));
+        assert_eq!(
+            u64::try_from_json(&JsonNode::Number(9007199254740992.0)),
+            Ok(1 << 53)
+        );
+    }
+
+    #[test]
+    fn integers_reject_out_of_range_numbers() {
+        let err = u8::try_from_json(&JsonNode::Number(300.0)).unwrap_err();
+        assert_eq!(
+            err.message(),
+            "Expected an integer from 0 to 255, found 300"
+        );
+        assert!(u32::try_from_json(&JsonNode::Number(-1.0)).is_err());
+        assert!(i8::try_from_json(&JsonNode::Number(128.0)).is_err());
+        assert!(u64::try_from_json(&JsonNode::Number(18446744073709551616.0)).is_err());
+    }
+
+    #[test]
+    fn integers_reject_fractions_and_non_finite_numbers() {
+        let err = i32::try_from_json(&JsonNode::Number(1.5)).unwrap_err();
+        assert_eq!(
+            err.message(),
+            "Expected an integer from -2147483648 to 2147483647, found 1.5"
+        );
+        assert!(i64::try_from_json(&JsonNode::Number(f64::NAN)).is_err());
+        assert!(u64::try_from_json(&JsonNode::Number(f64::INFINITY)).is_err());
+    }
+
+    #[test]
+    fn f32_rejects_numbers_it_cannot_represent() {
+        assert_eq!(f32::try_from_json(&JsonNode::Number(1.5)), Ok(1.5));
+        assert!(f32::try_from_json(&JsonNode::Number(1e300)).is_err());
+        assert_eq!(
+            f32::try_from_json(&JsonNode::Number(f64::INFINITY)),
+            Ok(f32::INFINITY)
+        );
+    }
+}
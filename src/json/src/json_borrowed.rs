@@ -0,0 +1,463 @@
+use crate::json_basic::{array_index, pointer_tokens, JsonNode, ToJson};
+use crate::json_error::{ParseError, ParseErrorKind};
+use crate::json_impl::JsonToken;
+use crate::json_lexer::JsonLexer;
+use crate::json_options::{DuplicateKeyPolicy, ParseOptions};
+use crate::json_parser::{insert_member, JsonGrammar, MemberValue, ParserEvent, PartialContainer};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::ops::{Index, Range};
+
+/// A JSON value whose strings and keys borrow from the text it was parsed from, for
+/// reading a document without copying it. Only strings with escapes in them are
+/// allocated.
+///
+/// ```ignore
+/// let request = parse_borrowed(&body)?;
+/// let user = request.pointer("/user/name").and_then(JsonValue::as_str);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum JsonValue<'a> {
+    Object(HashMap<Cow<'a, str>, JsonValue<'a>>),
+    Array(Vec<JsonValue<'a>>),
+    String(Cow<'a, str>),
+    Number(f64),
+    Boolean(bool),
+    #[default]
+    Null,
+}
+
+impl<'a> JsonValue<'a> {
+    pub fn obj_iter(
+        &self,
+    ) -> Option<std::collections::hash_map::Iter<'_, Cow<'a, str>, JsonValue<'a>>> {
+        match self {
+            JsonValue::Object(obj) => Some(obj.iter()),
+            _ => None,
+        }
+    }
+
+    pub fn obj_into_iter(
+        self,
+    ) -> Option<std::collections::hash_map::IntoIter<Cow<'a, str>, JsonValue<'a>>> {
+        match self {
+            JsonValue::Object(obj) => Some(obj.into_iter()),
+            _ => None,
+        }
+    }
+
+    pub fn arr_iter(&self) -> Option<std::slice::Iter<'_, JsonValue<'a>>> {
+        match self {
+            JsonValue::Array(arr) => Some(arr.iter()),
+            _ => None,
+        }
+    }
+
+    pub fn arr_into_iter(self) -> Option<std::vec::IntoIter<JsonValue<'a>>> {
+        match self {
+            JsonValue::Array(arr) => Some(arr.into_iter()),
+            _ => None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            JsonValue::Object(obj) => obj.len(),
+            JsonValue::Array(arr) => arr.len(),
+            _ => panic!("Cannot get length of non-object or non-array type"),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, key: &str) -> Option<&JsonValue<'a>> {
+        match self {
+            JsonValue::Object(obj) => obj.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn get_index(&self, index: usize) -> Option<&JsonValue<'a>> {
+        match self {
+            JsonValue::Array(arr) => arr.get(index),
+            _ => None,
+        }
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        match self {
+            JsonValue::Object(obj) => obj.contains_key(key),
+            _ => panic!("Cannot check key in non-object type"),
+        }
+    }
+
+    /// The value at an RFC 6901 JSON pointer, as `JsonNode::pointer`.
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonValue<'a>> {
+        let tokens = pointer_tokens(pointer).ok()?;
+        tokens.iter().try_fold(self, |value, token| match value {
+            JsonValue::Object(obj) => obj.get(token.as_str()),
+            JsonValue::Array(arr) => arr.get(array_index(token)?),
+            _ => None,
+        })
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Copies the value into a `JsonNode`.
+    pub fn to_json_node(&self) -> JsonNode {
+        match self {
+            JsonValue::Object(obj) => JsonNode::Object(
+                obj.iter()
+                    .map(|(key, value)| (key.to_string(), value.to_json_node()))
+                    .collect(),
+            ),
+            JsonValue::Array(arr) => {
+                JsonNode::Array(arr.iter().map(JsonValue::to_json_node).collect())
+            }
+            JsonValue::String(s) => JsonNode::String(s.to_string()),
+            JsonValue::Number(n) => JsonNode::Number(*n),
+            JsonValue::Boolean(b) => JsonNode::Boolean(*b),
+            JsonValue::Null => JsonNode::Null,
+        }
+    }
+
+    /// Turns the value into a `JsonNode`, reusing the strings it already owns.
+    pub fn into_json_node(self) -> JsonNode {
+        match self {
+            JsonValue::Object(obj) => JsonNode::Object(
+                obj.into_iter()
+                    .map(|(key, value)| (key.into_owned(), value.into_json_node()))
+                    .collect(),
+            ),
+            JsonValue::Array(arr) => {
+                JsonNode::Array(arr.into_iter().map(JsonValue::into_json_node).collect())
+            }
+            JsonValue::String(s) => JsonNode::String(s.into_owned()),
+            JsonValue::Number(n) => JsonNode::Number(n),
+            JsonValue::Boolean(b) => JsonNode::Boolean(b),
+            JsonValue::Null => JsonNode::Null,
+        }
+    }
+
+    /// A copy that no longer borrows from the input.
+    pub fn into_owned(self) -> JsonValue<'static> {
+        match self {
+            JsonValue::Object(obj) => JsonValue::Object(
+                obj.into_iter()
+                    .map(|(key, value)| (Cow::Owned(key.into_owned()), value.into_owned()))
+                    .collect(),
+            ),
+            JsonValue::Array(arr) => {
+                JsonValue::Array(arr.into_iter().map(JsonValue::into_owned).collect())
+            }
+            JsonValue::String(s) => JsonValue::String(Cow::Owned(s.into_owned())),
+            JsonValue::Number(n) => JsonValue::Number(n),
+            JsonValue::Boolean(b) => JsonValue::Boolean(b),
+            JsonValue::Null => JsonValue::Null,
+        }
+    }
+}
+
+impl Display for JsonValue<'_> {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        self.to_json_node().fmt(f)
+    }
+}
+
+impl ToJson for JsonValue<'_> {
+    fn to_json(&self) -> JsonNode {
+        self.to_json_node()
+    }
+}
+
+impl<'a> Index<&str> for JsonValue<'a> {
+    type Output = JsonValue<'a>;
+
+    fn index(&self, index: &str) -> &Self::Output {
+        match self {
+            JsonValue::Object(obj) => obj.get(index).expect("Key not found"),
+            _ => panic!("Cannot index non-object type"),
+        }
+    }
+}
+
+impl<'a> Index<usize> for JsonValue<'a> {
+    type Output = JsonValue<'a>;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        match self {
+            JsonValue::Array(arr) => arr.get(index).expect("Index out of bounds"),
+            _ => panic!("Cannot index non-array type"),
+        }
+    }
+}
+
+impl<'a> MemberValue for JsonValue<'a> {
+    fn array(values: Vec<JsonValue<'a>>) -> JsonValue<'a> {
+        JsonValue::Array(values)
+    }
+
+    fn as_array_mut(&mut self) -> Option<&mut Vec<JsonValue<'a>>> {
+        match self {
+            JsonValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+/// Builds `JsonValue`s from events, as `TreeBuilder` does for `JsonNode`s.
+struct ValueBuilder<'a> {
+    containers: Vec<PartialContainer<JsonValue<'a>, Cow<'a, str>>>,
+    duplicate_keys: DuplicateKeyPolicy,
+}
+
+impl<'a> ValueBuilder<'a> {
+    /// Adds `event`, with the text of its string if it is a key or a string value.
+    fn push(&mut self, event: ParserEvent, string: Option<Cow<'a, str>>) -> Option<JsonValue<'a>> {
+        let value = match event {
+            ParserEvent::StartObject => {
+                self.containers
+                    .push((JsonValue::Object(HashMap::new()), None, HashSet::new()));
+                return None;
+            }
+            ParserEvent::StartArray => {
+                self.containers
+                    .push((JsonValue::Array(Vec::new()), None, HashSet::new()));
+                return None;
+            }
+            ParserEvent::Key(_) => {
+                if let Some((_, pending_key, _)) = self.containers.last_mut() {
+                    *pending_key = string;
+                }
+                return None;
+            }
+            ParserEvent::Value(JsonNode::String(_)) => {
+                JsonValue::String(string.unwrap_or_default())
+            }
+            ParserEvent::Value(JsonNode::Number(n)) => JsonValue::Number(n),
+            ParserEvent::Value(JsonNode::Boolean(b)) => JsonValue::Boolean(b),
+            ParserEvent::Value(_) => JsonValue::Null,
+            ParserEvent::EndArray | ParserEvent::EndObject => self.containers.pop()?.0,
+        };
+        match self.containers.last_mut() {
+            None => Some(value),
+            Some((JsonValue::Object(obj), key, collected)) => {
+                let key = key.take().unwrap_or_default();
+                insert_member(obj, collected, key, value, self.duplicate_keys);
+                None
+            }
+            Some((JsonValue::Array(arr), _, _)) => {
+                arr.push(value);
+                None
+            }
+            Some(_) => unreachable!(),
+        }
+    }
+}
+
+/// The text of the string token at `span`: borrowed from `input`, or decoded if it has
+/// escapes.
+fn string_at<'a>(input: &'a str, span: Range<usize>, options: ParseOptions) -> Cow<'a, str> {
+    let raw = &input[span.start + 1..span.end - 1];
+    if !raw.contains('\\') {
+        return Cow::Borrowed(raw);
+    }
+    match JsonLexer::new(&input[span])
+        .with_options(options)
+        .next_token()
+    {
+        Ok(Some((JsonToken::String(s), _))) => Cow::Owned(s),
+        _ => unreachable!("the string was read once already"),
+    }
+}
+
+/// Parses `input` into a `JsonValue` that borrows from it.
+pub fn parse_borrowed(input: &str) -> Result<JsonValue<'_>, ParseError> {
+    parse_borrowed_with_options(input, ParseOptions::new())
+}
+
+pub fn parse_borrowed_with_options(
+    input: &str,
+    options: ParseOptions,
+) -> Result<JsonValue<'_>, ParseError> {
+    let mut lexer = JsonLexer::new(input).with_options(options);
+    // Strings are taken from the input instead.
+    lexer.store_strings = false;
+    let mut grammar = JsonGrammar::new().with_options(options);
+    let mut builder = ValueBuilder {
+        containers: Vec::new(),
+        duplicate_keys: options.duplicate_keys,
+    };
+    loop {
+        let (token, position) = match lexer.next_token()? {
+            Some(token) => token,
+            None => {
+                return Err(ParseError::new(
+                    ParseErrorKind::UnexpectedEnd,
+                    lexer.position(),
+                ))
+            }
+        };
+        let span = position.offset..lexer.position().offset;
+        let (token, string) = match token {
+            JsonToken::String(_) => {
+                let string = string_at(input, span, options);
+                // The grammar needs the text of keys only to find duplicates.
+                let token = if options.duplicate_keys == DuplicateKeyPolicy::Error
+                    && grammar.expects_key()
+                {
+                    JsonToken::String(string.to_string())
+                } else {
+                    JsonToken::String(String::new())
+                };
+                (token, Some(string))
+            }
+            JsonToken::Identifier(name) => (
+                JsonToken::Identifier(name),
+                Some(Cow::Borrowed(&input[span])),
+            ),
+            token => (token, None),
+        };
+        let Some(event) = grammar.accept(token, position)? else {
+            continue;
+        };
+        if let Some(value) = builder.push(event, string) {
+            if !lexer.at_end()? {
+                return Err(ParseError::new(
+                    ParseErrorKind::TrailingCharacters,
+                    lexer.position(),
+                ));
+            }
+            return Ok(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::borrow::Cow;
+
+    const USER: &str = r#"{"name": "Yiran", "quote": "say \"hi\"", "tags": ["a", "b\n"], "age": 20, "ok": true, "none": null}"#;
+
+    #[test]
+    fn strings_borrow_unless_they_have_escapes() {
+        let value = parse_borrowed(USER).unwrap();
+        assert!(matches!(
+            value["name"],
+            JsonValue::String(Cow::Borrowed("Yiran"))
+        ));
+        assert!(matches!(&value["quote"], JsonValue::String(Cow::Owned(s)) if s == "say \"hi\""));
+        assert!(matches!(
+            value["tags"][0],
+            JsonValue::String(Cow::Borrowed("a"))
+        ));
+        assert_eq!(value["tags"][1].as_str(), Some("b\n"));
+    }
+
+    #[test]
+    fn members_can_be_looked_up() {
+        let value = parse_borrowed(USER).unwrap();
+        assert_eq!(value["age"], JsonValue::Number(20.0));
+        assert_eq!(value.get("ok"), Some(&JsonValue::Boolean(true)));
+        assert!(value.contains_key("none"));
+        assert_eq!(value.len(), 6);
+        assert!(!value.is_empty());
+        assert!(parse_borrowed("[]").unwrap().is_empty());
+    }
+
+    #[test]
+    fn pointers_resolve_like_json_node() {
+        let value = parse_borrowed(USER).unwrap();
+        assert_eq!(
+            value.pointer("/tags/1").and_then(JsonValue::as_str),
+            Some("b\n")
+        );
+        assert_eq!(value.pointer("/tags/01"), None);
+        assert_eq!(value.pointer(""), Some(&value));
+        let node = parse_json(USER).unwrap();
+        assert_eq!(node.pointer("/tags/0"), Some(&json!("a")));
+        assert_eq!(node.pointer("/missing"), None);
+    }
+
+    #[test]
+    fn containers_can_be_iterated() {
+        let value = parse_borrowed(USER).unwrap();
+        let tags: Vec<&str> = value["tags"]
+            .arr_iter()
+            .unwrap()
+            .filter_map(JsonValue::as_str)
+            .collect();
+        assert_eq!(tags, vec!["a", "b\n"]);
+        let mut keys: Vec<&str> = value
+            .obj_iter()
+            .unwrap()
+            .map(|(key, _)| key.as_ref())
+            .collect();
+        keys.sort();
+        assert_eq!(keys, vec!["age", "name", "none", "ok", "quote", "tags"]);
+    }
+
+    #[test]
+    fn converts_to_owned_values() {
+        let value = parse_borrowed(USER).unwrap();
+        let node = parse_json(USER).unwrap();
+        assert_eq!(value.to_json(), node);
+        let owned: JsonValue<'static> = {
+            let text = String::from(USER);
+            parse_borrowed(&text).unwrap().into_owned()
+        };
+        assert_eq!(owned, value);
+        assert_eq!(value.into_json_node(), node);
+    }
+
+    #[test]
+    fn options_apply() {
+        let json5 = ParseOptions::json5();
+        let value = parse_borrowed_with_options("{name: 'it\\'s', list: [1, 2,],}", json5).unwrap();
+        assert!(matches!(value.get("name"), Some(JsonValue::String(Cow::Owned(s))) if s == "it's"));
+        assert_eq!(value["list"].len(), 2);
+    }
+
+    #[test]
+    fn duplicate_keys_follow_the_policy() {
+        let input = r#"{"a": 1, "a": 2, "a": 3}"#;
+        let parse = |policy| {
+            parse_borrowed_with_options(input, ParseOptions::new().with_duplicate_keys(policy))
+        };
+        assert_eq!(
+            parse(DuplicateKeyPolicy::Collect).unwrap().to_json(),
+            json!({"a": [1, 2, 3]})
+        );
+        assert_eq!(
+            parse(DuplicateKeyPolicy::FirstWins).unwrap()["a"],
+            JsonValue::Number(1.0)
+        );
+        assert_eq!(
+            parse(DuplicateKeyPolicy::LastWins).unwrap()["a"],
+            JsonValue::Number(3.0)
+        );
+        let err = parse(DuplicateKeyPolicy::Error).unwrap_err();
+        assert!(matches!(err.kind(), ParseErrorKind::DuplicateKey { key, .. } if key == "a"));
+    }
+
+    #[test]
+    fn incomplete_or_trailing_input_is_an_error() {
+        assert!(matches!(
+            parse_borrowed("[1] 2").unwrap_err().kind(),
+            ParseErrorKind::TrailingCharacters
+        ));
+        assert!(matches!(
+            parse_borrowed("[\"a\"").unwrap_err().kind(),
+            ParseErrorKind::UnexpectedEnd
+        ));
+    }
+}
@@ -0,0 +1,637 @@
+use crate::json_basic::{array_index, pointer_tokens, JsonNode};
+use crate::json_error::{JsonError, ParseError, ParseErrorKind};
+use crate::json_impl::JsonToken;
+use crate::json_lexer::JsonLexer;
+use crate::json_options::{DuplicateKeyPolicy, ParseOptions};
+use crate::json_parser::{parse_str_with_options, JsonGrammar, ParserEvent};
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CstKind {
+    Object,
+    Array,
+    Scalar,
+}
+
+/// A value and the byte ranges of the tokens it is made of. Whitespace and comments
+/// are whatever lies between them.
+struct CstNode {
+    kind: CstKind,
+    span: Range<usize>,
+    entries: Vec<CstEntry>,
+}
+
+/// A member of an object or an element of an array.
+struct CstEntry {
+    key: Option<(String, Range<usize>)>,
+    value: CstNode,
+    /// The `,` after the entry, if there is one.
+    comma: Option<Range<usize>>,
+}
+
+impl CstEntry {
+    fn start(&self) -> usize {
+        match &self.key {
+            Some((_, span)) => span.start,
+            None => self.value.span.start,
+        }
+    }
+
+    fn end(&self) -> usize {
+        match &self.comma {
+            Some(comma) => comma.end,
+            None => self.value.span.end,
+        }
+    }
+}
+
+impl CstNode {
+    /// The entry `token` names. Of duplicate keys the last one counts unless the first
+    /// wins when parsing.
+    fn entry_index(&self, token: &str, options: &ParseOptions) -> Option<usize> {
+        let is_key = |entry: &CstEntry| matches!(&entry.key, Some((key, _)) if key == token);
+        match self.kind {
+            CstKind::Object if options.duplicate_keys == DuplicateKeyPolicy::FirstWins => {
+                self.entries.iter().position(is_key)
+            }
+            CstKind::Object => self.entries.iter().rposition(is_key),
+            CstKind::Array => array_index(token).filter(|&index| index < self.entries.len()),
+            CstKind::Scalar => None,
+        }
+    }
+
+    fn find(&self, tokens: &[String], options: &ParseOptions) -> Option<&CstNode> {
+        let mut node = self;
+        for token in tokens {
+            node = &node.entries[node.entry_index(token, options)?].value;
+        }
+        Some(node)
+    }
+}
+
+struct Frame {
+    kind: CstKind,
+    start: usize,
+    entries: Vec<CstEntry>,
+    key: Option<(String, Range<usize>)>,
+}
+
+fn parse_cst(text: &str, options: ParseOptions) -> Result<CstNode, ParseError> {
+    let mut lexer = JsonLexer::new(text).with_options(options);
+    let mut grammar = JsonGrammar::new().with_options(options);
+    let mut frames: Vec<Frame> = Vec::new();
+    loop {
+        let (token, position) = match lexer.next_token()? {
+            Some(token) => token,
+            None => {
+                return Err(ParseError::new(
+                    ParseErrorKind::UnexpectedEnd,
+                    lexer.position(),
+                ))
+            }
+        };
+        let span = position.offset..lexer.position().offset;
+        let is_comma = matches!(token, JsonToken::Comma);
+        let (kind, span, entries) = match grammar.accept(token, position)? {
+            None => {
+                if is_comma {
+                    if let Some(entry) = frames.last_mut().and_then(|f| f.entries.last_mut()) {
+                        entry.comma = Some(span);
+                    }
+                }
+                continue;
+            }
+            Some(event @ (ParserEvent::StartObject | ParserEvent::StartArray)) => {
+                frames.push(Frame {
+                    kind: match event {
+                        ParserEvent::StartObject => CstKind::Object,
+                        _ => CstKind::Array,
+                    },
+                    start: span.start,
+                    entries: Vec::new(),
+                    key: None,
+                });
+                continue;
+            }
+            Some(ParserEvent::Key(key)) => {
+                if let Some(frame) = frames.last_mut() {
+                    frame.key = Some((key, span));
+                }
+                continue;
+            }
+            Some(ParserEvent::Value(_)) => (CstKind::Scalar, span, Vec::new()),
+            Some(ParserEvent::EndArray | ParserEvent::EndObject) => {
+                let Some(frame) = frames.pop() else {
+                    unreachable!("the grammar only ends containers that were started")
+                };
+                (frame.kind, frame.start..span.end, frame.entries)
+            }
+        };
+        let node = CstNode {
+            kind,
+            span,
+            entries,
+        };
+        match frames.last_mut() {
+            Some(parent) => parent.entries.push(CstEntry {
+                key: parent.key.take(),
+                value: node,
+                comma: None,
+            }),
+            None if lexer.at_end()? => return Ok(node),
+            None => {
+                return Err(ParseError::new(
+                    ParseErrorKind::TrailingCharacters,
+                    lexer.position(),
+                ))
+            }
+        }
+    }
+}
+
+/// A JSON document that can be edited without losing its formatting. Whitespace,
+/// comments, key order and the spelling of numbers and strings are kept; an edit
+/// rewrites only the value, member or element it touches, and the rest of the text is
+/// written back unchanged.
+///
+/// ```ignore
+/// let mut config = JsonDocument::parse_with_options(text, ParseOptions::jsonc())?;
+/// config.set("/server/port", &json!(8080))?;
+/// config.remove("/debug")?;
+/// std::fs::write(path, config.as_str())?;
+/// ```
+pub struct JsonDocument {
+    text: String,
+    options: ParseOptions,
+    root: CstNode,
+}
+
+impl JsonDocument {
+    pub fn parse(text: impl Into<String>) -> Result<JsonDocument, ParseError> {
+        JsonDocument::parse_with_options(text, ParseOptions::new())
+    }
+
+    /// Parses `text`, accepting the extensions `options` turns on. Comments need
+    /// `ParseOptions::with_comments` to be read, and are kept like whitespace.
+    pub fn parse_with_options(
+        text: impl Into<String>,
+        options: ParseOptions,
+    ) -> Result<JsonDocument, ParseError> {
+        let text = text.into();
+        let root = parse_cst(&text, options)?;
+        Ok(JsonDocument {
+            text,
+            options,
+            root,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn into_string(self) -> String {
+        self.text
+    }
+
+    /// The text of the value at `pointer`, exactly as it is written.
+    pub fn source(&self, pointer: &str) -> Option<&str> {
+        let node = self
+            .root
+            .find(&pointer_tokens(pointer).ok()?, &self.options)?;
+        Some(&self.text[node.span.clone()])
+    }
+
+    /// The value at `pointer`.
+    pub fn get(&self, pointer: &str) -> Option<JsonNode> {
+        parse_str_with_options(self.source(pointer)?, self.options).ok()
+    }
+
+    /// Replaces the value at `pointer`. If the pointer names a key that its object
+    /// does not have, the member is added as by `insert`.
+    pub fn set(&mut self, pointer: &str, value: &JsonNode) -> Result<(), JsonError> {
+        let tokens = pointer_tokens(pointer)?;
+        let Some((last, parent_tokens)) = tokens.split_last() else {
+            let span = self.root.span.clone();
+            let pretty = self.text[span.clone()].contains('\n');
+            let text = self.render(value, pretty, line_indent(&self.text, span.start));
+            return self.splice(span, text);
+        };
+        let parent = self.container(pointer, parent_tokens)?;
+        match parent.entry_index(last, &self.options) {
+            Some(index) => {
+                let span = parent.entries[index].value.span.clone();
+                let pretty = self.separator(parent).contains('\n');
+                let text = self.render(value, pretty, line_indent(&self.text, span.start));
+                self.splice(span, text)
+            }
+            None if parent.kind == CstKind::Object => {
+                let (range, text) = self.insertion(parent, parent.entries.len(), Some(last), value);
+                self.splice(range, text)
+            }
+            None => Err(JsonError::at_pointer(pointer, "Index out of bounds")),
+        }
+    }
+
+    /// Inserts `value` into an array before the index `pointer` names, or at the end
+    /// for `-`; or adds a member to an object, which must not have the key yet.
+    pub fn insert(&mut self, pointer: &str, value: &JsonNode) -> Result<(), JsonError> {
+        let tokens = pointer_tokens(pointer)?;
+        let Some((last, parent_tokens)) = tokens.split_last() else {
+            return Err(JsonError::at_pointer(pointer, "Cannot insert at the root"));
+        };
+        let parent = self.container(pointer, parent_tokens)?;
+        let len = parent.entries.len();
+        let (index, key) = match parent.kind {
+            CstKind::Object if parent.entry_index(last, &self.options).is_some() => {
+                return Err(JsonError::at_pointer(pointer, "The key already exists"))
+            }
+            CstKind::Object => (len, Some(last.as_str())),
+            _ if last == "-" => (len, None),
+            _ => match array_index(last) {
+                Some(index) if index <= len => (index, None),
+                _ => return Err(JsonError::at_pointer(pointer, "Index out of bounds")),
+            },
+        };
+        let (range, text) = self.insertion(parent, index, key, value);
+        self.splice(range, text)
+    }
+
+    /// Removes the member or element at `pointer`, with its comma. Comments before
+    /// and after it are kept.
+    pub fn remove(&mut self, pointer: &str) -> Result<(), JsonError> {
+        let tokens = pointer_tokens(pointer)?;
+        let Some((last, parent_tokens)) = tokens.split_last() else {
+            return Err(JsonError::at_pointer(pointer, "Cannot remove the root"));
+        };
+        let parent = self.container(pointer, parent_tokens)?;
+        let index = parent
+            .entry_index(last, &self.options)
+            .ok_or_else(|| JsonError::at_pointer(pointer, "No value at this pointer"))?;
+        let (range, text) = self.removal(parent, index);
+        self.splice(range, text)
+    }
+
+    fn container(&self, pointer: &str, tokens: &[String]) -> Result<&CstNode, JsonError> {
+        match self.root.find(tokens, &self.options) {
+            Some(node) if node.kind != CstKind::Scalar => Ok(node),
+            Some(_) => Err(JsonError::at_pointer(
+                pointer,
+                "The parent is not an object or array",
+            )),
+            None => Err(JsonError::at_pointer(pointer, "The parent does not exist")),
+        }
+    }
+
+    /// Replaces `range` and parses the result, leaving the document as it was if the
+    /// edit does not produce valid JSON.
+    fn splice(&mut self, range: Range<usize>, replacement: String) -> Result<(), JsonError> {
+        let mut text = self.text.clone();
+        text.replace_range(range, &replacement);
+        self.root = parse_cst(&text, self.options).map_err(|err| {
+            JsonError::new(format!("The edit made the document invalid: {}", err))
+        })?;
+        self.text = text;
+        Ok(())
+    }
+
+    /// What goes between two entries of `container` when one is added: a line break
+    /// and the indentation the entries already have, or a space.
+    fn separator(&self, container: &CstNode) -> String {
+        let entries = &container.entries;
+        let between = match (entries.first(), entries.get(1)) {
+            (Some(first), Some(second)) => &self.text[first.end()..second.start()],
+            (Some(first), None) => &self.text[container.span.start + 1..first.start()],
+            _ => "",
+        };
+        match between.rfind('\n') {
+            Some(newline) => {
+                let line = &between[newline + 1..];
+                format!("\n{}", &line[..line.len() - line.trim_start().len()])
+            }
+            None if between.is_empty() => String::new(),
+            None => " ".to_string(),
+        }
+    }
+
+    fn insertion(
+        &self,
+        container: &CstNode,
+        index: usize,
+        key: Option<&str>,
+        value: &JsonNode,
+    ) -> (Range<usize>, String) {
+        let separator = self.separator(container);
+        let pretty = separator.contains('\n');
+        let indent = match separator.rfind('\n') {
+            Some(newline) => separator[newline + 1..].to_string(),
+            None => line_indent(&self.text, container.span.start).to_string(),
+        };
+        let entry = match key {
+            Some(key) => format!("{}: {}", quote(key), self.render(value, pretty, &indent)),
+            None => self.render(value, pretty, &indent),
+        };
+
+        let entries = &container.entries;
+        let close = container.span.end - 1;
+        if entries.is_empty() {
+            let inner = container.span.start + 1..close;
+            return if self.text[inner.clone()].trim().is_empty() {
+                (inner, entry)
+            } else {
+                (close..close, format!("{} ", entry))
+            };
+        }
+        if index < entries.len() {
+            let start = entries[index].start();
+            return (start..start, format!("{},{}", entry, separator));
+        }
+        let last = &entries[entries.len() - 1];
+        match &last.comma {
+            // Keep the trailing comma style.
+            Some(comma) => (comma.end..comma.end, format!("{}{},", separator, entry)),
+            None => {
+                let end = last.value.span.end;
+                (end..end, format!(",{}{}", separator, entry))
+            }
+        }
+    }
+
+    /// The range to replace to remove an entry of `container`, and what to replace it
+    /// with. Comments around the entry are kept.
+    fn removal(&self, container: &CstNode, index: usize) -> (Range<usize>, String) {
+        let text = self.text.as_str();
+        let entries = &container.entries;
+        let entry = &entries[index];
+        if entries.len() == 1 {
+            let inner = container.span.start + 1..container.span.end - 1;
+            let before = &text[inner.start..entry.start()];
+            let after = &text[entry.end()..inner.end];
+            if before.trim().is_empty() && after.trim().is_empty() {
+                return (inner, String::new());
+            }
+            return (entry.start()..entry.end(), String::new());
+        }
+        // Where the line the entry starts on would start without the entry's indentation.
+        let line_start = text[..entry.start()].trim_end_matches([' ', '\t']).len();
+        match &entry.comma {
+            // Take the comma and the spaces after it, and the whole line if the entry
+            // has it to itself.
+            Some(comma) => {
+                let rest = &text[comma.end..];
+                let end = text.len() - rest.trim_start_matches([' ', '\t']).len();
+                let newline = match &text[end..] {
+                    rest if rest.starts_with("\r\n") => 2,
+                    rest if rest.starts_with('\n') => 1,
+                    _ => 0,
+                };
+                if newline > 0 && text[..line_start].ends_with('\n') {
+                    (line_start..end + newline, String::new())
+                } else {
+                    (entry.start()..end, String::new())
+                }
+            }
+            // The last entry: take the comma before it instead, and the line break
+            // before the entry, keeping what lies between.
+            None => {
+                let previous = &entries[index - 1];
+                let end = previous.value.span.end;
+                let comma = previous.comma.clone().unwrap_or(end..end);
+                let mut start = line_start;
+                if start > comma.end && text[..start].ends_with('\n') {
+                    start -= 1;
+                    if start > comma.end && text[..start].ends_with('\r') {
+                        start -= 1;
+                    }
+                }
+                (comma.start..entry.end(), text[comma.end..start].to_string())
+            }
+        }
+    }
+
+    /// `value` as text: on one line, or laid out over several lines with `indent` as
+    /// the indentation of the line it starts on.
+    fn render(&self, value: &JsonNode, pretty: bool, indent: &str) -> String {
+        let inner_indent = format!("{}{}", indent, indent_unit(&self.text));
+        let items: Vec<String> = match value {
+            JsonNode::Object(obj) if !obj.is_empty() => {
+                let mut keys: Vec<&String> = obj.keys().collect();
+                keys.sort();
+                keys.into_iter()
+                    .map(|key| {
+                        let value = self.render(&obj[key], pretty, &inner_indent);
+                        format!("{}: {}", quote(key), value)
+                    })
+                    .collect()
+            }
+            JsonNode::Array(arr) if !arr.is_empty() => arr
+                .iter()
+                .map(|value| self.render(value, pretty, &inner_indent))
+                .collect(),
+            JsonNode::String(s) => return quote(s),
+            _ => return value.dump_compact(false),
+        };
+        let (open, close) = match value {
+            JsonNode::Object(_) => ('{', '}'),
+            _ => ('[', ']'),
+        };
+        if pretty {
+            let separator = format!(",\n{}", inner_indent);
+            format!(
+                "{}\n{}{}\n{}{}",
+                open,
+                inner_indent,
+                items.join(&separator),
+                indent,
+                close
+            )
+        } else {
+            format!("{}{}{}", open, items.join(", "), close)
+        }
+    }
+}
+
+impl Display for JsonDocument {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// `s` as a JSON string, escaping only what JSON requires so that non-ASCII text
+/// stays readable.
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The whitespace at the start of the line `offset` is on.
+fn line_indent(text: &str, offset: usize) -> &str {
+    let line_start = text[..offset].rfind('\n').map_or(0, |newline| newline + 1);
+    let line = &text[line_start..offset];
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// The indentation of the first indented line, which is taken to be one level.
+fn indent_unit(text: &str) -> &str {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("  ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    const SETTINGS: &str = r#"// Server settings
+{
+    "port": 8080, // default
+    "ratio": 1.50,
+    "hosts": ["a", "b"],
+    "tls": {
+        "enabled": false
+    }
+}
+"#;
+
+    fn settings() -> JsonDocument {
+        JsonDocument::parse_with_options(SETTINGS, ParseOptions::jsonc()).unwrap()
+    }
+
+    fn jsonc(text: &str) -> JsonDocument {
+        JsonDocument::parse_with_options(text, ParseOptions::jsonc()).unwrap()
+    }
+
+    #[test]
+    fn reads_values_and_their_source() {
+        let config = settings();
+        assert_eq!(config.as_str(), SETTINGS);
+        assert_eq!(config.source("/ratio"), Some("1.50"));
+        assert_eq!(config.get("/hosts/1"), Some(json!("b")));
+        assert_eq!(config.get("/tls/missing"), None);
+    }
+
+    #[test]
+    fn edits_keep_comments_and_layout() {
+        let mut config = settings();
+        config.set("/port", &json!(9090)).unwrap();
+        config.insert("/hosts/1", &json!("c")).unwrap();
+        config.insert("/hosts/-", &json!("d")).unwrap();
+        config
+            .set("/tls/cert", &json!({"path": "x.pem", "days": 30}))
+            .unwrap();
+        config.remove("/ratio").unwrap();
+        assert_eq!(
+            config.to_string(),
+            r#"// Server settings
+{
+    "port": 9090, // default
+    "hosts": ["a", "c", "b", "d"],
+    "tls": {
+        "enabled": false,
+        "cert": {
+            "days": 30,
+            "path": "x.pem"
+        }
+    }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn removing_every_member_leaves_empty_containers() {
+        let mut config = settings();
+        config.remove("/tls/enabled").unwrap();
+        config.remove("/hosts/0").unwrap();
+        assert!(config.as_str().contains("\"tls\": {}"));
+        assert!(config.as_str().contains("\"hosts\": [\"b\"]"));
+        config.set("/tls/on", &json!(true)).unwrap();
+        assert!(config.as_str().contains("\"tls\": {\"on\": true}"));
+    }
+
+    #[test]
+    fn trailing_commas_are_kept() {
+        let mut trailing = jsonc("[1,\n 2,\n]");
+        trailing.insert("/-", &json!(3)).unwrap();
+        assert_eq!(trailing.as_str(), "[1,\n 2,\n 3,\n]");
+        trailing.remove("/2").unwrap();
+        assert_eq!(trailing.as_str(), "[1,\n 2,\n]");
+    }
+
+    #[test]
+    fn removing_members_keeps_comments_on_other_lines() {
+        let mut commented = jsonc(
+            "{\n    // the port\n    \"port\": 1,\n    // the host\n    \"host\": \"a\", // local\n    /* last */\n    \"tls\": false\n}",
+        );
+        commented.remove("/port").unwrap();
+        assert_eq!(
+            commented.as_str(),
+            "{\n    // the port\n    // the host\n    \"host\": \"a\", // local\n    /* last */\n    \"tls\": false\n}"
+        );
+        commented.remove("/tls").unwrap();
+        assert_eq!(
+            commented.as_str(),
+            "{\n    // the port\n    // the host\n    \"host\": \"a\" // local\n    /* last */\n}"
+        );
+    }
+
+    #[test]
+    fn removing_elements_keeps_inline_comments() {
+        let mut inline = jsonc("[1, /* one */ 2, /* two */ 3]");
+        inline.remove("/1").unwrap();
+        assert_eq!(inline.as_str(), "[1, /* one */ /* two */ 3]");
+        inline.remove("/1").unwrap();
+        assert_eq!(inline.as_str(), "[1 /* one */ /* two */]");
+    }
+
+    #[test]
+    fn invalid_edits_are_errors() {
+        let mut config = settings();
+        let err = config.insert("/port", &json!(1)).unwrap_err();
+        assert_eq!(err.pointer(), "/port");
+        assert!(config.set("/port/x", &json!(1)).is_err());
+        assert!(config.remove("/hosts/9").is_err());
+        assert!(config.set("/missing/x", &json!(1)).is_err());
+        assert!(JsonDocument::parse("{} x").is_err());
+    }
+
+    #[test]
+    fn setting_the_root_replaces_the_document() {
+        let mut document = JsonDocument::parse("{\"a\":1}").unwrap();
+        document.set("/b", &json!([1, 2])).unwrap();
+        document.set("", &json!([])).unwrap();
+        assert_eq!(document.as_str(), "[]");
+    }
+
+    #[test]
+    fn inserted_strings_are_not_ascii_escaped() {
+        let mut document = JsonDocument::parse("{}").unwrap();
+        document
+            .set("/café", &json!({"note": "naïve \"quote\"\n\u{1}"}))
+            .unwrap();
+        assert_eq!(
+            document.as_str(),
+            "{\"café\": {\"note\": \"naïve \\\"quote\\\"\\n\\u0001\"}}"
+        );
+        assert_eq!(
+            document.get("/café/note"),
+            Some(json!("naïve \"quote\"\n\u{1}"))
+        );
+    }
+}
@@ -5,6 +5,7 @@ struct JsonDumper {
     indent_size: usize,
     result: String,
     escape_string: bool,
+    compact: bool,
 }
 
 pub fn dump_json_node(json: &JsonNode, indent: usize, escape_string: bool) -> String {
@@ -13,12 +14,36 @@ pub fn dump_json_node(json: &JsonNode, indent: usize, escape_string: bool) -> St
         indent_size: indent,
         result: String::new(),
         escape_string,
+        compact: false,
+    };
+    dumper.process(json);
+    dumper.result
+}
+
+/// Dumps `json` on a single line, without any whitespace between tokens.
+pub fn dump_json_node_compact(json: &JsonNode, escape_string: bool) -> String {
+    let mut dumper = JsonDumper {
+        current_indent: 0,
+        indent_size: 0,
+        result: String::new(),
+        escape_string,
+        compact: true,
     };
     dumper.process(json);
     dumper.result
 }
 
 impl JsonDumper {
+    fn push_string(&mut self, s: &str) {
+        self.result.push('"');
+        if self.escape_string {
+            self.result.push_str(&translate_escape(s));
+        } else {
+            self.result.push_str(s);
+        }
+        self.result.push('"');
+    }
+
     fn indent(&mut self) {
         for _ in 0..self.current_indent {
             self.result.push(' ');
@@ -32,6 +57,20 @@ impl JsonDumper {
                     return;
                 }
 
+                if self.compact {
+                    self.result.push('{');
+                    for (idx, (key, value)) in obj.iter().enumerate() {
+                        if idx > 0 {
+                            self.result.push(',');
+                        }
+                        self.push_string(key);
+                        self.result.push(':');
+                        self.process(value);
+                    }
+                    self.result.push('}');
+                    return;
+                }
+
                 self.result.push_str("{\n");
                 self.current_indent += self.indent_size;
                 for (idx, (key, value)) in obj.iter().enumerate() {
@@ -56,6 +95,18 @@ impl JsonDumper {
                     return;
                 }
 
+                if self.compact {
+                    self.result.push('[');
+                    for (idx, value) in arr.iter().enumerate() {
+                        if idx > 0 {
+                            self.result.push(',');
+                        }
+                        self.process(value);
+                    }
+                    self.result.push(']');
+                    return;
+                }
+
                 self.result.push_str("[\n");
                 self.current_indent += self.indent_size;
                 for (idx, value) in arr.iter().enumerate() {
@@ -71,14 +122,10 @@ impl JsonDumper {
                 self.indent();
                 self.result.push(']');
             }
-            JsonNode::String(s) => {
-                self.result.push('"');
-                if self.escape_string {
-                    self.result.push_str(&translate_escape(s));
-                } else {
-                    self.result.push_str(s);
-                }
-                self.result.push('"');
+            JsonNode::String(s) => self.push_string(s),
+            // JSON has no NaN or Infinity, so they are written as `null`.
+            JsonNode::Number(n) if !n.is_finite() => {
+                self.result.push_str("null");
             }
             JsonNode::Number(n) => {
                 self.result.push_str(&n.to_string());
@@ -105,11 +152,29 @@ fn translate_escape(s: &str) -> String {
             '\u{8}' => result.push_str("\\b"),
             '\u{c}' => result.push_str("\\f"),
             '/' => result.push_str("\\/"),
-            utf_char if !utf_char.is_ascii() => {
-                result.push_str(&format!("\\u{:04x}", utf_char as u32));
+            // Characters outside the BMP are escaped as a UTF-16 surrogate pair.
+            utf_char if !utf_char.is_ascii() || utf_char.is_ascii_control() => {
+                for unit in utf_char.encode_utf16(&mut [0; 2]) {
+                    result.push_str(&format!("\\u{:04x}", unit));
+                }
             }
             _ => result.push(c),
         }
     }
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn non_finite_numbers_dump_as_null() {
+        let value = json!([f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 1.5]);
+        assert_eq!(value.dump_compact(false), "[null,null,null,1.5]");
+        assert_eq!(
+            parse_json(&value.dump(2, false)),
+            Some(json!([null, null, null, 1.5]))
+        );
+    }
+}
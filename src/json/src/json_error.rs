@@ -0,0 +1,366 @@
+use std::fmt::{Display, Formatter};
+
+/// Error produced when a `JsonNode` cannot be converted into a Rust value.
+///
+/// `pointer` is the RFC 6901 JSON pointer of the offending node, relative to the
+/// value `try_from_json` was called on. It is built up while the error bubbles out
+/// of nested conversions.
+///
+/// Validation failures are reported together: such an error holds every violation,
+/// each with its own pointer, and `violations` lists them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonError {
+    pointer: String,
+    message: String,
+    violations: Vec<JsonError>,
+}
+
+impl JsonError {
+    pub fn new(message: impl Into<String>) -> JsonError {
+        JsonError {
+            pointer: String::new(),
+            message: message.into(),
+            violations: Vec::new(),
+        }
+    }
+
+    /// Combines several errors into one; a single error is returned as is, and an
+    /// empty list gives a plain "Validation failed".
+    pub fn from_violations(mut violations: Vec<JsonError>) -> JsonError {
+        match violations.len() {
+            0 => return JsonError::new("Validation failed"),
+            1 => return violations.pop().unwrap(),
+            _ => {}
+        }
+        JsonError {
+            pointer: String::new(),
+            message: format!("{} validation errors", violations.len()),
+            violations,
+        }
+    }
+
+    pub fn missing_field(field: &str) -> JsonError {
+        JsonError::new(format!("Missing field \"{}\"", field))
+    }
+
+    pub fn missing_element(index: usize) -> JsonError {
+        JsonError::new(format!("Missing element at index {}", index))
+    }
+
+    /// Reports keys of an object that are not in `expected`, suggesting the closest
+    /// expected key for each one that looks like a typo.
+    pub fn unknown_fields<'a>(
+        unknown: impl IntoIterator<Item = &'a str>,
+        expected: &[&str],
+    ) -> JsonError {
+        let mut unknown: Vec<&str> = unknown.into_iter().collect();
+        unknown.sort_unstable();
+
+        let listed: Vec<String> = unknown
+            .iter()
+            .map(|key| match suggest(key, expected) {
+                Some(suggestion) => format!("\"{}\" (did you mean \"{}\"?)", key, suggestion),
+                None => format!("\"{}\"", key),
+            })
+            .collect();
+
+        let expected: Vec<String> = expected.iter().map(|key| format!("\"{}\"", key)).collect();
+
+        JsonError::new(format!(
+            "Unknown field{} {}, expected {}",
+            if unknown.len() == 1 { "" } else { "s" },
+            listed.join(", "),
+            if expected.is_empty() {
+                "no fields".to_string()
+            } else {
+                format!("one of {}", expected.join(", "))
+            }
+        ))
+    }
+
+    /// Prefixes the pointer with an object key.
+    pub fn with_key(mut self, key: &str) -> JsonError {
+        let escaped = key.replace('~', "~0").replace('/', "~1");
+        self.prefix_pointer(&format!("/{}", escaped));
+        self
+    }
+
+    /// Prefixes the pointer with an array index.
+    pub fn with_index(mut self, index: usize) -> JsonError {
+        self.prefix_pointer(&format!("/{}", index));
+        self
+    }
+
+    /// An error at `pointer`, a JSON Pointer such as `/users/0/name`.
+    pub(crate) fn at_pointer(pointer: &str, message: impl Into<String>) -> JsonError {
+        let segments: Vec<&str> = pointer.split('/').skip(1).collect();
+        segments
+            .into_iter()
+            .rev()
+            .fold(JsonError::new(message), |err, segment| {
+                err.with_key(&segment.replace("~1", "/").replace("~0", "~"))
+            })
+    }
+
+    fn prefix_pointer(&mut self, prefix: &str) {
+        self.pointer.insert_str(0, prefix);
+        for violation in &mut self.violations {
+            violation.prefix_pointer(prefix);
+        }
+    }
+
+    pub fn pointer(&self) -> &str {
+        &self.pointer
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The individual errors this error is made of, or the error itself if it is not
+    /// a combination of several.
+    pub fn violations(&self) -> &[JsonError] {
+        if self.violations.is_empty() {
+            std::slice::from_ref(self)
+        } else {
+            &self.violations
+        }
+    }
+
+    /// Like `violations`, but takes the errors out, for merging into another list.
+    pub fn into_violations(self) -> Vec<JsonError> {
+        if self.violations.is_empty() {
+            vec![self]
+        } else {
+            self.violations
+        }
+    }
+}
+
+/// Collects the results of decoding several values, reporting every error instead of
+/// only the first.
+pub(crate) fn collect_results<T, C>(
+    results: impl IntoIterator<Item = Result<T, JsonError>>,
+) -> Result<C, JsonError>
+where
+    C: FromIterator<T>,
+{
+    let mut values = Vec::new();
+    let mut violations = Vec::new();
+    for result in results {
+        match result {
+            Ok(value) => values.push(value),
+            Err(err) => violations.extend(err.into_violations()),
+        }
+    }
+    if violations.is_empty() {
+        Ok(values.into_iter().collect())
+    } else {
+        Err(JsonError::from_violations(violations))
+    }
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        if self.pointer.is_empty() {
+            write!(f, "{}", self.message)?;
+        } else {
+            write!(f, "{}: {}", self.pointer, self.message)?;
+        }
+        for violation in &self.violations {
+            write!(f, "\n  {}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+fn suggest<'a>(key: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let threshold = std::cmp::max(1, key.chars().count() / 3);
+    candidates
+        .iter()
+        .map(|candidate| (edit_distance(key, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Optimal string alignment distance: Levenshtein plus adjacent transpositions,
+/// so that "nmae" is a single edit away from "name".
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+
+    rows[a.len()][b.len()]
+}
+
+/// A location in JSON text. `offset` counts bytes from the start of the input;
+/// `line` and `column` are 1-based, with columns counted in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn new() -> Position {
+        Position {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// Moves past `c`.
+    pub fn advance(&mut self, c: char) {
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Position {
+        Position::new()
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// What went wrong while reading JSON text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    UnexpectedCharacter(char),
+    UnexpectedEnd,
+    /// A token that is valid JSON but not allowed here, such as a `,` after `[`.
+    UnexpectedToken {
+        found: String,
+        expected: &'static str,
+    },
+    InvalidLiteral(String),
+    InvalidNumber(String),
+    InvalidEscape,
+    ControlCharacterInString,
+    InvalidUtf8,
+    /// Something other than whitespace after the value.
+    TrailingCharacters,
+    Io(String),
+    /// Valid JSON that `FromJson` could not convert.
+    Conversion(JsonError),
+    /// The limits set in `ParseOptions`, each with the maximum that was exceeded.
+    DepthLimitExceeded(usize),
+    InputTooLarge(usize),
+    StringTooLong(usize),
+    NumberTooLong(usize),
+    TooManyMembers(usize),
+    ArrayTooLong(usize),
+    /// A key seen before in the same object, with the position of its first occurrence.
+    DuplicateKey {
+        key: String,
+        first: Position,
+    },
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ParseErrorKind::UnexpectedCharacter(c) => write!(f, "Unexpected character {:?}", c),
+            ParseErrorKind::UnexpectedEnd => write!(f, "Unexpected end of input"),
+            ParseErrorKind::UnexpectedToken { found, expected } => {
+                write!(f, "Expected {}, found {}", expected, found)
+            }
+            ParseErrorKind::InvalidLiteral(literal) => write!(f, "Invalid literal \"{}\"", literal),
+            ParseErrorKind::InvalidNumber(number) => write!(f, "Invalid number \"{}\"", number),
+            ParseErrorKind::InvalidEscape => write!(f, "Invalid escape sequence"),
+            ParseErrorKind::ControlCharacterInString => {
+                write!(f, "Control characters must be escaped in strings")
+            }
+            ParseErrorKind::InvalidUtf8 => write!(f, "Invalid UTF-8"),
+            ParseErrorKind::TrailingCharacters => {
+                write!(f, "Unexpected characters after the value")
+            }
+            ParseErrorKind::Io(message) => write!(f, "I/O error: {}", message),
+            ParseErrorKind::Conversion(err) => write!(f, "Invalid value: {}", err),
+            ParseErrorKind::DepthLimitExceeded(max) => {
+                write!(f, "Nesting is deeper than {} levels", max)
+            }
+            ParseErrorKind::InputTooLarge(max) => write!(f, "Input is longer than {} bytes", max),
+            ParseErrorKind::StringTooLong(max) => {
+                write!(f, "String is longer than {} bytes", max)
+            }
+            ParseErrorKind::NumberTooLong(max) => {
+                write!(f, "Number is longer than {} characters", max)
+            }
+            ParseErrorKind::TooManyMembers(max) => {
+                write!(f, "Object has more than {} members", max)
+            }
+            ParseErrorKind::ArrayTooLong(max) => {
+                write!(f, "Array has more than {} elements", max)
+            }
+            ParseErrorKind::DuplicateKey { key, first } => {
+                write!(f, "Duplicate key \"{}\" (first seen at {})", key, first)
+            }
+        }
+    }
+}
+
+/// Error produced when text cannot be parsed as JSON, with the position where
+/// parsing stopped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    position: Position,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, position: Position) -> ParseError {
+        ParseError { kind, position }
+    }
+
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{} at {}", self.kind, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
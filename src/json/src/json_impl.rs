@@ -4,10 +4,35 @@ pub enum JsonToken {
     Number(f64),
     Boolean(bool),
     Null,
+    /// A bare word, read only when unquoted keys are allowed; the grammar decides
+    /// whether it is a key or a literal.
+    Identifier(String),
     Comma,
     Colon,
     LeftBrace,
     RightBrace,
     LeftBracket,
     RightBracket,
-}
\ No newline at end of file
+    /// The RS character that starts each text of an RFC 7464 JSON text sequence.
+    RecordSeparator,
+}
+
+impl JsonToken {
+    /// How the token is named in error messages.
+    pub fn describe(&self) -> String {
+        match self {
+            JsonToken::String(_) => "a string".to_string(),
+            JsonToken::Number(n) => format!("number {}", n),
+            JsonToken::Boolean(b) => format!("'{}'", b),
+            JsonToken::Null => "'null'".to_string(),
+            JsonToken::Identifier(name) => format!("'{}'", name),
+            JsonToken::Comma => "','".to_string(),
+            JsonToken::Colon => "':'".to_string(),
+            JsonToken::LeftBrace => "'{'".to_string(),
+            JsonToken::RightBrace => "'}'".to_string(),
+            JsonToken::LeftBracket => "'['".to_string(),
+            JsonToken::RightBracket => "']'".to_string(),
+            JsonToken::RecordSeparator => "a record separator".to_string(),
+        }
+    }
+}
@@ -0,0 +1,552 @@
+use crate::json_basic::JsonNode;
+use crate::json_schema::SCHEMA_DIALECT;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+
+const DEFAULT_MAX_ENUM_VALUES: usize = 16;
+
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Infers a schema from sample documents, such as the lines of an NDJSON feed.
+///
+/// Every path unions the types seen there. Object members missing from some samples
+/// are optional, string members with a small set of repeated values are reported as
+/// enums, and numbers keep the range they were seen in. The result is available both
+/// as a JSON Schema and as Rust source for `#[derive(JsonType)]` structs.
+#[derive(Debug, Clone)]
+pub struct SchemaInference {
+    shape: Shape,
+    max_enum_values: usize,
+}
+
+/// What has been seen at one path of the samples.
+#[derive(Debug, Clone, Default)]
+struct Shape {
+    nulls: usize,
+    booleans: usize,
+    numbers: Option<NumberShape>,
+    strings: Option<StringShape>,
+    array: Option<Box<Shape>>,
+    object: Option<ObjectShape>,
+}
+
+#[derive(Debug, Clone)]
+struct NumberShape {
+    min: f64,
+    max: f64,
+    integers: bool,
+}
+
+#[derive(Debug, Clone)]
+struct StringShape {
+    count: usize,
+    /// Distinct values, until there are too many of them to be an enum.
+    values: Option<BTreeSet<String>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ObjectShape {
+    count: usize,
+    fields: BTreeMap<String, (usize, Shape)>,
+}
+
+impl Default for SchemaInference {
+    fn default() -> Self {
+        SchemaInference::new()
+    }
+}
+
+impl SchemaInference {
+    pub fn new() -> SchemaInference {
+        SchemaInference {
+            shape: Shape::default(),
+            max_enum_values: DEFAULT_MAX_ENUM_VALUES,
+        }
+    }
+
+    /// The largest set of distinct strings reported as an enum; 0 disables enums.
+    pub fn with_max_enum_values(mut self, max_enum_values: usize) -> SchemaInference {
+        self.max_enum_values = max_enum_values;
+        self
+    }
+
+    pub fn from_samples<'a>(samples: impl IntoIterator<Item = &'a JsonNode>) -> SchemaInference {
+        let mut inference = SchemaInference::new();
+        inference.extend(samples);
+        inference
+    }
+
+    pub fn add_sample(&mut self, sample: &JsonNode) {
+        self.shape.add(sample, self.max_enum_values);
+    }
+
+    /// The inferred schema as a Draft 2020-12 document.
+    pub fn json_schema(&self) -> JsonNode {
+        let mut schema = match self.shape.schema(self.max_enum_values) {
+            JsonNode::Boolean(_) => JsonNode::Object(HashMap::new()),
+            schema => schema,
+        };
+        schema["$schema"] = JsonNode::String(SCHEMA_DIALECT.to_string());
+        schema
+    }
+
+    /// Rust source defining `root_name` and the structs nested in it, deriving
+    /// `JsonType`. `JsonType` and `JsonNode` are expected to be in scope.
+    ///
+    /// Fields are named after their keys, so keys that cannot name a field are only
+    /// listed in the struct's doc comment. Optional members are `Option`s that still
+    /// have to be present (possibly as `null`) for the derived `FromJson` to accept them.
+    pub fn rust_source(&self, root_name: &str) -> String {
+        let mut generator = RustGenerator {
+            max_enum_values: self.max_enum_values,
+            names: BTreeSet::new(),
+            structs: Vec::new(),
+        };
+        let root_type = generator.rust_type(&self.shape, root_name);
+        let mut source = String::new();
+        if generator.structs.is_empty() || root_type != generator.structs[0].0 {
+            writeln!(source, "pub type {} = {};", root_name, root_type).unwrap();
+            if !generator.structs.is_empty() {
+                source.push('\n');
+            }
+        }
+        let definitions: Vec<String> = generator.structs.into_iter().map(|(_, s)| s).collect();
+        source.push_str(&definitions.join("\n"));
+        source
+    }
+}
+
+impl<'a> Extend<&'a JsonNode> for SchemaInference {
+    fn extend<I: IntoIterator<Item = &'a JsonNode>>(&mut self, samples: I) {
+        for sample in samples {
+            self.add_sample(sample);
+        }
+    }
+}
+
+impl Shape {
+    fn add(&mut self, value: &JsonNode, max_enum_values: usize) {
+        match value {
+            JsonNode::Null => self.nulls += 1,
+            JsonNode::Boolean(_) => self.booleans += 1,
+            JsonNode::Number(n) => {
+                let integer = n.fract() == 0.0;
+                let numbers = self.numbers.get_or_insert(NumberShape {
+                    min: *n,
+                    max: *n,
+                    integers: true,
+                });
+                numbers.min = numbers.min.min(*n);
+                numbers.max = numbers.max.max(*n);
+                numbers.integers &= integer;
+            }
+            JsonNode::String(s) => {
+                let strings = self.strings.get_or_insert(StringShape {
+                    count: 0,
+                    values: Some(BTreeSet::new()),
+                });
+                strings.count += 1;
+                if let Some(values) = &mut strings.values {
+                    values.insert(s.clone());
+                    if values.len() > max_enum_values {
+                        strings.values = None;
+                    }
+                }
+            }
+            JsonNode::Array(arr) => {
+                let items = self.array.get_or_insert_with(Default::default);
+                for item in arr {
+                    items.add(item, max_enum_values);
+                }
+            }
+            JsonNode::Object(obj) => {
+                let object = self.object.get_or_insert_with(Default::default);
+                object.count += 1;
+                for (key, member) in obj {
+                    let (seen, shape) = object.fields.entry(key.clone()).or_default();
+                    *seen += 1;
+                    shape.add(member, max_enum_values);
+                }
+            }
+        }
+    }
+
+    /// Distinct values of an enum-like string member: few of them, each seen about
+    /// twice or more on average.
+    fn enum_values(&self, max_enum_values: usize) -> Option<&BTreeSet<String>> {
+        let strings = self.strings.as_ref()?;
+        let values = strings.values.as_ref()?;
+        if !values.is_empty()
+            && values.len() <= max_enum_values
+            && strings.count >= 2 * values.len()
+        {
+            Some(values)
+        } else {
+            None
+        }
+    }
+
+    /// Number of kinds of non-null values seen.
+    fn kinds(&self) -> usize {
+        [
+            self.booleans > 0,
+            self.numbers.is_some(),
+            self.strings.is_some(),
+            self.array.is_some(),
+            self.object.is_some(),
+        ]
+        .iter()
+        .filter(|seen| **seen)
+        .count()
+    }
+
+    fn schema(&self, max_enum_values: usize) -> JsonNode {
+        let mut alternatives = Vec::new();
+        if self.booleans > 0 {
+            alternatives.push(type_schema("boolean"));
+        }
+        if let Some(numbers) = &self.numbers {
+            let mut schema = type_schema(if numbers.integers {
+                "integer"
+            } else {
+                "number"
+            });
+            schema["minimum"] = JsonNode::Number(numbers.min);
+            schema["maximum"] = JsonNode::Number(numbers.max);
+            alternatives.push(schema);
+        }
+        if self.strings.is_some() {
+            let mut schema = type_schema("string");
+            if let Some(values) = self.enum_values(max_enum_values) {
+                schema["enum"] =
+                    JsonNode::Array(values.iter().cloned().map(JsonNode::String).collect());
+            }
+            alternatives.push(schema);
+        }
+        if let Some(items) = &self.array {
+            let mut schema = type_schema("array");
+            if items.kinds() > 0 || items.nulls > 0 {
+                schema["items"] = items.schema(max_enum_values);
+            }
+            alternatives.push(schema);
+        }
+        if let Some(object) = &self.object {
+            let mut schema = type_schema("object");
+            schema["properties"] = JsonNode::Object(
+                object
+                    .fields
+                    .iter()
+                    .map(|(key, (_, shape))| (key.clone(), shape.schema(max_enum_values)))
+                    .collect(),
+            );
+            schema["required"] = JsonNode::Array(
+                object
+                    .fields
+                    .iter()
+                    .filter(|(_, (seen, _))| *seen == object.count)
+                    .map(|(key, _)| JsonNode::String(key.clone()))
+                    .collect(),
+            );
+            alternatives.push(schema);
+        }
+        if self.nulls > 0 {
+            alternatives.push(type_schema("null"));
+        }
+
+        match alternatives.len() {
+            0 => JsonNode::Boolean(true),
+            1 => alternatives.pop().unwrap(),
+            _ => {
+                let mut schema = JsonNode::Object(HashMap::new());
+                schema["anyOf"] = JsonNode::Array(alternatives);
+                schema
+            }
+        }
+    }
+}
+
+fn type_schema(name: &str) -> JsonNode {
+    let mut schema = JsonNode::Object(HashMap::new());
+    schema["type"] = JsonNode::String(name.to_string());
+    schema
+}
+
+struct RustGenerator {
+    max_enum_values: usize,
+    names: BTreeSet<String>,
+    /// Type name and source of each struct, in the order they were named.
+    structs: Vec<(String, String)>,
+}
+
+impl RustGenerator {
+    /// The Rust type for `shape`, defining structs named after `name` as needed.
+    fn rust_type(&mut self, shape: &Shape, name: &str) -> String {
+        let inner = if shape.kinds() != 1 {
+            return "JsonNode".to_string();
+        } else if shape.booleans > 0 {
+            "bool".to_string()
+        } else if let Some(numbers) = &shape.numbers {
+            if numbers.integers { "i64" } else { "f64" }.to_string()
+        } else if shape.strings.is_some() {
+            "String".to_string()
+        } else if let Some(items) = &shape.array {
+            format!("Vec<{}>", self.rust_type(items, &singular(name)))
+        } else if let Some(object) = &shape.object {
+            self.rust_struct(object, name)
+        } else {
+            unreachable!()
+        };
+        if shape.nulls > 0 {
+            format!("Option<{}>", inner)
+        } else {
+            inner
+        }
+    }
+
+    fn rust_struct(&mut self, object: &ObjectShape, name: &str) -> String {
+        let name = unique(&mut self.names, &pascal_case(name));
+        let index = self.structs.len();
+        self.structs.push((name.clone(), String::new()));
+
+        let mut fields = String::new();
+        let mut skipped_keys = Vec::new();
+        for (key, (seen, shape)) in &object.fields {
+            let Some(field_name) = field_name(key) else {
+                skipped_keys.push(format!("{:?}", key));
+                continue;
+            };
+            let mut ty = self.rust_type(shape, key);
+            let optional = *seen < object.count;
+            if optional && !ty.starts_with("Option<") && ty != "JsonNode" {
+                ty = format!("Option<{}>", ty);
+            }
+
+            if optional {
+                writeln!(fields, "    /// Missing from some samples.").unwrap();
+            }
+            if let Some(values) = shape.enum_values(self.max_enum_values) {
+                let values: Vec<String> = values.iter().map(|v| format!("{:?}", v)).collect();
+                writeln!(fields, "    /// One of {}.", values.join(", ")).unwrap();
+            }
+            if let Some(numbers) = &shape.numbers {
+                writeln!(
+                    fields,
+                    "    /// Seen from {} to {}.",
+                    numbers.min, numbers.max
+                )
+                .unwrap();
+            }
+            writeln!(fields, "    pub {}: {},", field_name, ty).unwrap();
+        }
+
+        let mut source = String::new();
+        if !skipped_keys.is_empty() {
+            writeln!(
+                source,
+                "/// Members without a field: {}.",
+                skipped_keys.join(", ")
+            )
+            .unwrap();
+        }
+        if object
+            .fields
+            .keys()
+            .any(|key| key.contains(char::is_uppercase))
+        {
+            writeln!(source, "#[allow(non_snake_case)]").unwrap();
+        }
+        writeln!(source, "#[derive(Debug, Clone, PartialEq, JsonType)]").unwrap();
+        writeln!(source, "pub struct {} {{", name).unwrap();
+        source.push_str(&fields);
+        writeln!(source, "}}").unwrap();
+
+        self.structs[index].1 = source;
+        name
+    }
+}
+
+/// Appends a number to `name` until it is not in `names`, then records it.
+fn unique(names: &mut BTreeSet<String>, name: &str) -> String {
+    let mut candidate = name.to_string();
+    let mut suffix = 2;
+    while names.contains(&candidate) {
+        candidate = format!("{}{}", name, suffix);
+        suffix += 1;
+    }
+    names.insert(candidate.clone());
+    candidate
+}
+
+/// Splits a key such as `firstName`, `first-name` or `FIRST_NAME` into lowercase words.
+fn words(key: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut previous_lower = false;
+    for c in key.chars() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            previous_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && previous_lower && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// The field `key` is decoded into: the key itself, as a raw identifier if it is a
+/// keyword, or `None` if it cannot name a field.
+fn field_name(key: &str) -> Option<String> {
+    let mut chars = key.chars();
+    let identifier = matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    match key {
+        _ if !identifier => None,
+        "_" | "self" | "Self" | "super" | "crate" => None,
+        _ if RUST_KEYWORDS.contains(&key) => Some(format!("r#{}", key)),
+        _ => Some(key.to_string()),
+    }
+}
+
+fn pascal_case(key: &str) -> String {
+    let name: String = words(key)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("Type{}", name)
+    } else if name == "Self" {
+        "Self_".to_string()
+    } else {
+        name
+    }
+}
+
+/// Name for the items of an array member, e.g. `Item` for `items`.
+fn singular(name: &str) -> String {
+    match name.strip_suffix('s') {
+        Some(stem) if !stem.is_empty() && !stem.ends_with('s') => stem.to_string(),
+        _ => format!("{}Item", name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn log_lines() -> Vec<JsonNode> {
+        let feed = r#"{"id": 1, "level": "info", "user": {"first-name": "a"}, "tags": ["x"]}
+{"id": 2, "level": "warn", "user": {"first-name": "b", "age": 30}, "tags": []}
+{"id": 3, "level": "info", "user": null, "tags": ["y", "z"], "score": 0.5}
+{"id": 40, "level": "info", "user": {"first-name": "c", "age": 41}, "tags": ["x"]}"#;
+        feed.lines().map(|line| parse_json(line).unwrap()).collect()
+    }
+
+    #[test]
+    fn json_schema_unions_the_samples() {
+        let samples = log_lines();
+        let schema = SchemaInference::from_samples(&samples).json_schema();
+        assert_eq!(schema["type"], json!("object"));
+        assert_eq!(schema["required"], json!(["id", "level", "tags", "user"]));
+        assert_eq!(
+            schema["properties"]["user"]["anyOf"][1],
+            json!({"type": "null"})
+        );
+        assert_eq!(
+            schema["properties"]["user"]["anyOf"][0]["required"],
+            json!(["first-name"])
+        );
+        for sample in &samples {
+            assert!(SchemaValidator::compile(&schema).unwrap().is_valid(sample));
+        }
+    }
+
+    #[test]
+    fn json_schema_keeps_enums_and_ranges() {
+        let schema = SchemaInference::from_samples(&log_lines()).json_schema();
+        assert_eq!(
+            schema["properties"]["id"],
+            json!({"type": "integer", "minimum": 1, "maximum": 40})
+        );
+        assert_eq!(
+            schema["properties"]["level"],
+            json!({"type": "string", "enum": ["info", "warn"]})
+        );
+        assert_eq!(
+            schema["properties"]["score"],
+            json!({"type": "number", "minimum": 0.5, "maximum": 0.5})
+        );
+    }
+
+    #[test]
+    fn rust_source_defines_nested_structs() {
+        let inference = SchemaInference::from_samples(&log_lines());
+        assert_eq!(
+            inference.rust_source("LogLine"),
+            r#"#[derive(Debug, Clone, PartialEq, JsonType)]
+pub struct LogLine {
+    /// Seen from 1 to 40.
+    pub id: i64,
+    /// One of "info", "warn".
+    pub level: String,
+    /// Missing from some samples.
+    /// Seen from 0.5 to 0.5.
+    pub score: Option<f64>,
+    pub tags: Vec<String>,
+    pub user: Option<User>,
+}
+
+/// Members without a field: "first-name".
+#[derive(Debug, Clone, PartialEq, JsonType)]
+pub struct User {
+    /// Missing from some samples.
+    /// Seen from 30 to 41.
+    pub age: Option<i64>,
+}
+"#
+        );
+    }
+
+    #[test]
+    fn rust_source_names_fields_after_keys() {
+        let inference =
+            SchemaInference::from_samples(&[json!({"type": true, "userId": true, "self": true})]);
+        assert_eq!(
+            inference.rust_source("Event"),
+            r#"/// Members without a field: "self".
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, PartialEq, JsonType)]
+pub struct Event {
+    pub r#type: bool,
+    pub userId: bool,
+}
+"#
+        );
+    }
+
+    #[test]
+    fn rust_source_aliases_non_object_roots() {
+        let inference = SchemaInference::from_samples(&[json!([1, 2])]);
+        assert_eq!(inference.rust_source("Ids"), "pub type Ids = Vec<i64>;\n");
+    }
+}
@@ -0,0 +1,217 @@
+use crate::json_basic::{FromJson, JsonNode, ToJson};
+use crate::json_error::{collect_results, JsonError};
+
+/// A map key that can be stored as a JSON object key.
+///
+/// Maps whose keys implement `JsonKey` are encoded as JSON objects with the
+/// stringified keys. Maps with other keys can still be encoded as an array of
+/// `[key, value]` pairs with `map_to_json_pairs` / `map_try_from_json_pairs`, or
+/// `#[json(as_pairs)]` in the derive.
+pub trait JsonKey: Sized {
+    fn to_json_key(&self) -> String;
+
+    fn from_json_key(key: &str) -> Result<Self, JsonError>;
+}
+
+impl JsonKey for String {
+    fn to_json_key(&self) -> String {
+        self.clone()
+    }
+
+    fn from_json_key(key: &str) -> Result<Self, JsonError> {
+        Ok(key.to_string())
+    }
+}
+
+impl JsonKey for bool {
+    fn to_json_key(&self) -> String {
+        self.to_string()
+    }
+
+    fn from_json_key(key: &str) -> Result<Self, JsonError> {
+        match key {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            _ => Err(JsonError::new(format!("Invalid boolean key \"{}\"", key))),
+        }
+    }
+}
+
+impl JsonKey for char {
+    fn to_json_key(&self) -> String {
+        self.to_string()
+    }
+
+    fn from_json_key(key: &str) -> Result<Self, JsonError> {
+        let mut chars = key.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(JsonError::new(format!("Invalid char key \"{}\"", key))),
+        }
+    }
+}
+
+macro_rules! impl_json_key_for_integer {
+    ($($t:ty),*) => {
+        $(
+            impl JsonKey for $t {
+                fn to_json_key(&self) -> String {
+                    self.to_string()
+                }
+
+                fn from_json_key(key: &str) -> Result<Self, JsonError> {
+                    key.parse().map_err(|_| {
+                        JsonError::new(format!(
+                            "Invalid {} key \"{}\"",
+                            stringify!($t),
+                            key
+                        ))
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_json_key_for_integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+pub(crate) fn object_from_entries<'a, K, V>(
+    entries: impl IntoIterator<Item = (&'a K, &'a V)>,
+) -> JsonNode
+where
+    K: JsonKey + 'a,
+    V: ToJson + 'a,
+{
+    JsonNode::Object(
+        entries
+            .into_iter()
+            .map(|(k, v)| (k.to_json_key(), v.to_json()))
+            .collect(),
+    )
+}
+
+pub(crate) fn collect_object_entries<K, V, M>(json: &JsonNode) -> Result<M, JsonError>
+where
+    K: JsonKey,
+    V: FromJson,
+    M: FromIterator<(K, V)>,
+{
+    match json {
+        JsonNode::Object(obj) => collect_results(obj.iter().map(|(k, v)| {
+            let key = K::from_json_key(k).map_err(|err| err.with_key(k))?;
+            let value = V::try_from_json(v).map_err(|err| err.with_key(k))?;
+            Ok((key, value))
+        })),
+        _ => Err(JsonError::new("Cannot convert non-object type to object")),
+    }
+}
+
+/// Encodes a map as an array of `[key, value]` pairs, for keys that have no string form.
+pub fn map_to_json_pairs<'a, M, K, V>(map: &'a M) -> JsonNode
+where
+    &'a M: IntoIterator<Item = (&'a K, &'a V)>,
+    K: ToJson + 'a,
+    V: ToJson + 'a,
+{
+    JsonNode::Array(
+        map.into_iter()
+            .map(|(k, v)| JsonNode::Array(vec![k.to_json(), v.to_json()]))
+            .collect(),
+    )
+}
+
+/// Decodes a map from the array of `[key, value]` pairs written by `map_to_json_pairs`.
+pub fn map_try_from_json_pairs<M, K, V>(json: &JsonNode) -> Result<M, JsonError>
+where
+    M: FromIterator<(K, V)>,
+    K: FromJson,
+    V: FromJson,
+{
+    match json {
+        JsonNode::Array(arr) => {
+            collect_results(arr.iter().enumerate().map(|(idx, pair)| {
+                <(K, V)>::try_from_json(pair).map_err(|err| err.with_index(idx))
+            }))
+        }
+        _ => Err(JsonError::new("Cannot convert non-array type to map pairs")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, JsonKey)]
+    enum Color {
+        Red,
+        Green,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, JsonKey, JsonType)]
+    #[json(transparent)]
+    struct AccountId(u32);
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, JsonType)]
+    struct GridPoint {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Debug, Clone, PartialEq, JsonType)]
+    struct KeyedMaps {
+        by_id: HashMap<u32, String>,
+        by_char: BTreeMap<char, i32>,
+        flags: HashMap<bool, u8>,
+        colors: BTreeMap<Color, f64>,
+        accounts: HashMap<AccountId, bool>,
+        #[json(as_pairs)]
+        cells: BTreeMap<GridPoint, String>,
+    }
+
+    #[test]
+    fn keyed_maps_round_trip() {
+        let value = KeyedMaps {
+            by_id: [(1, "one".to_string()), (20, "twenty".to_string())]
+                .into_iter()
+                .collect(),
+            by_char: [('a', 1), ('b', 2)].into_iter().collect(),
+            flags: [(true, 1), (false, 0)].into_iter().collect(),
+            colors: [(Color::Red, 0.5), (Color::Green, 1.0)]
+                .into_iter()
+                .collect(),
+            accounts: [(AccountId(7), true)].into_iter().collect(),
+            cells: [(GridPoint { x: 1, y: 2 }, "mine".to_string())]
+                .into_iter()
+                .collect(),
+        };
+
+        let value_json = value.to_json();
+        assert_eq!(value_json["by_id"], json!({"1": "one", "20": "twenty"}));
+        assert_eq!(value_json["colors"], json!({"Red": 0.5, "Green": 1}));
+        assert_eq!(value_json["accounts"], json!({"7": true}));
+        assert_eq!(KeyedMaps::from_json(&value_json), value);
+    }
+
+    #[test]
+    fn as_pairs_encodes_entries_as_arrays() {
+        let cells: BTreeMap<GridPoint, String> = [(GridPoint { x: 1, y: 2 }, "mine".to_string())]
+            .into_iter()
+            .collect();
+        let pairs = map_to_json_pairs(&cells);
+        assert_eq!(pairs, json!([[{"x": 1, "y": 2}, "mine"]]));
+        assert_eq!(
+            map_try_from_json_pairs::<BTreeMap<_, _>, _, _>(&pairs),
+            Ok(cells)
+        );
+    }
+
+    #[test]
+    fn invalid_keys_report_the_key() {
+        let err = HashMap::<u32, String>::try_from_json(&json!({"x1": "a"})).unwrap_err();
+        assert_eq!(err.pointer(), "/x1");
+        assert_eq!(err.message(), "Invalid u32 key \"x1\"");
+        let err = BTreeMap::<Color, f64>::try_from_json(&json!({"Blue": 1})).unwrap_err();
+        assert_eq!(err.message(), "Invalid Color key \"Blue\"");
+    }
+}
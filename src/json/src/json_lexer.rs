@@ -1,237 +1,603 @@
+use crate::json_error::{ParseError, ParseErrorKind, Position};
 use crate::json_impl::JsonToken;
-use std::collections::LinkedList;
+use crate::json_options::ParseOptions;
+use std::io::{BufRead, Read};
 
-pub struct JsonLexer<'a> {
+/// Where `JsonLexer` reads characters from.
+pub trait JsonSource {
+    /// The next character without consuming it, or `None` at the end of the input.
+    fn peek_char(&mut self) -> Result<Option<char>, ParseErrorKind>;
+
+    /// Consumes the character last returned by `peek_char`.
+    fn bump(&mut self);
+}
+
+pub struct StrSource<'a> {
     input: &'a str,
     pos: usize,
 }
 
-impl<'a> JsonLexer<'a> {
+impl<'a> StrSource<'a> {
     pub fn new(input: &'a str) -> Self {
-        JsonLexer { input, pos: 0 }
+        StrSource { input, pos: 0 }
     }
+}
 
-    fn peek_char(&mut self) -> Option<char> {
-        self.input[self.pos..].chars().next()
+impl JsonSource for StrSource<'_> {
+    fn peek_char(&mut self) -> Result<Option<char>, ParseErrorKind> {
+        Ok(self.input[self.pos..].chars().next())
     }
 
-    fn move_to_next(&mut self, c: char) {
-        assert_eq!(self.peek_char(), Some(c));
-        self.pos += c.len_utf8();
+    fn bump(&mut self) {
+        if let Some(c) = self.input[self.pos..].chars().next() {
+            self.pos += c.len_utf8();
+        }
     }
+}
 
-    pub fn next_token(&mut self) -> Option<JsonToken> {
-        loop {
-            match self.peek_char() {
-                None => break None,
-                Some(c) => match self.handle_char(c) {
-                    Some(token) => break Some(token),
-                    None => continue,
-                },
+const READ_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Decodes UTF-8 from a reader through a fixed-size buffer. A character split
+/// across two reads is completed from the next read.
+pub struct ReadSource<R> {
+    reader: R,
+    buffer: Box<[u8]>,
+    start: usize,
+    end: usize,
+    peeked: Option<(char, usize)>,
+}
+
+impl<R: Read> ReadSource<R> {
+    pub fn new(reader: R) -> Self {
+        ReadSource {
+            reader,
+            buffer: vec![0; READ_BUFFER_SIZE].into_boxed_slice(),
+            start: 0,
+            end: 0,
+            peeked: None,
+        }
+    }
+
+    /// Reads until at least `needed` bytes are buffered; returns false at the end
+    /// of the input.
+    fn fill(&mut self, needed: usize) -> Result<bool, ParseErrorKind> {
+        while self.end - self.start < needed {
+            if self.start > 0 {
+                self.buffer.copy_within(self.start..self.end, 0);
+                self.end -= self.start;
+                self.start = 0;
+            }
+            match self.reader.read(&mut self.buffer[self.end..]) {
+                Ok(0) => return Ok(false),
+                Ok(read) => self.end += read,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(ParseErrorKind::Io(err.to_string())),
             }
         }
+        Ok(true)
     }
+}
 
-    fn handle_char(&mut self, mut c: char) -> Option<JsonToken> {
-        while c.is_whitespace() {
-            self.move_to_next(c);
-            c = self.peek_char()?;
+/// The length of the UTF-8 sequence starting with `byte`, or `None` if it cannot
+/// start one.
+pub(crate) fn utf8_width(byte: u8) -> Option<usize> {
+    match byte {
+        0x00..=0x7F => Some(1),
+        0xC2..=0xDF => Some(2),
+        0xE0..=0xEF => Some(3),
+        0xF0..=0xF4 => Some(4),
+        _ => None,
+    }
+}
+
+impl<R: Read> JsonSource for ReadSource<R> {
+    fn peek_char(&mut self) -> Result<Option<char>, ParseErrorKind> {
+        if let Some((c, _)) = self.peeked {
+            return Ok(Some(c));
         }
-        match c {
-            '"' => self.handle_string(),
-            '0'..='9' | 'e' | 'E' | '.' | '+' | '-' => self.handle_number(),
-            't' | 'f' => self.handle_boolean(),
-            'n' => self.handle_null(),
-            ',' => {
-                self.move_to_next(',');
-                Some(JsonToken::Comma)
-            }
-            ':' => {
-                self.move_to_next(':');
-                Some(JsonToken::Colon)
+        if !self.fill(1)? {
+            return Ok(None);
+        }
+        let width = utf8_width(self.buffer[self.start]).ok_or(ParseErrorKind::InvalidUtf8)?;
+        if !self.fill(width)? {
+            return Err(ParseErrorKind::InvalidUtf8);
+        }
+        let c = std::str::from_utf8(&self.buffer[self.start..self.start + width])
+            .map_err(|_| ParseErrorKind::InvalidUtf8)?
+            .chars()
+            .next()
+            .unwrap();
+        self.peeked = Some((c, width));
+        Ok(Some(c))
+    }
+
+    fn bump(&mut self) {
+        if let Some((_, width)) = self.peeked.take() {
+            self.start += width;
+        }
+    }
+}
+
+/// Decodes UTF-8 straight from a `BufRead`'s own buffer. Only a character split
+/// across two fills is copied.
+pub struct BufReadSource<R> {
+    reader: R,
+    split: [u8; 4],
+    peeked: Option<(char, usize)>,
+}
+
+impl<R: BufRead> BufReadSource<R> {
+    pub fn new(reader: R) -> Self {
+        BufReadSource {
+            reader,
+            split: [0; 4],
+            peeked: None,
+        }
+    }
+
+    /// Copies a character that is split across fills into `split` and consumes it
+    /// from the reader.
+    fn read_split_char(&mut self, width: usize) -> Result<char, ParseErrorKind> {
+        let mut len = 0;
+        while len < width {
+            let buf = fill_buf(&mut self.reader)?;
+            if buf.is_empty() {
+                return Err(ParseErrorKind::InvalidUtf8);
             }
-            '{' => {
-                self.move_to_next('{');
-                Some(JsonToken::LeftBrace)
+            let n = buf.len().min(width - len);
+            self.split[len..len + n].copy_from_slice(&buf[..n]);
+            self.reader.consume(n);
+            len += n;
+        }
+        std::str::from_utf8(&self.split[..width])
+            .map_err(|_| ParseErrorKind::InvalidUtf8)
+            .map(|s| s.chars().next().unwrap())
+    }
+}
+
+fn fill_buf<R: BufRead>(reader: &mut R) -> Result<&[u8], ParseErrorKind> {
+    loop {
+        match reader.fill_buf() {
+            Ok(_) => break,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(ParseErrorKind::Io(err.to_string())),
+        }
+    }
+    // The buffer cannot be returned from inside the loop because of the borrow
+    // checker; filling again just returns the data that is already buffered.
+    Ok(reader.fill_buf().unwrap_or(&[]))
+}
+
+impl<R: BufRead> JsonSource for BufReadSource<R> {
+    fn peek_char(&mut self) -> Result<Option<char>, ParseErrorKind> {
+        if let Some((c, _)) = self.peeked {
+            return Ok(Some(c));
+        }
+        let buf = fill_buf(&mut self.reader)?;
+        let first = match buf.first() {
+            Some(&first) => first,
+            None => return Ok(None),
+        };
+        if first.is_ascii() {
+            self.peeked = Some((first as char, 1));
+            return Ok(Some(first as char));
+        }
+        let width = utf8_width(first).ok_or(ParseErrorKind::InvalidUtf8)?;
+        if buf.len() >= width {
+            let c = std::str::from_utf8(&buf[..width])
+                .map_err(|_| ParseErrorKind::InvalidUtf8)?
+                .chars()
+                .next()
+                .unwrap();
+            self.peeked = Some((c, width));
+        } else {
+            // Already consumed, so `bump` has nothing left to consume.
+            let c = self.read_split_char(width)?;
+            self.peeked = Some((c, 0));
+        }
+        Ok(self.peeked.map(|(c, _)| c))
+    }
+
+    fn bump(&mut self) {
+        if let Some((_, width)) = self.peeked.take() {
+            self.reader.consume(width);
+        }
+    }
+}
+
+pub struct JsonLexer<S> {
+    source: S,
+    position: Position,
+    /// When false, string tokens are scanned and checked but their contents are not kept.
+    pub(crate) store_strings: bool,
+    /// Whether RS characters are read as `JsonToken::RecordSeparator`.
+    pub(crate) record_separators: bool,
+    options: ParseOptions,
+}
+
+impl<'a> JsonLexer<StrSource<'a>> {
+    pub fn new(input: &'a str) -> Self {
+        JsonLexer::from_source(StrSource::new(input))
+    }
+}
+
+impl<S: JsonSource> JsonLexer<S> {
+    pub fn from_source(source: S) -> Self {
+        JsonLexer {
+            source,
+            position: Position::new(),
+            store_strings: true,
+            record_separators: false,
+            options: ParseOptions::new(),
+        }
+    }
+
+    /// Accepts the extensions to JSON that `options` turns on.
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn options(&self) -> ParseOptions {
+        self.options
+    }
+
+    /// The position of the next character to be read.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError::new(kind, self.position)
+    }
+
+    fn peek_char(&mut self) -> Result<Option<char>, ParseError> {
+        let c = self.source.peek_char().map_err(|kind| self.error(kind))?;
+        match c {
+            Some(c) if self.position.offset + c.len_utf8() > self.options.max_input_bytes => {
+                Err(self.error(ParseErrorKind::InputTooLarge(self.options.max_input_bytes)))
             }
-            '}' => {
-                self.move_to_next('}');
-                Some(JsonToken::RightBrace)
+            _ => Ok(c),
+        }
+    }
+
+    fn move_to_next(&mut self, c: char) {
+        self.source.bump();
+        self.position.advance(c);
+    }
+
+    fn expect_char(&mut self) -> Result<char, ParseError> {
+        let c = self
+            .peek_char()?
+            .ok_or_else(|| self.error(ParseErrorKind::UnexpectedEnd))?;
+        self.move_to_next(c);
+        Ok(c)
+    }
+
+    /// The next token and the position it starts at, or `None` at the end of the input.
+    pub fn next_token(&mut self) -> Result<Option<(JsonToken, Position)>, ParseError> {
+        self.skip_whitespace()?;
+        let position = self.position;
+        match self.peek_char()? {
+            None => Ok(None),
+            Some(c) => Ok(Some((self.handle_char(c)?, position))),
+        }
+    }
+
+    /// Whether only whitespace is left. The whitespace is consumed.
+    pub fn at_end(&mut self) -> Result<bool, ParseError> {
+        self.skip_whitespace()?;
+        Ok(self.peek_char()?.is_none())
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), ParseError> {
+        loop {
+            match self.peek_char()? {
+                Some(c @ (' ' | '\t' | '\n' | '\r')) => self.move_to_next(c),
+                Some('/') if self.options.comments => self.skip_comment()?,
+                _ => return Ok(()),
             }
-            '[' => {
-                self.move_to_next('[');
-                Some(JsonToken::LeftBracket)
+        }
+    }
+
+    fn skip_comment(&mut self) -> Result<(), ParseError> {
+        let position = self.position;
+        self.move_to_next('/');
+        match self.peek_char()? {
+            Some('/') => {
+                while let Some(c) = self.peek_char()? {
+                    if c == '\n' {
+                        break;
+                    }
+                    self.move_to_next(c);
+                }
             }
-            ']' => {
-                self.move_to_next(']');
-                Some(JsonToken::RightBracket)
+            Some('*') => {
+                self.move_to_next('*');
+                let mut star = false;
+                loop {
+                    let c = self.expect_char()?;
+                    if star && c == '/' {
+                        break;
+                    }
+                    star = c == '*';
+                }
             }
             _ => {
-                panic!("Unexpected character: {}", c);
+                return Err(ParseError::new(
+                    ParseErrorKind::UnexpectedCharacter('/'),
+                    position,
+                ))
             }
         }
+        Ok(())
     }
 
-    fn handle_escape(&mut self) -> Option<char> {
-        assert_eq!(self.peek_char(), Some('\\'));
+    fn handle_char(&mut self, c: char) -> Result<JsonToken, ParseError> {
+        let token = match c {
+            '"' => return self.handle_string('"'),
+            '\'' if self.options.single_quoted_strings => return self.handle_string('\''),
+            '-' | '0'..='9' => return self.handle_number(),
+            'a'..='z' | 'A'..='Z' => return self.handle_keyword(),
+            '_' | '$' if self.options.unquoted_keys => return self.handle_keyword(),
+            ',' => JsonToken::Comma,
+            ':' => JsonToken::Colon,
+            '{' => JsonToken::LeftBrace,
+            '}' => JsonToken::RightBrace,
+            '[' => JsonToken::LeftBracket,
+            ']' => JsonToken::RightBracket,
+            '\u{1e}' if self.record_separators => JsonToken::RecordSeparator,
+            _ => return Err(self.error(ParseErrorKind::UnexpectedCharacter(c))),
+        };
+        self.move_to_next(c);
+        Ok(token)
+    }
+
+    /// The character an escape stands for, or `None` for an escaped line break.
+    fn handle_escape(&mut self) -> Result<Option<char>, ParseError> {
+        let position = self.position;
         self.move_to_next('\\');
-        match self.peek_char() {
-            Some('"') => {
-                self.move_to_next('"');
-                Some('"')
+        let c = match self.expect_char()? {
+            '"' => '"',
+            '\'' if self.options.single_quoted_strings => '\'',
+            '\n' | '\u{2028}' | '\u{2029}' if self.options.multiline_strings => return Ok(None),
+            '\r' if self.options.multiline_strings => {
+                if self.peek_char()? == Some('\n') {
+                    self.move_to_next('\n');
+                }
+                return Ok(None);
             }
-            Some('\\') => {
+            '\\' => '\\',
+            '/' => '/',
+            'b' => '\x08',
+            'f' => '\x0c',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let high = self.handle_hex4(position)?;
+                if !(0xD800..0xDC00).contains(&high) {
+                    return Ok(Some(char::from_u32(high).unwrap_or('\u{fffd}')));
+                }
+                // A high surrogate must be followed by an escaped low surrogate to
+                // form one character; unpaired surrogates become U+FFFD.
+                if self.peek_char()? != Some('\\') {
+                    return Ok(Some('\u{fffd}'));
+                }
                 self.move_to_next('\\');
-                Some('\\')
-            }
-            Some('/') => {
-                self.move_to_next('/');
-                Some('/')
-            }
-            Some('b') => {
-                self.move_to_next('b');
-                Some('\x08')
-            }
-            Some('f') => {
-                self.move_to_next('f');
-                Some('\x0c')
-            }
-            Some('n') => {
-                self.move_to_next('n');
-                Some('\n')
-            }
-            Some('r') => {
-                self.move_to_next('r');
-                Some('\r')
-            }
-            Some('t') => {
-                self.move_to_next('t');
-                Some('\t')
-            }
-            Some('u') => {
-                self.move_to_next('u');
-                let mut codepoint = 0;
-                for _ in 0..4 {
-                    match self.peek_char() {
-                        Some(c) if c.is_digit(16) => {
-                            codepoint = codepoint * 16 + c.to_digit(16)?;
-                            self.move_to_next(c);
-                        }
-                        _ => return None,
-                    }
+                if self.expect_char()? != 'u' {
+                    return Err(ParseError::new(ParseErrorKind::InvalidEscape, position));
+                }
+                let low = self.handle_hex4(position)?;
+                if !(0xDC00..0xE000).contains(&low) {
+                    return Ok(Some('\u{fffd}'));
                 }
-                std::char::from_u32(codepoint).or(Some('\u{fffd}'))
+                char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+                    .unwrap_or('\u{fffd}')
             }
-            _ => None,
+            _ => return Err(ParseError::new(ParseErrorKind::InvalidEscape, position)),
+        };
+        Ok(Some(c))
+    }
+
+    fn handle_hex4(&mut self, escape_position: Position) -> Result<u32, ParseError> {
+        let mut codepoint = 0;
+        for _ in 0..4 {
+            let digit = self
+                .expect_char()?
+                .to_digit(16)
+                .ok_or_else(|| ParseError::new(ParseErrorKind::InvalidEscape, escape_position))?;
+            codepoint = codepoint * 16 + digit;
         }
+        Ok(codepoint)
     }
 
-    fn handle_string(&mut self) -> Option<JsonToken> {
-        assert_eq!(self.peek_char(), Some('"'));
-        self.move_to_next('"');
+    fn handle_string(&mut self, quote: char) -> Result<JsonToken, ParseError> {
+        let position = self.position;
+        self.move_to_next(quote);
 
         let mut string = String::new();
+        let mut length = 0;
 
         loop {
-            match self.peek_char() {
-                Some('"') => {
-                    self.move_to_next('"');
-                    break Some(JsonToken::String(string));
+            let c = match self.peek_char()? {
+                Some(c) if c == quote => {
+                    self.move_to_next(quote);
+                    break Ok(JsonToken::String(string));
                 }
-                Some('\\') => match self.handle_escape() {
-                    Some(c) => string.push(c),
-                    None => {
-                        string.push('\\');
-                        let next = self.peek_char();
-                        if let Some(c) = next {
-                            string.push(c);
-                            self.move_to_next(c);
-                        } else {
-                            break None;
-                        }
-                    }
+                Some('\\') => match self.handle_escape()? {
+                    Some(c) => c,
+                    None => continue,
                 },
+                Some(c) if c < ' ' => {
+                    break Err(self.error(ParseErrorKind::ControlCharacterInString));
+                }
                 Some(c) => {
-                    string.push(c);
                     self.move_to_next(c);
+                    c
                 }
-                None => return None,
+                None => break Err(self.error(ParseErrorKind::UnexpectedEnd)),
+            };
+            length += c.len_utf8();
+            if length > self.options.max_string_length {
+                let max = self.options.max_string_length;
+                break Err(ParseError::new(
+                    ParseErrorKind::StringTooLong(max),
+                    position,
+                ));
+            }
+            if self.store_strings {
+                string.push(c);
             }
         }
     }
 
-    fn handle_number(&mut self) -> Option<JsonToken> {
+    /// Reads the characters a number may contain, then checks them against the JSON
+    /// number grammar.
+    fn handle_number(&mut self) -> Result<JsonToken, ParseError> {
+        let position = self.position;
         let mut number = String::new();
-        let c = self.peek_char()?;
-        number.push(c);
-        self.move_to_next(c);
-
-        while let Some(c) = self.peek_char() {
-            if c.is_digit(10) || c == '.' || c == 'e' || c == 'E' || c == '+' || c == '-' {
-                number.push(c);
-                self.move_to_next(c);
-            } else {
+        while let Some(c) = self.peek_char()? {
+            if !is_number_char(c) {
                 break;
             }
+            if number.len() == self.options.max_number_length {
+                let max = self.options.max_number_length;
+                return Err(ParseError::new(
+                    ParseErrorKind::NumberTooLong(max),
+                    position,
+                ));
+            }
+            number.push(c);
+            self.move_to_next(c);
         }
-
-        match number.parse::<f64>() {
-            Ok(n) => Some(JsonToken::Number(n)),
-            Err(_) => None,
-        }
+        number_token(number, &self.options).map_err(|kind| ParseError::new(kind, position))
     }
 
-    fn handle_boolean(&mut self) -> Option<JsonToken> {
-        let mut boolean = String::new();
-        let c = self.peek_char()?;
-        boolean.push(c);
-        self.move_to_next(c);
-
-        while let Some(c) = self.peek_char() {
-            if c.is_ascii_alphabetic() {
-                boolean.push(c);
-                self.move_to_next(c);
-            } else {
+    fn handle_keyword(&mut self) -> Result<JsonToken, ParseError> {
+        let position = self.position;
+        let mut keyword = String::new();
+        while let Some(c) = self.peek_char()? {
+            if !is_keyword_char(c) {
                 break;
             }
+            keyword.push(c);
+            self.move_to_next(c);
         }
-
-        match boolean.as_str() {
-            "true" => Some(JsonToken::Boolean(true)),
-            "false" => Some(JsonToken::Boolean(false)),
-            _ => None,
+        if self.options.unquoted_keys {
+            // Only the grammar knows whether this is a key or a value.
+            return Ok(JsonToken::Identifier(keyword));
         }
+        keyword_token(keyword, &self.options).map_err(|kind| ParseError::new(kind, position))
     }
+}
 
-    fn handle_null(&mut self) -> Option<JsonToken> {
-        let mut null = String::new();
-        null.push('n');
-        self.move_to_next('n');
+/// `position` moved past `text`.
+pub(crate) fn advanced(mut position: Position, text: &str) -> Position {
+    for c in text.chars() {
+        position.advance(c);
+    }
+    position
+}
 
-        while let Some(c) = self.peek_char() {
-            if c.is_ascii_alphabetic() {
-                null.push(c);
-                self.move_to_next(c);
-            } else {
-                break;
-            }
-        }
+/// Characters that continue a number. More are accepted than the grammar allows, so
+/// that `012` or `1.5x` is reported as one invalid number.
+pub(crate) fn is_number_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '.' || c == '+' || c == '-'
+}
 
-        match null.as_str() {
-            "null" => Some(JsonToken::Null),
-            _ => None,
+pub(crate) fn is_keyword_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '$'
+}
+
+pub(crate) fn number_token(
+    number: String,
+    options: &ParseOptions,
+) -> Result<JsonToken, ParseErrorKind> {
+    let (negative, unsigned) = match number.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, number.as_str()),
+    };
+    let sign = if negative { -1.0 } else { 1.0 };
+    if options.non_finite_numbers && negative {
+        match unsigned {
+            "Infinity" => return Ok(JsonToken::Number(f64::NEG_INFINITY)),
+            "NaN" => return Ok(JsonToken::Number(f64::NAN)),
+            _ => {}
+        }
+    }
+    if let Some(digits) = unsigned
+        .strip_prefix("0x")
+        .or_else(|| unsigned.strip_prefix("0X"))
+        .filter(|_| options.hex_numbers)
+    {
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(ParseErrorKind::InvalidNumber(number));
         }
+        let value = digits.bytes().fold(0.0, |value, digit| {
+            value * 16.0 + (digit as char).to_digit(16).unwrap() as f64
+        });
+        return Ok(JsonToken::Number(sign * value));
+    }
+    if !is_json_number(&number) {
+        return Err(ParseErrorKind::InvalidNumber(number));
+    }
+    match number.parse::<f64>() {
+        Ok(n) if n.is_finite() => Ok(JsonToken::Number(n)),
+        _ => Err(ParseErrorKind::InvalidNumber(number)),
     }
 }
 
-pub fn parse_all(input: &str) -> Option<LinkedList<JsonToken>> {
-    let mut lexer = JsonLexer::new(input);
-    let mut tokens = LinkedList::new();
+pub(crate) fn keyword_token(
+    keyword: String,
+    options: &ParseOptions,
+) -> Result<JsonToken, ParseErrorKind> {
+    match keyword.as_str() {
+        "true" => Ok(JsonToken::Boolean(true)),
+        "false" => Ok(JsonToken::Boolean(false)),
+        "null" => Ok(JsonToken::Null),
+        "Infinity" if options.non_finite_numbers => Ok(JsonToken::Number(f64::INFINITY)),
+        "NaN" if options.non_finite_numbers => Ok(JsonToken::Number(f64::NAN)),
+        _ => Err(ParseErrorKind::InvalidLiteral(keyword)),
+    }
+}
 
-    loop {
-        match lexer.next_token() {
-            Some(token) => tokens.push_back(token),
-            None => break Some(tokens),
+/// `-? (0 | [1-9][0-9]*) (. [0-9]+)? ([eE] [+-]? [0-9]+)?`
+fn is_json_number(number: &str) -> bool {
+    let bytes = number.as_bytes();
+    let mut i = 0;
+    let digits = |i: &mut usize| {
+        let start = *i;
+        while *i < bytes.len() && bytes[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        *i - start
+    };
+
+    if bytes.get(i) == Some(&b'-') {
+        i += 1;
+    }
+    match bytes.get(i) {
+        Some(b'0') => i += 1,
+        Some(b'1'..=b'9') => {
+            digits(&mut i);
+        }
+        _ => return false,
+    }
+    if bytes.get(i) == Some(&b'.') {
+        i += 1;
+        if digits(&mut i) == 0 {
+            return false;
+        }
+    }
+    if matches!(bytes.get(i), Some(b'e' | b'E')) {
+        i += 1;
+        if matches!(bytes.get(i), Some(b'+' | b'-')) {
+            i += 1;
+        }
+        if digits(&mut i) == 0 {
+            return false;
         }
     }
+    i == bytes.len()
 }
@@ -0,0 +1,277 @@
+use crate::json_basic::{FromJson, JsonNode, ToJson};
+use crate::json_error::{ParseError, ParseErrorKind, Position};
+use crate::json_lexer::{advanced, JsonLexer};
+use crate::json_options::ParseOptions;
+use crate::json_parser::JsonParser;
+use std::io::{BufRead, Write};
+use std::marker::PhantomData;
+
+/// Reads newline-delimited JSON (NDJSON, JSON Lines): one value per line. Blank lines
+/// are skipped, and error positions count lines and bytes from the start of the input.
+///
+/// ```ignore
+/// for record in NdjsonReader::new(BufReader::new(file)) {
+///     let record = record?;
+/// }
+/// ```
+pub struct NdjsonReader<R> {
+    reader: R,
+    line: usize,
+    offset: usize,
+    skip_invalid: bool,
+    skipped: usize,
+    buffer: Vec<u8>,
+    done: bool,
+    options: ParseOptions,
+}
+
+impl<R: BufRead> NdjsonReader<R> {
+    pub fn new(reader: R) -> NdjsonReader<R> {
+        NdjsonReader {
+            reader,
+            line: 0,
+            offset: 0,
+            skip_invalid: false,
+            skipped: 0,
+            buffer: Vec::new(),
+            done: false,
+            options: ParseOptions::new(),
+        }
+    }
+
+    /// Parses each line with `options`. The limits apply to each line on its own.
+    pub fn with_options(mut self, options: ParseOptions) -> NdjsonReader<R> {
+        self.options = options;
+        self
+    }
+
+    /// Passes over lines that cannot be parsed (or converted, for `values`) instead of
+    /// reporting them. I/O errors are still reported, and end the iteration.
+    pub fn with_skip_invalid_lines(mut self, skip_invalid: bool) -> NdjsonReader<R> {
+        self.skip_invalid = skip_invalid;
+        self
+    }
+
+    /// The number of the last line read, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// How many invalid lines have been passed over.
+    pub fn skipped_lines(&self) -> usize {
+        self.skipped
+    }
+
+    /// Converts each line into a `T` as it is read.
+    pub fn values<T: FromJson>(self) -> NdjsonValues<R, T> {
+        NdjsonValues {
+            reader: self,
+            marker: PhantomData,
+        }
+    }
+
+    /// Reads the next line that is not blank into `buffer`, returning where it starts.
+    fn read_line(&mut self) -> Result<Option<Position>, ParseError> {
+        loop {
+            let start = Position {
+                offset: self.offset,
+                line: self.line + 1,
+                column: 1,
+            };
+            self.buffer.clear();
+            let read = self
+                .reader
+                .read_until(b'\n', &mut self.buffer)
+                .map_err(|err| ParseError::new(ParseErrorKind::Io(err.to_string()), start))?;
+            if read == 0 {
+                return Ok(None);
+            }
+            self.line += 1;
+            self.offset += read;
+            if !self.buffer.iter().all(u8::is_ascii_whitespace) {
+                return Ok(Some(start));
+            }
+        }
+    }
+
+    fn parse_line(&self, start: Position) -> Result<JsonNode, ParseError> {
+        let text = std::str::from_utf8(&self.buffer).map_err(|err| {
+            let valid = std::str::from_utf8(&self.buffer[..err.valid_up_to()]).unwrap_or_default();
+            ParseError::new(ParseErrorKind::InvalidUtf8, advanced(start, valid))
+        })?;
+        JsonParser::new(JsonLexer::new(text).with_options(self.options))
+            .parse_document()
+            .map_err(|err| {
+                let position = advanced(start, &text[..err.position().offset]);
+                ParseError::new(err.kind().clone(), position)
+            })
+    }
+
+    /// The next line turned into a `T` by `convert`, which is given the position the
+    /// line starts at.
+    fn next_with<T>(
+        &mut self,
+        convert: impl Fn(JsonNode, Position) -> Result<T, ParseError>,
+    ) -> Option<Result<T, ParseError>> {
+        if self.done {
+            return None;
+        }
+        loop {
+            let start = match self.read_line() {
+                Ok(Some(start)) => start,
+                Ok(None) => return None,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            };
+            match self.parse_line(start).and_then(|node| convert(node, start)) {
+                Err(_) if self.skip_invalid => self.skipped += 1,
+                result => return Some(result),
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for NdjsonReader<R> {
+    type Item = Result<JsonNode, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with(|node, _| Ok(node))
+    }
+}
+
+/// The lines of an `NdjsonReader` converted into `T`s. A line that parses but does
+/// not convert is reported as `ParseErrorKind::Conversion` at the start of the line.
+pub struct NdjsonValues<R, T> {
+    reader: NdjsonReader<R>,
+    marker: PhantomData<T>,
+}
+
+impl<R: BufRead, T: FromJson> Iterator for NdjsonValues<R, T> {
+    type Item = Result<T, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.reader.next_with(|node, start| {
+            T::try_from_json(&node)
+                .map_err(|err| ParseError::new(ParseErrorKind::Conversion(err), start))
+        })
+    }
+}
+
+/// Writes values as newline-delimited JSON: each one compact, with strings escaped so
+/// that it stays on its own line.
+pub struct NdjsonWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    pub fn new(writer: W) -> NdjsonWriter<W> {
+        NdjsonWriter { writer }
+    }
+
+    pub fn write(&mut self, value: &JsonNode) -> std::io::Result<()> {
+        let mut line = value.dump_compact(true);
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())
+    }
+
+    pub fn write_value<T: ToJson + ?Sized>(&mut self, value: &T) -> std::io::Result<()> {
+        self.write(&value.to_json())
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[derive(Debug, Clone, PartialEq, JsonType)]
+    struct Friend {
+        name: String,
+        age: i32,
+    }
+
+    /// Lines with an invalid third line and a fourth that is not a `Friend`.
+    const FRIENDS: &str = "{\"name\": \"A\", \"age\": 20}\r\n\n  {\"name\": \"B\", \"age\": }\n[1]\n{\"name\": \"C\", \"age\": 22}";
+
+    fn records() -> Vec<JsonNode> {
+        vec![
+            json!({"name": "A", "age": 20}),
+            json!({"name": "line\nbreak \u{1f600}", "age": 21}),
+            json!([1, "two", null]),
+        ]
+    }
+
+    fn write_records() -> String {
+        let mut writer = NdjsonWriter::new(Vec::new());
+        for record in &records() {
+            writer.write(record).unwrap();
+        }
+        writer.write_value(&3.5).unwrap();
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn writer_writes_one_compact_value_per_line() {
+        let output = write_records();
+        assert_eq!(output.lines().count(), 4);
+        assert_eq!(output.lines().nth(2), Some("[1,\"two\",null]"));
+    }
+
+    #[test]
+    fn reader_reads_written_values() {
+        let output = write_records();
+        let read: Vec<JsonNode> = NdjsonReader::new(output.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(read[..3], records()[..]);
+        assert_eq!(read[3], json!(3.5));
+    }
+
+    #[test]
+    fn reader_reports_positions_in_the_input() {
+        let mut reader = NdjsonReader::new(FRIENDS.as_bytes());
+        assert!(reader.next().unwrap().is_ok());
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!((err.position().line, err.position().column), (3, 24));
+        assert_eq!(err.position().offset, FRIENDS.find("}\n").unwrap());
+        assert_eq!(reader.line(), 3);
+        assert_eq!(reader.count(), 2);
+    }
+
+    #[test]
+    fn reader_skips_invalid_lines() {
+        let mut reader = NdjsonReader::new(FRIENDS.as_bytes()).with_skip_invalid_lines(true);
+        assert_eq!(reader.by_ref().count(), 3);
+        assert_eq!(reader.skipped_lines(), 1);
+    }
+
+    #[test]
+    fn values_decode_each_line() {
+        let friends: Vec<Result<Friend, ParseError>> =
+            NdjsonReader::new(FRIENDS.as_bytes()).values().collect();
+        assert_eq!(friends.len(), 4);
+        assert_eq!(friends[0].as_ref().unwrap().name, "A");
+        let err = friends[2].as_ref().unwrap_err();
+        assert!(matches!(err.kind(), ParseErrorKind::Conversion(_)));
+        assert_eq!(err.position().line, 4);
+    }
+
+    #[test]
+    fn values_skip_lines_that_do_not_decode() {
+        let names: Vec<String> = NdjsonReader::new(FRIENDS.as_bytes())
+            .with_skip_invalid_lines(true)
+            .values::<Friend>()
+            .map(|friend| friend.unwrap().name)
+            .collect();
+        assert_eq!(names, ["A", "C"]);
+    }
+}
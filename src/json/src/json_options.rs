@@ -0,0 +1,407 @@
+/// Which extensions to strict JSON (RFC 8259) the parser accepts, and how much input
+/// it takes before giving up. Every extension is off by default; `json5` and `jsonc`
+/// turn on the usual sets for hand-written files.
+///
+/// The limits are for input from untrusted sources. Only nesting is limited by default,
+/// to 128 levels, since dropping a deeper `JsonNode` can overflow the stack; the others
+/// are unlimited until set.
+///
+/// ```ignore
+/// let options = ParseOptions::new().with_comments(true).with_trailing_commas(true);
+/// let config = parse_json_with_options(text, options)?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub(crate) comments: bool,
+    pub(crate) trailing_commas: bool,
+    pub(crate) single_quoted_strings: bool,
+    pub(crate) unquoted_keys: bool,
+    pub(crate) hex_numbers: bool,
+    pub(crate) non_finite_numbers: bool,
+    pub(crate) multiline_strings: bool,
+    pub(crate) max_depth: usize,
+    pub(crate) max_input_bytes: usize,
+    pub(crate) max_string_length: usize,
+    pub(crate) max_number_length: usize,
+    pub(crate) max_object_members: usize,
+    pub(crate) max_array_length: usize,
+    pub(crate) duplicate_keys: DuplicateKeyPolicy,
+}
+
+/// What to do when an object has the same key more than once. Parsers disagree on
+/// which value counts, so for input that other programs read too, `Error` is safest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeyPolicy {
+    /// Fail with `ParseErrorKind::DuplicateKey`, which has both positions.
+    Error,
+    FirstWins,
+    #[default]
+    LastWins,
+    /// Keep every value, in order, in an array under the key. Keys that appear only
+    /// once keep their value as it is.
+    Collect,
+}
+
+impl ParseOptions {
+    /// Strict JSON.
+    pub fn new() -> ParseOptions {
+        ParseOptions {
+            comments: false,
+            trailing_commas: false,
+            single_quoted_strings: false,
+            unquoted_keys: false,
+            hex_numbers: false,
+            non_finite_numbers: false,
+            multiline_strings: false,
+            max_depth: 128,
+            max_input_bytes: usize::MAX,
+            max_string_length: usize::MAX,
+            max_number_length: usize::MAX,
+            max_object_members: usize::MAX,
+            max_array_length: usize::MAX,
+            duplicate_keys: DuplicateKeyPolicy::LastWins,
+        }
+    }
+
+    /// All the extensions JSON5 makes to JSON that `ParseOptions` supports.
+    pub fn json5() -> ParseOptions {
+        ParseOptions {
+            comments: true,
+            trailing_commas: true,
+            single_quoted_strings: true,
+            unquoted_keys: true,
+            hex_numbers: true,
+            non_finite_numbers: true,
+            multiline_strings: true,
+            ..ParseOptions::new()
+        }
+    }
+
+    /// JSON with comments and trailing commas, as in editor settings files.
+    pub fn jsonc() -> ParseOptions {
+        ParseOptions::new()
+            .with_comments(true)
+            .with_trailing_commas(true)
+    }
+
+    /// `// line` and `/* block */` comments wherever whitespace may appear.
+    pub fn with_comments(mut self, comments: bool) -> ParseOptions {
+        self.comments = comments;
+        self
+    }
+
+    /// A `,` after the last element of an array or member of an object.
+    pub fn with_trailing_commas(mut self, trailing_commas: bool) -> ParseOptions {
+        self.trailing_commas = trailing_commas;
+        self
+    }
+
+    /// Strings in `'single quotes'`, in which `"` needs no escape. `\'` is accepted in
+    /// either kind of string.
+    pub fn with_single_quoted_strings(mut self, single_quoted_strings: bool) -> ParseOptions {
+        self.single_quoted_strings = single_quoted_strings;
+        self
+    }
+
+    /// Object keys written as bare identifiers, such as `{name: "A"}`.
+    pub fn with_unquoted_keys(mut self, unquoted_keys: bool) -> ParseOptions {
+        self.unquoted_keys = unquoted_keys;
+        self
+    }
+
+    /// Hexadecimal integers such as `0xFF` and `-0x10`.
+    pub fn with_hex_numbers(mut self, hex_numbers: bool) -> ParseOptions {
+        self.hex_numbers = hex_numbers;
+        self
+    }
+
+    /// `Infinity`, `-Infinity` and `NaN` as numbers.
+    pub fn with_non_finite_numbers(mut self, non_finite_numbers: bool) -> ParseOptions {
+        self.non_finite_numbers = non_finite_numbers;
+        self
+    }
+
+    /// Strings continued on the next line by ending the line with a `\`. The escaped
+    /// line break is not part of the string.
+    pub fn with_multiline_strings(mut self, multiline_strings: bool) -> ParseOptions {
+        self.multiline_strings = multiline_strings;
+        self
+    }
+
+    /// How many objects and arrays may be nested inside each other; the root counts
+    /// as one level.
+    pub fn with_max_depth(mut self, max_depth: usize) -> ParseOptions {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// The length of the whole input in bytes, whitespace included.
+    pub fn with_max_input_bytes(mut self, max_input_bytes: usize) -> ParseOptions {
+        self.max_input_bytes = max_input_bytes;
+        self
+    }
+
+    /// The length of a string or key in bytes, after escapes are decoded.
+    pub fn with_max_string_length(mut self, max_string_length: usize) -> ParseOptions {
+        self.max_string_length = max_string_length;
+        self
+    }
+
+    /// The number of characters in a number as written.
+    pub fn with_max_number_length(mut self, max_number_length: usize) -> ParseOptions {
+        self.max_number_length = max_number_length;
+        self
+    }
+
+    pub fn with_max_object_members(mut self, max_object_members: usize) -> ParseOptions {
+        self.max_object_members = max_object_members;
+        self
+    }
+
+    pub fn with_max_array_length(mut self, max_array_length: usize) -> ParseOptions {
+        self.max_array_length = max_array_length;
+        self
+    }
+
+    /// How to treat a key that appears twice in one object; the last value wins by
+    /// default.
+    pub fn with_duplicate_keys(mut self, duplicate_keys: DuplicateKeyPolicy) -> ParseOptions {
+        self.duplicate_keys = duplicate_keys;
+        self
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    const CONFIG: &str = r#"
+        // Written by hand.
+        {
+            name: 'Yiran "Mushroom"', /* nickname */
+            $id: 0xFF,
+            limits: [Infinity, -Infinity, -0x10,],
+            'quoted': "it\'s",
+            text: "one \
+two",
+        }
+    "#;
+
+    #[test]
+    fn json5_accepts_relaxed_syntax() {
+        let value = parse_json_with_options(CONFIG, ParseOptions::json5()).unwrap();
+        assert_eq!(value["name"], json!("Yiran \"Mushroom\""));
+        assert_eq!(value["$id"], json!(255));
+        assert_eq!(
+            value["limits"],
+            json!([f64::INFINITY, f64::NEG_INFINITY, -16])
+        );
+        assert_eq!(value["quoted"], json!("it's"));
+        assert_eq!(value["text"], json!("one two"));
+    }
+
+    #[test]
+    fn json5_reads_nan_and_keyword_keys() {
+        let value = parse_json_with_options("[NaN, {true: null}]", ParseOptions::json5()).unwrap();
+        assert!(matches!(value[0], JsonNode::Number(n) if n.is_nan()));
+        assert_eq!(value[1], json!({"true": null}));
+    }
+
+    #[test]
+    fn json5_rejects_unknown_literals() {
+        assert!(parse_json_with_options("[,]", ParseOptions::json5()).is_err());
+        let err = parse_json_with_options("{a: nope}", ParseOptions::json5()).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseErrorKind::InvalidLiteral("nope".to_string())
+        );
+        assert_eq!(err.position().column, 5);
+    }
+
+    #[test]
+    fn strict_parsing_rejects_extensions() {
+        assert!(parse_json(CONFIG).is_none());
+        assert!(parse_json("[1, 2,]").is_none());
+    }
+
+    #[test]
+    fn jsonc_allows_comments_and_trailing_commas() {
+        let text = "{\"a\": [1, 2,], /* b */ \"b\": {},} // end";
+        assert_eq!(
+            parse_json_with_options(text, ParseOptions::jsonc()).unwrap(),
+            json!({"a": [1, 2], "b": {}})
+        );
+        assert!(parse_json_with_options("{a: 1}", ParseOptions::jsonc()).is_err());
+        assert!(parse_json_with_options("0x10", ParseOptions::jsonc()).is_err());
+    }
+
+    #[test]
+    fn comments_alone_keep_trailing_commas_invalid() {
+        let options = ParseOptions::new().with_comments(true);
+        let err = parse_json_with_options("[1, /* x */ 2,]", options).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseErrorKind::UnexpectedToken {
+                found: "']'".to_string(),
+                expected: "a value"
+            }
+        );
+        assert_eq!(err.position().offset, 14);
+    }
+
+    #[test]
+    fn unterminated_comments_are_errors() {
+        let options = ParseOptions::new().with_comments(true);
+        let err = parse_json_with_options("[1, /* x ", options).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnexpectedEnd);
+    }
+
+    #[test]
+    fn hex_numbers_need_digits() {
+        let options = ParseOptions::new().with_hex_numbers(true);
+        assert_eq!(parse_json_with_options("0x1F", options), Ok(json!(31)));
+        assert!(parse_json_with_options("0x", options).is_err());
+    }
+
+    #[test]
+    fn depth_is_limited_by_default() {
+        let deep = "[".repeat(100_000) + &"]".repeat(100_000);
+        let err = parse_json_with_options(&deep, ParseOptions::new()).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::DepthLimitExceeded(128));
+        assert_eq!(err.position().offset, 128);
+        assert!(parse_json(&deep).is_none());
+        let err = parse_reader(deep.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::DepthLimitExceeded(128));
+    }
+
+    #[test]
+    fn unlimited_depth_does_not_overflow_the_stack() {
+        let unclosed = "{\"a\": ".repeat(100_000);
+        let unlimited = ParseOptions::new().with_max_depth(usize::MAX);
+        let err = parse_json_with_options(&unclosed, unlimited).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnexpectedEnd);
+    }
+
+    #[test]
+    fn max_depth_counts_nested_containers() {
+        let nested = "[[1], {\"a\": [2]}]";
+        let parse = |max_depth| {
+            parse_json_with_options(nested, ParseOptions::new().with_max_depth(max_depth))
+        };
+        assert!(parse(3).is_ok());
+        assert_eq!(
+            parse(2).unwrap_err().kind(),
+            &ParseErrorKind::DepthLimitExceeded(2)
+        );
+    }
+
+    #[test]
+    fn size_limits_report_where_they_were_exceeded() {
+        let cases = [
+            (
+                ParseOptions::new().with_max_input_bytes(8),
+                "[1, 2, 3]",
+                ParseErrorKind::InputTooLarge(8),
+                8,
+            ),
+            (
+                ParseOptions::new().with_max_input_bytes(9),
+                "[1, 2, 3]  ",
+                ParseErrorKind::InputTooLarge(9),
+                9,
+            ),
+            (
+                ParseOptions::new().with_max_string_length(3),
+                "[\"abc\", \"ab\\u00e9\"]",
+                ParseErrorKind::StringTooLong(3),
+                8,
+            ),
+            (
+                ParseOptions::new().with_max_string_length(3),
+                "{\"abcd\": 1}",
+                ParseErrorKind::StringTooLong(3),
+                1,
+            ),
+            (
+                ParseOptions::new().with_max_number_length(4),
+                "[1234, -1.5e10]",
+                ParseErrorKind::NumberTooLong(4),
+                7,
+            ),
+            (
+                ParseOptions::new().with_max_object_members(2),
+                "{\"a\": 1, \"b\": {}, \"c\": 3}",
+                ParseErrorKind::TooManyMembers(2),
+                18,
+            ),
+            (
+                ParseOptions::new().with_max_array_length(2),
+                "[[1, 2], 3, 4]",
+                ParseErrorKind::ArrayTooLong(2),
+                12,
+            ),
+        ];
+        for (options, input, kind, offset) in cases {
+            let err = parse_json_with_options(input, options).unwrap_err();
+            assert_eq!(
+                (err.kind(), err.position().offset),
+                (&kind, offset),
+                "{}",
+                input
+            );
+            let err = parse_reader_with_options(input.as_bytes(), options).unwrap_err();
+            assert_eq!((err.kind(), err.position().offset), (&kind, offset));
+            let err = parse_buf_reader_with_options(input.as_bytes(), options).unwrap_err();
+            assert_eq!((err.kind(), err.position().offset), (&kind, offset));
+        }
+    }
+
+    #[test]
+    fn values_at_the_limits_are_accepted() {
+        let options = ParseOptions::new()
+            .with_max_input_bytes(9)
+            .with_max_string_length(3)
+            .with_max_number_length(4)
+            .with_max_object_members(2)
+            .with_max_array_length(2);
+        assert_eq!(
+            parse_json_with_options("{\"a\":[1]}", options),
+            Ok(json!({"a": [1]}))
+        );
+    }
+
+    #[test]
+    fn event_reader_applies_limits() {
+        let options = ParseOptions::new().with_max_string_length(3);
+        let mut reader = JsonEventReader::new("[\"abcd\"]".as_bytes()).with_options(options);
+        assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::StartArray));
+        assert_eq!(
+            reader.next_event().unwrap_err().kind(),
+            &ParseErrorKind::StringTooLong(3)
+        );
+    }
+
+    #[test]
+    fn streams_apply_limits_to_each_value() {
+        let mut stream =
+            JsonStream::new("[1] [[2]]").with_options(ParseOptions::new().with_max_depth(1));
+        assert_eq!(stream.next().unwrap().unwrap().0, json!([1]));
+        assert_eq!(
+            stream.next().unwrap().unwrap_err().kind(),
+            &ParseErrorKind::DepthLimitExceeded(1)
+        );
+
+        let options = ParseOptions::new().with_max_string_length(3);
+        let mut lines = NdjsonReader::new("\"abc\"\n\"abcd\"\n".as_bytes()).with_options(options);
+        assert_eq!(lines.next().unwrap().unwrap(), json!("abc"));
+        let err = lines.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::StringTooLong(3));
+        assert_eq!((err.position().line, err.position().offset), (2, 6));
+    }
+}
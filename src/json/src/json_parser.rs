@@ -1,78 +1,620 @@
 use crate::json_basic::*;
+use crate::json_error::{ParseError, ParseErrorKind, Position};
 use crate::json_impl::JsonToken;
-use std::collections::{HashMap, LinkedList};
+use crate::json_lexer::{keyword_token, JsonLexer, JsonSource};
+use crate::json_options::{DuplicateKeyPolicy, ParseOptions};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::ops::Range;
 
-struct JsonParser {
-    tokens: LinkedList<JsonToken>,
+/// One step through a JSON document. Scalars are carried as `JsonNode`s.
+pub enum ParserEvent {
+    StartObject,
+    Key(String),
+    StartArray,
+    Value(JsonNode),
+    EndArray,
+    EndObject,
 }
 
-impl JsonParser {
-    pub fn new(tokens: LinkedList<JsonToken>) -> JsonParser {
-        JsonParser { tokens }
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    RootValue,
+    ArrayStart,
+    ArrayElement,
+    ArrayNext,
+    ObjectStart,
+    ObjectKey,
+    ObjectColon,
+    ObjectValue,
+    ObjectNext,
+    Done,
+}
+
+impl State {
+    fn expected(self) -> &'static str {
+        match self {
+            State::RootValue | State::ArrayElement | State::ObjectValue => "a value",
+            State::ArrayStart => "a value or ']'",
+            State::ArrayNext => "',' or ']'",
+            State::ObjectStart => "a string key or '}'",
+            State::ObjectKey => "a string key",
+            State::ObjectColon => "':'",
+            State::ObjectNext => "',' or '}'",
+            State::Done => "the end of the input",
+        }
     }
+}
 
-    pub fn parse(&mut self) -> Option<JsonNode> {
-        self.parse_value()
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Container {
+    Object,
+    Array,
+}
+
+struct OpenContainer {
+    container: Container,
+    /// How many entries have been read so far.
+    entries: usize,
+    /// Where each key was first seen, kept only to reject duplicates.
+    keys: HashMap<String, Position>,
+}
+
+impl OpenContainer {
+    fn new(container: Container) -> OpenContainer {
+        OpenContainer {
+            container,
+            entries: 0,
+            keys: HashMap::new(),
+        }
     }
+}
 
-    pub fn parse_value(&mut self) -> Option<JsonNode> {
-        match self.tokens.pop_front() {
-            Some(JsonToken::String(s)) => Some(JsonNode::String(s)),
-            Some(JsonToken::Number(n)) => Some(JsonNode::Number(n)),
-            Some(JsonToken::Boolean(b)) => Some(JsonNode::Boolean(b)),
-            Some(JsonToken::Null) => Some(JsonNode::Null),
-            Some(JsonToken::LeftBrace) => self.parse_object(),
-            Some(JsonToken::LeftBracket) => self.parse_array(),
-            _ => None,
+/// The JSON grammar as a state machine: accepts tokens one at a time and turns them
+/// into events, keeping the open containers on an explicit stack instead of recursing.
+pub struct JsonGrammar {
+    stack: Vec<OpenContainer>,
+    state: State,
+    options: ParseOptions,
+}
+
+impl JsonGrammar {
+    pub fn new() -> JsonGrammar {
+        JsonGrammar {
+            stack: Vec::new(),
+            state: State::RootValue,
+            options: ParseOptions::new(),
         }
     }
 
-    pub fn parse_object(&mut self) -> Option<JsonNode> {
-        let mut obj = HashMap::new();
+    pub fn with_options(mut self, options: ParseOptions) -> JsonGrammar {
+        self.options = options;
+        self
+    }
 
-        loop {
-            match self.tokens.pop_front() {
-                Some(JsonToken::RightBrace) => break Some(JsonNode::Object(obj)),
-                Some(JsonToken::String(key)) => match self.tokens.pop_front() {
-                    Some(JsonToken::Colon) => {
-                        let value = self.parse_value()?;
-                        obj.insert(key, value);
-                        match self.tokens.pop_front() {
-                            Some(JsonToken::Comma) => continue,
-                            Some(JsonToken::RightBrace) => break Some(JsonNode::Object(obj)),
-                            _ => break None,
-                        }
-                    }
-                    _ => break None,
+    pub fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    /// Whether the root value is complete.
+    pub fn is_done(&self) -> bool {
+        self.state == State::Done
+    }
+
+    /// Whether no token of the current root value has been accepted yet.
+    pub fn is_at_root(&self) -> bool {
+        self.state == State::RootValue
+    }
+
+    /// Whether the next token must be an object key (or the end of the object).
+    pub fn expects_key(&self) -> bool {
+        matches!(self.state, State::ObjectStart | State::ObjectKey)
+    }
+
+    /// Expects another root value.
+    pub fn reset(&mut self) {
+        self.stack.clear();
+        self.state = State::RootValue;
+    }
+
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Whether the enclosing container may end with the next token.
+    fn may_end_container(&self) -> bool {
+        match self.state {
+            State::ArrayStart | State::ArrayNext | State::ObjectStart | State::ObjectNext => true,
+            State::ArrayElement | State::ObjectKey => self.options.trailing_commas,
+            _ => false,
+        }
+    }
+
+    /// The event `token` produces, or `None` for punctuation that only moves the
+    /// state along.
+    pub fn accept(
+        &mut self,
+        token: JsonToken,
+        position: Position,
+    ) -> Result<Option<ParserEvent>, ParseError> {
+        let event = match (self.state, token) {
+            (State::ObjectColon, JsonToken::Colon) => {
+                self.state = State::ObjectValue;
+                return Ok(None);
+            }
+            (State::ArrayNext, JsonToken::Comma) => {
+                self.state = State::ArrayElement;
+                return Ok(None);
+            }
+            (State::ObjectNext, JsonToken::Comma) => {
+                self.state = State::ObjectKey;
+                return Ok(None);
+            }
+            (
+                State::ArrayStart | State::ArrayNext | State::ArrayElement,
+                JsonToken::RightBracket,
+            ) if self.may_end_container() => {
+                self.end_container();
+                ParserEvent::EndArray
+            }
+            (State::ObjectStart | State::ObjectNext | State::ObjectKey, JsonToken::RightBrace)
+                if self.may_end_container() =>
+            {
+                self.end_container();
+                ParserEvent::EndObject
+            }
+            (
+                State::ObjectStart | State::ObjectKey,
+                JsonToken::String(key) | JsonToken::Identifier(key),
+            ) => {
+                let max = self.options.max_object_members;
+                self.count_entry(max, ParseErrorKind::TooManyMembers(max), position)?;
+                if self.options.duplicate_keys == DuplicateKeyPolicy::Error {
+                    self.check_duplicate(&key, position)?;
+                }
+                self.state = State::ObjectColon;
+                ParserEvent::Key(key)
+            }
+            (
+                State::RootValue | State::ArrayStart | State::ArrayElement | State::ObjectValue,
+                token,
+            ) => {
+                let token = match token {
+                    JsonToken::Identifier(name) => keyword_token(name, &self.options)
+                        .map_err(|kind| ParseError::new(kind, position))?,
+                    token => token,
+                };
+                if matches!(token, JsonToken::LeftBrace | JsonToken::LeftBracket)
+                    && self.stack.len() >= self.options.max_depth
+                {
+                    let max = self.options.max_depth;
+                    return Err(ParseError::new(
+                        ParseErrorKind::DepthLimitExceeded(max),
+                        position,
+                    ));
+                }
+                if matches!(self.state, State::ArrayStart | State::ArrayElement) {
+                    let max = self.options.max_array_length;
+                    self.count_entry(max, ParseErrorKind::ArrayTooLong(max), position)?;
+                }
+                match self.start_value(token) {
+                    Ok(event) => event,
+                    Err(token) => return Err(self.unexpected(&token, position)),
+                }
+            }
+            (_, token) => return Err(self.unexpected(&token, position)),
+        };
+        Ok(Some(event))
+    }
+
+    /// Counts an entry of the innermost container, failing with `kind` past `max`.
+    fn count_entry(
+        &mut self,
+        max: usize,
+        kind: ParseErrorKind,
+        position: Position,
+    ) -> Result<(), ParseError> {
+        if let Some(open) = self.stack.last_mut() {
+            open.entries += 1;
+            if open.entries > max {
+                return Err(ParseError::new(kind, position));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_duplicate(&mut self, key: &str, position: Position) -> Result<(), ParseError> {
+        let Some(open) = self.stack.last_mut() else {
+            return Ok(());
+        };
+        match open.keys.get(key) {
+            Some(&first) => Err(ParseError::new(
+                ParseErrorKind::DuplicateKey {
+                    key: key.to_string(),
+                    first,
                 },
-                _ => break None,
+                position,
+            )),
+            None => {
+                open.keys.insert(key.to_string(), position);
+                Ok(())
+            }
+        }
+    }
+
+    fn unexpected(&self, token: &JsonToken, position: Position) -> ParseError {
+        ParseError::new(
+            ParseErrorKind::UnexpectedToken {
+                found: token.describe(),
+                expected: self.state.expected(),
+            },
+            position,
+        )
+    }
+
+    /// Starts a value with `token`, or gives the token back if it cannot start one.
+    fn start_value(&mut self, token: JsonToken) -> Result<ParserEvent, JsonToken> {
+        let scalar = match token {
+            JsonToken::LeftBrace => {
+                self.stack.push(OpenContainer::new(Container::Object));
+                self.state = State::ObjectStart;
+                return Ok(ParserEvent::StartObject);
+            }
+            JsonToken::LeftBracket => {
+                self.stack.push(OpenContainer::new(Container::Array));
+                self.state = State::ArrayStart;
+                return Ok(ParserEvent::StartArray);
+            }
+            JsonToken::String(s) => JsonNode::String(s),
+            JsonToken::Number(n) => JsonNode::Number(n),
+            JsonToken::Boolean(b) => JsonNode::Boolean(b),
+            JsonToken::Null => JsonNode::Null,
+            token => return Err(token),
+        };
+        self.after_value();
+        Ok(ParserEvent::Value(scalar))
+    }
+
+    fn end_container(&mut self) {
+        self.stack.pop();
+        self.after_value();
+    }
+
+    fn after_value(&mut self) {
+        self.state = match self.stack.last().map(|open| open.container) {
+            None => State::Done,
+            Some(Container::Array) => State::ArrayNext,
+            Some(Container::Object) => State::ObjectNext,
+        };
+    }
+}
+
+impl Default for JsonGrammar {
+    fn default() -> JsonGrammar {
+        JsonGrammar::new()
+    }
+}
+
+/// An object or array still being built, with the key of the member being read and,
+/// for `DuplicateKeyPolicy::Collect`, the keys whose values have been collected.
+pub(crate) type PartialContainer<V, K> = (V, Option<K>, HashSet<K>);
+
+/// A value that `insert_member` can collect repeated members into.
+pub(crate) trait MemberValue: Default {
+    fn array(values: Vec<Self>) -> Self;
+
+    fn as_array_mut(&mut self) -> Option<&mut Vec<Self>>;
+}
+
+impl MemberValue for JsonNode {
+    fn array(values: Vec<JsonNode>) -> JsonNode {
+        JsonNode::Array(values)
+    }
+
+    fn as_array_mut(&mut self) -> Option<&mut Vec<JsonNode>> {
+        match self {
+            JsonNode::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+/// Adds the member `key: value` to `obj`, following `policy` if `key` is already
+/// there. `DuplicateKeyPolicy::Error` is left to `JsonGrammar`, so here it overwrites.
+pub(crate) fn insert_member<K: Eq + Hash + Clone, V: MemberValue>(
+    obj: &mut HashMap<K, V>,
+    collected: &mut HashSet<K>,
+    key: K,
+    value: V,
+    policy: DuplicateKeyPolicy,
+) {
+    match (policy, obj.entry(key)) {
+        (_, Entry::Vacant(entry)) => {
+            entry.insert(value);
+        }
+        (DuplicateKeyPolicy::FirstWins, Entry::Occupied(_)) => {}
+        (DuplicateKeyPolicy::Collect, Entry::Occupied(mut entry)) => {
+            if collected.insert(entry.key().clone()) {
+                let first = std::mem::take(entry.get_mut());
+                *entry.get_mut() = V::array(vec![first, value]);
+            } else if let Some(values) = entry.get_mut().as_array_mut() {
+                values.push(value);
+            }
+        }
+        (_, Entry::Occupied(mut entry)) => {
+            entry.insert(value);
+        }
+    }
+}
+
+/// Builds `JsonNode`s from events, holding the containers that are still open.
+#[derive(Default)]
+pub struct TreeBuilder {
+    containers: Vec<PartialContainer<JsonNode, String>>,
+    duplicate_keys: DuplicateKeyPolicy,
+}
+
+impl TreeBuilder {
+    pub fn with_duplicate_keys(mut self, duplicate_keys: DuplicateKeyPolicy) -> TreeBuilder {
+        self.duplicate_keys = duplicate_keys;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.containers.is_empty()
+    }
+
+    /// Adds `event`, returning the value it completes at the outermost level.
+    pub fn push(&mut self, event: ParserEvent) -> Option<JsonNode> {
+        let value = match event {
+            ParserEvent::StartObject => {
+                self.containers
+                    .push((JsonNode::Object(HashMap::new()), None, HashSet::new()));
+                return None;
             }
+            ParserEvent::StartArray => {
+                self.containers
+                    .push((JsonNode::Array(Vec::new()), None, HashSet::new()));
+                return None;
+            }
+            ParserEvent::Key(key) => {
+                if let Some((_, pending_key, _)) = self.containers.last_mut() {
+                    *pending_key = Some(key);
+                }
+                return None;
+            }
+            ParserEvent::Value(value) => value,
+            ParserEvent::EndArray | ParserEvent::EndObject => self.containers.pop()?.0,
+        };
+        match self.containers.last_mut() {
+            None => Some(value),
+            Some((JsonNode::Object(obj), key, collected)) => {
+                let key = key.take().unwrap_or_default();
+                insert_member(obj, collected, key, value, self.duplicate_keys);
+                None
+            }
+            Some((JsonNode::Array(arr), _, _)) => {
+                arr.push(value);
+                None
+            }
+            Some(_) => unreachable!(),
+        }
+    }
+}
+
+/// Pulls tokens from a `JsonLexer` through a `JsonGrammar`.
+pub struct JsonParser<S> {
+    lexer: JsonLexer<S>,
+    peeked: Option<(JsonToken, Position)>,
+    grammar: JsonGrammar,
+}
+
+impl<S: JsonSource> JsonParser<S> {
+    pub fn new(lexer: JsonLexer<S>) -> JsonParser<S> {
+        JsonParser {
+            grammar: JsonGrammar::new().with_options(lexer.options()),
+            lexer,
+            peeked: None,
         }
     }
 
-    pub fn parse_array(&mut self) -> Option<JsonNode> {
-        let mut arr = Vec::new();
+    pub fn with_options(mut self, options: ParseOptions) -> JsonParser<S> {
+        self.lexer = self.lexer.with_options(options);
+        self.grammar = self.grammar.with_options(options);
+        self
+    }
+
+    fn peek_token(&mut self) -> Result<Option<&(JsonToken, Position)>, ParseError> {
+        if self.peeked.is_none() {
+            self.peeked = self.lexer.next_token()?;
+        }
+        Ok(self.peeked.as_ref())
+    }
+
+    fn next_token(&mut self) -> Result<(JsonToken, Position), ParseError> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.lexer.next_token()?.ok_or_else(|| {
+                ParseError::new(ParseErrorKind::UnexpectedEnd, self.lexer.position())
+            }),
+        }
+    }
 
+    /// The next event and the position of the token it comes from, or `None` once the
+    /// root value is complete.
+    pub fn next_event(&mut self) -> Result<Option<(ParserEvent, Position)>, ParseError> {
+        while !self.grammar.is_done() {
+            let (token, position) = self.next_token()?;
+            if let Some(event) = self.grammar.accept(token, position)? {
+                return Ok(Some((event, position)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Skips the next value and everything nested in it. Where a key is expected the
+    /// whole member is skipped; if the enclosing container ends instead, nothing is.
+    pub fn skip_value(&mut self) -> Result<(), ParseError> {
+        if self.grammar.is_done() {
+            return Ok(());
+        }
+        if self.grammar.may_end_container() {
+            if let Some((JsonToken::RightBracket | JsonToken::RightBrace, _)) = self.peek_token()? {
+                return Ok(());
+            }
+        }
+
+        let depth = self.grammar.depth();
+        self.lexer.store_strings = false;
+        let result = loop {
+            match self.next_event() {
+                Ok(Some((ParserEvent::Key(_), _)))
+                | Ok(Some((ParserEvent::StartObject | ParserEvent::StartArray, _))) => {}
+                Ok(Some(_)) if self.grammar.depth() > depth => {}
+                Ok(_) => break Ok(()),
+                Err(err) => break Err(err),
+            }
+        };
+        self.lexer.store_strings = true;
+        result
+    }
+
+    /// Reads the next complete value, building objects and arrays without recursion.
+    pub fn parse_value(&mut self) -> Result<JsonNode, ParseError> {
+        let mut builder =
+            TreeBuilder::default().with_duplicate_keys(self.grammar.options().duplicate_keys);
         loop {
-            match self.tokens.front() {
-                Some(JsonToken::RightBracket) => {
-                    self.tokens.pop_front();
-                    break Some(JsonNode::Array(arr))
-                },
-                Some(JsonToken::Comma) => {
-                    self.tokens.pop_front();
-                    continue
+            let (event, position) = match self.next_event()? {
+                Some(event) => event,
+                None => {
+                    return Err(ParseError::new(
+                        ParseErrorKind::UnexpectedEnd,
+                        self.lexer.position(),
+                    ))
                 }
-                Some(_) => {
-                    arr.push(self.parse_value()?);
+            };
+            if builder.is_empty() {
+                // Called in the middle of a container that ends here.
+                let found = match event {
+                    ParserEvent::EndArray => Some(JsonToken::RightBracket),
+                    ParserEvent::EndObject => Some(JsonToken::RightBrace),
+                    _ => None,
+                };
+                if let Some(found) = found {
+                    return Err(ParseError::new(
+                        ParseErrorKind::UnexpectedToken {
+                            found: found.describe(),
+                            expected: "a value",
+                        },
+                        position,
+                    ));
                 }
-                None => break None,
+            }
+            if let Some(value) = builder.push(event) {
+                return Ok(value);
             }
         }
     }
+
+    /// Reads a value that must make up the rest of the input.
+    pub fn parse_document(&mut self) -> Result<JsonNode, ParseError> {
+        let value = self.parse_value()?;
+        let position = match &self.peeked {
+            Some((_, position)) => *position,
+            None if self.lexer.at_end()? => return Ok(value),
+            None => self.lexer.position(),
+        };
+        Err(ParseError::new(
+            ParseErrorKind::TrailingCharacters,
+            position,
+        ))
+    }
+
+    /// Reads the next of several root values that follow each other, with the range
+    /// of bytes it spans, or `None` at the end of the input. Record separators before
+    /// the value are skipped.
+    pub fn parse_next(&mut self) -> Result<Option<(JsonNode, Range<usize>)>, ParseError> {
+        let start = loop {
+            match self.peek_token()? {
+                None => return Ok(None),
+                Some((JsonToken::RecordSeparator, _)) => self.peeked = None,
+                Some((_, position)) => break position.offset,
+            }
+        };
+        self.grammar.reset();
+        let value = self.parse_value()?;
+        Ok(Some((value, start..self.lexer.position().offset)))
+    }
 }
 
-pub fn parse_all(tokens: LinkedList<JsonToken>) -> Option<JsonNode> {
-    let mut parser = JsonParser::new(tokens);
-    parser.parse()
+pub fn parse_str_with_options(input: &str, options: ParseOptions) -> Result<JsonNode, ParseError> {
+    JsonParser::new(JsonLexer::new(input).with_options(options)).parse_document()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    const REPEATED: &str =
+        "{\"role\": \"user\",\n \"nested\": {\"role\": 1},\n \"role\": \"admin\", \"role\": [0]}";
+
+    fn parse(policy: DuplicateKeyPolicy) -> Result<JsonNode, ParseError> {
+        parse_json_with_options(REPEATED, ParseOptions::new().with_duplicate_keys(policy))
+    }
+
+    #[test]
+    fn last_duplicate_wins_by_default() {
+        assert_eq!(
+            parse(DuplicateKeyPolicy::LastWins).unwrap()["role"],
+            json!([0])
+        );
+        assert_eq!(parse_json(REPEATED).unwrap()["role"], json!([0]));
+    }
+
+    #[test]
+    fn first_duplicate_can_win() {
+        assert_eq!(
+            parse(DuplicateKeyPolicy::FirstWins).unwrap()["role"],
+            json!("user")
+        );
+    }
+
+    #[test]
+    fn duplicates_can_be_collected() {
+        assert_eq!(
+            parse(DuplicateKeyPolicy::Collect).unwrap(),
+            json!({"role": ["user", "admin", [0]], "nested": {"role": 1}})
+        );
+    }
+
+    #[test]
+    fn duplicate_errors_point_at_both_keys() {
+        let err = parse(DuplicateKeyPolicy::Error).unwrap_err();
+        let ParseErrorKind::DuplicateKey { key, first } = err.kind() else {
+            panic!("unexpected error {}", err);
+        };
+        assert_eq!(key, "role");
+        assert_eq!((first.line, first.column), (1, 2));
+        assert_eq!((err.position().line, err.position().column), (3, 2));
+        assert_eq!(
+            err.to_string(),
+            "Duplicate key \"role\" (first seen at line 1, column 2) at line 3, column 2"
+        );
+    }
+
+    #[test]
+    fn keys_are_only_duplicates_within_one_object() {
+        let strict = ParseOptions::new().with_duplicate_keys(DuplicateKeyPolicy::Error);
+        assert!(parse_json_with_options("[{\"a\": 1}, {\"a\": 2}]", strict).is_ok());
+    }
+
+    #[test]
+    fn documents_follow_the_policy() {
+        let first_wins = ParseOptions::new().with_duplicate_keys(DuplicateKeyPolicy::FirstWins);
+        let document = JsonDocument::parse_with_options(REPEATED, first_wins).unwrap();
+        assert_eq!(document.source("/role"), Some("\"user\""));
+        let strict = ParseOptions::new().with_duplicate_keys(DuplicateKeyPolicy::Error);
+        assert!(JsonDocument::parse_with_options(REPEATED, strict).is_err());
+    }
 }
@@ -0,0 +1,613 @@
+use crate::json_basic::JsonNode;
+use crate::json_error::{ParseError, ParseErrorKind, Position};
+use crate::json_impl::JsonToken;
+use crate::json_lexer::{
+    advanced, is_keyword_char, is_number_char, keyword_token, number_token, utf8_width, JsonLexer,
+};
+use crate::json_options::ParseOptions;
+use crate::json_parser::{JsonGrammar, TreeBuilder};
+
+/// What `JsonPushParser::feed` made of a chunk.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PushStatus {
+    /// No value was completed; feed more input.
+    NeedMoreData,
+    /// The root values completed by the chunk, in order.
+    Values(Vec<JsonNode>),
+}
+
+/// The token that the bytes fed last have started but not finished.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pending {
+    None,
+    String {
+        quote: u8,
+        escaped: bool,
+        /// Hex digits of a `\u` escape still to come.
+        hex_digits: u8,
+        /// At most the number of bytes the string decodes to so far, for stopping an
+        /// overlong string early; the exact length is checked once it is complete.
+        length: usize,
+    },
+    Number,
+    Literal,
+    /// A non-ASCII character outside a string, which is an error once complete.
+    Char {
+        width: usize,
+    },
+    /// A `/` that starts a comment if `/` or `*` follows.
+    Slash,
+    /// A `//` comment, which the next line break ends.
+    LineComment,
+    /// A `/* */` comment; `star` is whether its last byte was `*`.
+    BlockComment {
+        star: bool,
+    },
+}
+
+/// Parses JSON from chunks of bytes as they arrive, for input that cannot be read
+/// with a blocking `io::Read`. State is kept between calls, so no consumed input is
+/// rescanned however the input is split, and a token split across chunks is finished
+/// by the next one. Any number of root values may follow each other.
+///
+/// `with_options` applies `ParseOptions` as `JsonParser` does. Comments are skipped
+/// as they arrive, holding back at most the bytes of one character.
+///
+/// ```ignore
+/// let mut parser = JsonPushParser::new();
+/// while let Some(chunk) = socket.next_chunk() {
+///     if let PushStatus::Values(values) = parser.feed(&chunk)? {
+///         values.into_iter().for_each(handle);
+///     }
+/// }
+/// parser.finish()?.map(handle);
+/// ```
+pub struct JsonPushParser {
+    grammar: JsonGrammar,
+    builder: TreeBuilder,
+    pending: Pending,
+    /// The bytes of the pending token.
+    buffer: Vec<u8>,
+    /// Where the pending token starts, or the next byte if there is none.
+    position: Position,
+    error: Option<ParseError>,
+}
+
+impl JsonPushParser {
+    pub fn new() -> JsonPushParser {
+        JsonPushParser {
+            grammar: JsonGrammar::new(),
+            builder: TreeBuilder::default(),
+            pending: Pending::None,
+            buffer: Vec::new(),
+            position: Position::new(),
+            error: None,
+        }
+    }
+
+    /// The position just past the bytes fed so far.
+    pub fn position(&self) -> Position {
+        let mut position = advanced(self.position, &String::from_utf8_lossy(&self.buffer));
+        position.offset = self.position.offset + self.buffer.len();
+        position
+    }
+
+    pub fn with_options(mut self, options: ParseOptions) -> JsonPushParser {
+        self.grammar = self.grammar.with_options(options);
+        self.builder = TreeBuilder::default().with_duplicate_keys(options.duplicate_keys);
+        self
+    }
+
+    /// Parses `chunk`. After an error the parser stays failed and returns the same
+    /// error from every later call; values completed earlier in the failing chunk are
+    /// dropped.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<PushStatus, ParseError> {
+        if let Some(err) = &self.error {
+            return Err(err.clone());
+        }
+        let mut values = Vec::new();
+        if let Err(err) = self.feed_bytes(chunk, &mut values) {
+            self.error = Some(err.clone());
+            return Err(err);
+        }
+        Ok(if values.is_empty() {
+            PushStatus::NeedMoreData
+        } else {
+            PushStatus::Values(values)
+        })
+    }
+
+    /// Ends the input, completing a number or literal at the end of it. Returns the
+    /// root value that completes, if any; a value that is still open is an error.
+    pub fn finish(mut self) -> Result<Option<JsonNode>, ParseError> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        let mut values = Vec::new();
+        match self.pending {
+            Pending::Number | Pending::Literal => self.complete_token(&mut values)?,
+            Pending::String { .. } | Pending::Char { .. } | Pending::BlockComment { .. } => {
+                return Err(ParseError::new(
+                    ParseErrorKind::UnexpectedEnd,
+                    self.position(),
+                ))
+            }
+            Pending::Slash => {
+                return Err(ParseError::new(
+                    ParseErrorKind::UnexpectedCharacter('/'),
+                    self.position,
+                ))
+            }
+            Pending::LineComment if !self.buffer.is_empty() => {
+                return Err(ParseError::new(ParseErrorKind::InvalidUtf8, self.position))
+            }
+            Pending::LineComment | Pending::None => {}
+        }
+        if !self.grammar.is_done() && !self.grammar.is_at_root() {
+            return Err(ParseError::new(
+                ParseErrorKind::UnexpectedEnd,
+                self.position,
+            ));
+        }
+        Ok(values.pop())
+    }
+
+    fn feed_bytes(&mut self, chunk: &[u8], values: &mut Vec<JsonNode>) -> Result<(), ParseError> {
+        let options = *self.grammar.options();
+        for &byte in chunk {
+            if self.position.offset + self.buffer.len() >= options.max_input_bytes {
+                return Err(ParseError::new(
+                    ParseErrorKind::InputTooLarge(options.max_input_bytes),
+                    self.position(),
+                ));
+            }
+            match self.pending {
+                Pending::None => {}
+                Pending::Number if byte.is_ascii() && is_number_char(byte as char) => {
+                    if self.buffer.len() == options.max_number_length {
+                        return Err(ParseError::new(
+                            ParseErrorKind::NumberTooLong(options.max_number_length),
+                            self.position,
+                        ));
+                    }
+                    self.buffer.push(byte);
+                    continue;
+                }
+                Pending::Literal if byte.is_ascii() && is_keyword_char(byte as char) => {
+                    self.buffer.push(byte);
+                    continue;
+                }
+                // The byte ends the number or literal and is handled on its own below.
+                Pending::Number | Pending::Literal => self.complete_token(values)?,
+                Pending::String {
+                    quote,
+                    escaped,
+                    hex_digits,
+                    length,
+                } => {
+                    self.buffer.push(byte);
+                    let (escaped, hex_digits, length) = match byte {
+                        _ if hex_digits > 0 && byte.is_ascii_hexdigit() => {
+                            (false, hex_digits - 1, length)
+                        }
+                        b'u' if escaped => (false, 4, length + 1),
+                        // A line continuation decodes to nothing.
+                        b'\n' | b'\r' | 0x80.. if escaped => (false, 0, length),
+                        _ if escaped => (false, 0, length + 1),
+                        _ if byte == quote => {
+                            self.complete_token(values)?;
+                            continue;
+                        }
+                        b'\\' => (true, 0, length),
+                        // One per character rather than per byte, and none for the `\n`
+                        // of an escaped `\r\n`.
+                        0x80..=0xbf | b'\n' | b'\r' => (false, 0, length),
+                        _ => (false, 0, length + 1),
+                    };
+                    if length > options.max_string_length {
+                        return Err(ParseError::new(
+                            ParseErrorKind::StringTooLong(options.max_string_length),
+                            self.position,
+                        ));
+                    }
+                    self.pending = Pending::String {
+                        quote,
+                        escaped,
+                        hex_digits,
+                        length,
+                    };
+                    continue;
+                }
+                Pending::Char { width } => {
+                    self.buffer.push(byte);
+                    if self.buffer.len() == width {
+                        self.complete_token(values)?;
+                    }
+                    continue;
+                }
+                Pending::Slash => {
+                    self.pending = match byte {
+                        b'/' => Pending::LineComment,
+                        b'*' => Pending::BlockComment { star: false },
+                        _ => {
+                            return Err(ParseError::new(
+                                ParseErrorKind::UnexpectedCharacter('/'),
+                                self.position,
+                            ))
+                        }
+                    };
+                    self.buffer.clear();
+                    self.position.advance('/');
+                    self.position.advance(byte as char);
+                    continue;
+                }
+                // The line break is whitespace and is handled on its own below.
+                Pending::LineComment if byte == b'\n' && self.buffer.is_empty() => {
+                    self.pending = Pending::None;
+                }
+                Pending::LineComment => {
+                    self.skip_comment_byte(byte)?;
+                    continue;
+                }
+                Pending::BlockComment { star } => {
+                    self.skip_comment_byte(byte)?;
+                    self.pending = if star && byte == b'/' {
+                        Pending::None
+                    } else {
+                        Pending::BlockComment { star: byte == b'*' }
+                    };
+                    continue;
+                }
+            }
+            self.start_token(byte, values)?;
+        }
+        Ok(())
+    }
+
+    fn start_token(&mut self, byte: u8, values: &mut Vec<JsonNode>) -> Result<(), ParseError> {
+        let options = *self.grammar.options();
+        let pending = match byte {
+            b' ' | b'\t' | b'\n' | b'\r' => {
+                self.position.advance(byte as char);
+                return Ok(());
+            }
+            b'"' => Pending::String {
+                quote: byte,
+                escaped: false,
+                hex_digits: 0,
+                length: 0,
+            },
+            b'\'' if options.single_quoted_strings => Pending::String {
+                quote: byte,
+                escaped: false,
+                hex_digits: 0,
+                length: 0,
+            },
+            b'-' | b'0'..=b'9' if options.max_number_length == 0 => {
+                return Err(ParseError::new(
+                    ParseErrorKind::NumberTooLong(0),
+                    self.position,
+                ))
+            }
+            b'-' | b'0'..=b'9' => Pending::Number,
+            b'a'..=b'z' | b'A'..=b'Z' => Pending::Literal,
+            b'_' | b'$' if options.unquoted_keys => Pending::Literal,
+            b'/' if options.comments => Pending::Slash,
+            b',' | b':' | b'{' | b'}' | b'[' | b']' => {
+                let token = match byte {
+                    b',' => JsonToken::Comma,
+                    b':' => JsonToken::Colon,
+                    b'{' => JsonToken::LeftBrace,
+                    b'}' => JsonToken::RightBrace,
+                    b'[' => JsonToken::LeftBracket,
+                    _ => JsonToken::RightBracket,
+                };
+                let position = self.position;
+                self.position.advance(byte as char);
+                return self.accept(token, position, values);
+            }
+            _ if byte.is_ascii() => {
+                return Err(ParseError::new(
+                    ParseErrorKind::UnexpectedCharacter(byte as char),
+                    self.position,
+                ))
+            }
+            _ => match utf8_width(byte) {
+                Some(width) => Pending::Char { width },
+                None => return Err(ParseError::new(ParseErrorKind::InvalidUtf8, self.position)),
+            },
+        };
+        self.pending = pending;
+        self.buffer.push(byte);
+        Ok(())
+    }
+
+    /// Consumes a byte of a comment. The bytes of a multi-byte character are buffered
+    /// until it is complete, so that it is checked and counted as one column.
+    fn skip_comment_byte(&mut self, byte: u8) -> Result<(), ParseError> {
+        let invalid = match self.buffer.first() {
+            Some(_) => !(0x80..=0xbf).contains(&byte),
+            None => utf8_width(byte).is_none(),
+        };
+        if invalid {
+            return Err(ParseError::new(ParseErrorKind::InvalidUtf8, self.position));
+        }
+        self.buffer.push(byte);
+        if Some(self.buffer.len()) == utf8_width(self.buffer[0]) {
+            let text = std::str::from_utf8(&self.buffer)
+                .map_err(|_| ParseError::new(ParseErrorKind::InvalidUtf8, self.position))?;
+            self.position = advanced(self.position, text);
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+
+    /// Turns the buffered bytes into a token, which is decoded here once it is known to
+    /// be complete.
+    fn complete_token(&mut self, values: &mut Vec<JsonNode>) -> Result<(), ParseError> {
+        let start = self.position;
+        let options = *self.grammar.options();
+        let pending = std::mem::replace(&mut self.pending, Pending::None);
+        let text = String::from_utf8(std::mem::take(&mut self.buffer)).map_err(|err| {
+            let valid = &err.as_bytes()[..err.utf8_error().valid_up_to()];
+            let position = advanced(start, std::str::from_utf8(valid).unwrap_or_default());
+            ParseError::new(ParseErrorKind::InvalidUtf8, position)
+        })?;
+        let token = match pending {
+            Pending::Number => number_token(text.clone(), &options),
+            // Only the grammar knows whether this is a key or a value.
+            Pending::Literal if options.unquoted_keys => Ok(JsonToken::Identifier(text.clone())),
+            Pending::Literal => keyword_token(text.clone(), &options),
+            Pending::Char { .. } => Err(ParseErrorKind::UnexpectedCharacter(
+                text.chars().next().unwrap_or('\u{fffd}'),
+            )),
+            Pending::Slash | Pending::LineComment | Pending::BlockComment { .. } => {
+                unreachable!("comments are skipped rather than completed")
+            }
+            Pending::String { .. } | Pending::None => {
+                let mut lexer = JsonLexer::new(&text).with_options(options);
+                match lexer.next_token() {
+                    Ok(Some((token, _))) => Ok(token),
+                    Ok(None) => unreachable!("a complete string is one token"),
+                    Err(err) => {
+                        let position = advanced(start, &text[..err.position().offset]);
+                        return Err(ParseError::new(err.kind().clone(), position));
+                    }
+                }
+            }
+        }
+        .map_err(|kind| ParseError::new(kind, start))?;
+        self.position = advanced(start, &text);
+        self.accept(token, start, values)
+    }
+
+    fn accept(
+        &mut self,
+        token: JsonToken,
+        position: Position,
+        values: &mut Vec<JsonNode>,
+    ) -> Result<(), ParseError> {
+        if self.grammar.is_done() {
+            self.grammar.reset();
+        }
+        if let Some(event) = self.grammar.accept(token, position)? {
+            values.extend(self.builder.push(event));
+        }
+        Ok(())
+    }
+}
+
+impl Default for JsonPushParser {
+    fn default() -> JsonPushParser {
+        JsonPushParser::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    /// Feeds `text` one byte at a time, collecting every value including the one that
+    /// `finish` completes.
+    fn push(options: ParseOptions, text: &str) -> Result<Vec<JsonNode>, ParseError> {
+        let mut parser = JsonPushParser::new().with_options(options);
+        let mut values = Vec::new();
+        for byte in text.as_bytes().chunks(1) {
+            if let PushStatus::Values(done) = parser.feed(byte)? {
+                values.extend(done);
+            }
+        }
+        values.extend(parser.finish()?);
+        Ok(values)
+    }
+
+    #[test]
+    fn values_split_at_any_point() {
+        let text = "{\"name\": \"\u{e9}\u{4e16}\\ud83d\\ude00\\\"\", \"list\": [1.5e3, true, null]}\n[] 42 \"\u{1f600}\" -7";
+        let expected = vec![
+            json!({"name": "\u{e9}\u{4e16}\u{1f600}\"", "list": [1500, true, null]}),
+            json!([]),
+            json!(42),
+            json!("\u{1f600}"),
+        ];
+
+        for chunk in [1, 2, 3, 5, text.len()] {
+            let mut parser = JsonPushParser::new();
+            let mut values = Vec::new();
+            for bytes in text.as_bytes().chunks(chunk) {
+                if let PushStatus::Values(done) = parser.feed(bytes).unwrap() {
+                    values.extend(done);
+                }
+            }
+            assert_eq!(values, expected);
+            assert_eq!(parser.position().offset, text.len());
+            // The trailing number could still go on until the input ends.
+            assert_eq!(parser.finish().unwrap(), Some(json!(-7)));
+        }
+    }
+
+    #[test]
+    fn values_complete_with_the_chunk_that_closes_them() {
+        let mut parser = JsonPushParser::new();
+        assert_eq!(parser.feed(b"[1, \"a").unwrap(), PushStatus::NeedMoreData);
+        assert_eq!(parser.feed(b"b\"").unwrap(), PushStatus::NeedMoreData);
+        assert_eq!(
+            parser.feed(b"]").unwrap(),
+            PushStatus::Values(vec![json!([1, "ab"])])
+        );
+        assert_eq!(parser.finish().unwrap(), None);
+    }
+
+    #[test]
+    fn errors_are_sticky() {
+        let mut parser = JsonPushParser::new();
+        parser.feed(b"{\"a\": [1,\n").unwrap();
+        let err = parser.feed(b"  ,]}").unwrap_err();
+        assert_eq!((err.position().line, err.position().column), (2, 3));
+        assert_eq!(parser.feed(b"2]}").unwrap_err(), err);
+    }
+
+    #[test]
+    fn invalid_utf8_is_reported_where_it_starts() {
+        let mut parser = JsonPushParser::new();
+        parser.feed(b"[\"\xe4\xb8").unwrap();
+        let err = parser.feed(b"\"]").unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::InvalidUtf8);
+        assert_eq!(err.position().offset, 2);
+    }
+
+    #[test]
+    fn finish_rejects_open_values() {
+        let mut parser = JsonPushParser::new();
+        parser.feed(b"{\"open\": true").unwrap();
+        let err = parser.finish().unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnexpectedEnd);
+        assert_eq!(err.position().offset, 13);
+    }
+
+    #[test]
+    fn json5_extensions() {
+        assert_eq!(
+            push(
+                ParseOptions::json5(),
+                "{key: 'it\\'s', $id: 0x1F, low: -Infinity, text: 'a\\\r\nb', list: [1,],}"
+            )
+            .unwrap(),
+            vec![json!({
+                "key": "it's",
+                "$id": 31,
+                "low": f64::NEG_INFINITY,
+                "text": "ab",
+                "list": [1],
+            })]
+        );
+    }
+
+    #[test]
+    fn comments_are_skipped() {
+        let text =
+            "// caf\u{e9}\n{\"a\": /* \u{1f600} ** */ 1, // x\n\"b\": [2 /**/]}/* end */ 3 // last";
+        let expected = vec![json!({"a": 1, "b": [2]}), json!(3)];
+        assert_eq!(push(ParseOptions::jsonc(), text).unwrap(), expected);
+
+        for chunk in [2, 3, 7] {
+            let mut parser = JsonPushParser::new().with_options(ParseOptions::jsonc());
+            let mut values = Vec::new();
+            for bytes in text.as_bytes().chunks(chunk) {
+                if let PushStatus::Values(done) = parser.feed(bytes).unwrap() {
+                    values.extend(done);
+                }
+            }
+            assert_eq!(parser.position().offset, text.len());
+            values.extend(parser.finish().unwrap());
+            assert_eq!(values, expected);
+        }
+    }
+
+    #[test]
+    fn positions_after_comments_match_the_lexer() {
+        let text = "/* \u{e9}\u{e9} */ [1\n// \u{4e16}\n /* x */ 2]";
+        let pushed = push(ParseOptions::jsonc(), text).unwrap_err();
+        let lexed = parse_json_with_options(text, ParseOptions::jsonc()).unwrap_err();
+        assert_eq!(pushed, lexed);
+        assert_eq!((pushed.position().line, pushed.position().column), (3, 10));
+    }
+
+    #[test]
+    fn invalid_comments() {
+        let jsonc = ParseOptions::jsonc();
+        for (text, kind) in [
+            ("[1 /x]", ParseErrorKind::UnexpectedCharacter('/')),
+            ("1 /", ParseErrorKind::UnexpectedCharacter('/')),
+            ("1 /* open *", ParseErrorKind::UnexpectedEnd),
+        ] {
+            let err = push(jsonc, text).unwrap_err();
+            assert_eq!(err.kind(), &kind, "{:?}", text);
+        }
+        assert_eq!(
+            push(ParseOptions::new(), "// note\n1").unwrap_err().kind(),
+            &ParseErrorKind::UnexpectedCharacter('/')
+        );
+
+        let mut parser = JsonPushParser::new().with_options(jsonc);
+        let err = parser.feed(b"1 // \xe9 \n").unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::InvalidUtf8);
+        assert_eq!(err.position().offset, 5);
+        let mut parser = JsonPushParser::new().with_options(jsonc);
+        parser.feed(b"1 // \xc3").unwrap();
+        assert_eq!(
+            parser.finish().unwrap_err().kind(),
+            &ParseErrorKind::InvalidUtf8
+        );
+    }
+
+    #[test]
+    fn grammar_options() {
+        assert!(matches!(
+            push(
+                ParseOptions::new().with_duplicate_keys(DuplicateKeyPolicy::Error),
+                r#"{"a": 1, "a": 2}"#
+            )
+            .unwrap_err()
+            .kind(),
+            ParseErrorKind::DuplicateKey { .. }
+        ));
+        assert_eq!(
+            push(ParseOptions::new().with_max_depth(2), "[[[1]]]")
+                .unwrap_err()
+                .kind(),
+            &ParseErrorKind::DepthLimitExceeded(2)
+        );
+    }
+
+    #[test]
+    fn overlong_tokens_fail_before_they_end() {
+        let mut parser =
+            JsonPushParser::new().with_options(ParseOptions::new().with_max_string_length(3));
+        let err = parser.feed(b"[\"abcd").unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::StringTooLong(3));
+        assert_eq!(err.position().offset, 1);
+        let mut parser =
+            JsonPushParser::new().with_options(ParseOptions::new().with_max_number_length(3));
+        let err = parser.feed(b"[1234").unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::NumberTooLong(3));
+        assert_eq!(err.position().offset, 1);
+    }
+
+    #[test]
+    fn length_limits() {
+        let strings = ParseOptions::new().with_max_string_length(2);
+        assert_eq!(push(strings, r#""A\n""#).unwrap(), vec![json!("A\n")]);
+        assert_eq!(
+            push(strings, r#""éé""#).unwrap_err().kind(),
+            &ParseErrorKind::StringTooLong(2)
+        );
+        assert_eq!(
+            push(ParseOptions::new().with_max_number_length(3), "[123]").unwrap(),
+            vec![json!([123])]
+        );
+        let err = push(ParseOptions::new().with_max_input_bytes(4), "[1, 2]").unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::InputTooLarge(4));
+        assert_eq!(err.position().offset, 4);
+    }
+}
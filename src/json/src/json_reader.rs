@@ -0,0 +1,311 @@
+use crate::json_basic::JsonNode;
+use crate::json_error::{ParseError, Position};
+use crate::json_lexer::{BufReadSource, JsonLexer, ReadSource};
+use crate::json_options::ParseOptions;
+use crate::json_parser::{JsonParser, ParserEvent};
+use std::io::{BufRead, Read};
+
+/// A scalar value read by `JsonEventReader`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JsonScalar<'a> {
+    String(&'a str),
+    Number(f64),
+    Boolean(bool),
+    Null,
+}
+
+impl JsonScalar<'_> {
+    pub fn to_json_node(&self) -> JsonNode {
+        match *self {
+            JsonScalar::String(s) => JsonNode::String(s.to_string()),
+            JsonScalar::Number(n) => JsonNode::Number(n),
+            JsonScalar::Boolean(b) => JsonNode::Boolean(b),
+            JsonScalar::Null => JsonNode::Null,
+        }
+    }
+}
+
+/// One step through a document read by `JsonEventReader`. Strings borrow from the
+/// reader and are valid until the next call.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JsonEvent<'a> {
+    StartObject,
+    Key(&'a str),
+    StartArray,
+    Value(JsonScalar<'a>),
+    EndArray,
+    EndObject,
+}
+
+/// Reads a JSON document from an `io::Read` as a sequence of events, without
+/// building a `JsonNode` tree. Memory use is bounded by the nesting depth and the
+/// longest string, not by the size of the document.
+///
+/// ```ignore
+/// let mut reader = JsonEventReader::new(file);
+/// while let Some(event) = reader.next_event()? {
+///     if event == JsonEvent::Key("payload") {
+///         reader.skip_value()?;
+///     }
+/// }
+/// ```
+pub struct JsonEventReader<R> {
+    parser: JsonParser<ReadSource<R>>,
+    current: Option<ParserEvent>,
+    position: Position,
+}
+
+impl<R: Read> JsonEventReader<R> {
+    pub fn new(reader: R) -> JsonEventReader<R> {
+        JsonEventReader {
+            parser: JsonParser::new(JsonLexer::from_source(ReadSource::new(reader))),
+            current: None,
+            position: Position::new(),
+        }
+    }
+
+    pub fn with_options(mut self, options: ParseOptions) -> JsonEventReader<R> {
+        self.parser = self.parser.with_options(options);
+        self
+    }
+
+    /// The next event, or `None` once the root value has been read.
+    pub fn next_event(&mut self) -> Result<Option<JsonEvent<'_>>, ParseError> {
+        self.current = match self.parser.next_event()? {
+            Some((event, position)) => {
+                self.position = position;
+                Some(event)
+            }
+            None => None,
+        };
+        Ok(self.current.as_ref().map(|event| match event {
+            ParserEvent::StartObject => JsonEvent::StartObject,
+            ParserEvent::Key(key) => JsonEvent::Key(key),
+            ParserEvent::StartArray => JsonEvent::StartArray,
+            ParserEvent::Value(JsonNode::String(s)) => JsonEvent::Value(JsonScalar::String(s)),
+            ParserEvent::Value(JsonNode::Number(n)) => JsonEvent::Value(JsonScalar::Number(*n)),
+            ParserEvent::Value(JsonNode::Boolean(b)) => JsonEvent::Value(JsonScalar::Boolean(*b)),
+            ParserEvent::Value(_) => JsonEvent::Value(JsonScalar::Null),
+            ParserEvent::EndArray => JsonEvent::EndArray,
+            ParserEvent::EndObject => JsonEvent::EndObject,
+        }))
+    }
+
+    /// Where the token of the last event starts.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Skips the next value without keeping its strings: after a `Key` event, the
+    /// member's value; in an array, the next element; at the root, the whole document.
+    /// Where a key is expected the whole member is skipped, and if the enclosing
+    /// container ends next nothing is consumed.
+    pub fn skip_value(&mut self) -> Result<(), ParseError> {
+        self.current = None;
+        self.parser.skip_value()
+    }
+
+    /// Reads the next value into a `JsonNode`, for the parts of a document that are
+    /// small enough to keep.
+    pub fn read_value(&mut self) -> Result<JsonNode, ParseError> {
+        self.current = None;
+        self.parser.parse_value()
+    }
+}
+
+/// Parses one JSON document from `reader` through a fixed-size buffer, so the input
+/// never has to be held in memory as a whole. Anything but whitespace after the
+/// value is an error.
+pub fn parse_reader<R: Read>(reader: R) -> Result<JsonNode, ParseError> {
+    parse_reader_with_options(reader, ParseOptions::new())
+}
+
+pub fn parse_reader_with_options<R: Read>(
+    reader: R,
+    options: ParseOptions,
+) -> Result<JsonNode, ParseError> {
+    JsonParser::new(JsonLexer::from_source(ReadSource::new(reader)).with_options(options))
+        .parse_document()
+}
+
+/// Like `parse_reader`, but decodes straight from the reader's own buffer instead of
+/// copying into another one.
+pub fn parse_buf_reader<R: BufRead>(reader: R) -> Result<JsonNode, ParseError> {
+    parse_buf_reader_with_options(reader, ParseOptions::new())
+}
+
+pub fn parse_buf_reader_with_options<R: BufRead>(
+    reader: R,
+    options: ParseOptions,
+) -> Result<JsonNode, ParseError> {
+    JsonParser::new(JsonLexer::from_source(BufReadSource::new(reader)).with_options(options))
+        .parse_document()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    /// Hands out at most `chunk` bytes per `read`, to split tokens and UTF-8
+    /// sequences across reads.
+    struct ChunkedReader<'a> {
+        data: &'a [u8],
+        chunk: usize,
+    }
+
+    impl std::io::Read for ChunkedReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.chunk.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn event_reader_reads_split_input() {
+        let text = "{\"name\": \"caf\u{e9} \\ud83d\\ude00\",\n \"skip\": {\"a\": [1, {\"b\": null}]},\n \"list\": [true, -1.5e2, \"\u{1f600}\"]}";
+        let mut reader = JsonEventReader::new(ChunkedReader {
+            data: text.as_bytes(),
+            chunk: 1,
+        });
+
+        assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::StartObject));
+        assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::Key("name")));
+        assert_eq!(
+            reader.next_event().unwrap(),
+            Some(JsonEvent::Value(JsonScalar::String("caf\u{e9} \u{1f600}")))
+        );
+        assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::Key("skip")));
+        assert_eq!(
+            reader.position(),
+            Position {
+                offset: 32,
+                line: 2,
+                column: 2
+            }
+        );
+        reader.skip_value().unwrap();
+        assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::Key("list")));
+        assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::StartArray));
+        assert_eq!(
+            reader.next_event().unwrap(),
+            Some(JsonEvent::Value(JsonScalar::Boolean(true)))
+        );
+        assert_eq!(
+            reader.next_event().unwrap(),
+            Some(JsonEvent::Value(JsonScalar::Number(-150.0)))
+        );
+        assert_eq!(reader.read_value().unwrap(), "\u{1f600}".to_json());
+        assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::EndArray));
+        assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::EndObject));
+        assert_eq!(reader.next_event().unwrap(), None);
+    }
+
+    #[test]
+    fn skip_value_skips_members() {
+        let mut reader = JsonEventReader::new(&br#"{"a": [1, 2], "b": 3}"#[..]);
+        reader.next_event().unwrap();
+        reader.skip_value().unwrap();
+        assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::Key("b")));
+        reader.skip_value().unwrap();
+        reader.skip_value().unwrap();
+        assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::EndObject));
+    }
+
+    #[test]
+    fn event_reader_reports_error_positions() {
+        let mut reader = JsonEventReader::new(&b"[1,\n  2 3]"[..]);
+        let err = loop {
+            match reader.next_event() {
+                Ok(_) => continue,
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(
+            err.kind(),
+            &ParseErrorKind::UnexpectedToken {
+                found: "number 3".to_string(),
+                expected: "',' or ']'"
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Expected ',' or ']', found number 3 at line 2, column 5"
+        );
+    }
+
+    #[test]
+    fn event_reader_rejects_invalid_utf8() {
+        let mut reader = JsonEventReader::new(&b"[\"ab\xff\"]"[..]);
+        reader.next_event().unwrap();
+        let err = reader.next_event().unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::InvalidUtf8);
+        assert_eq!(err.position().offset, 4);
+    }
+
+    /// A document larger than the readers' buffers, with multi-byte characters.
+    fn large_document() -> String {
+        let words: Vec<String> = (0..3000)
+            .map(|i| format!("\u{e9}\u{4e16}{}\u{1f600}", i))
+            .collect();
+        let text = json!({"words": words, "nested": {"ok": true}}).dump(2, false);
+        assert!(text.len() > 16 * 1024);
+        text
+    }
+
+    #[test]
+    fn parse_reader_reads_chunked_input() {
+        let text = large_document();
+        let expected = parse_json(&text).unwrap();
+        for chunk in [1, 3, 4096] {
+            let reader = ChunkedReader {
+                data: text.as_bytes(),
+                chunk,
+            };
+            assert_eq!(parse_reader(reader).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn parse_buf_reader_reads_with_any_capacity() {
+        let text = large_document();
+        let expected = parse_json(&text).unwrap();
+        for capacity in [1, 2, 5, 8192] {
+            let reader = std::io::BufReader::with_capacity(capacity, text.as_bytes());
+            assert_eq!(parse_buf_reader(reader).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn parse_reader_reports_invalid_utf8() {
+        let invalid = b"{\"a\": \"\xe4\xb8\"}";
+        let err = parse_reader(&invalid[..]).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::InvalidUtf8);
+        assert_eq!((err.position().offset, err.position().column), (7, 8));
+        let reader = std::io::BufReader::with_capacity(1, &invalid[..]);
+        assert_eq!(parse_buf_reader(reader).unwrap_err().position().offset, 7);
+    }
+
+    #[test]
+    fn parse_reader_rejects_trailing_characters() {
+        let err = parse_reader(&b"[1, 2]\n  x"[..]).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::TrailingCharacters);
+        assert_eq!(err.position().line, 2);
+        assert_eq!(parse_reader(&b" [1, 2] \n"[..]).unwrap(), json!([1, 2]));
+    }
+
+    #[test]
+    fn parse_reader_reports_io_errors() {
+        struct FailingReader;
+        impl std::io::Read for FailingReader {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("connection reset"))
+            }
+        }
+        assert_eq!(
+            parse_reader(FailingReader).unwrap_err().kind(),
+            &ParseErrorKind::Io("connection reset".to_string())
+        );
+    }
+}
@@ -0,0 +1,456 @@
+use crate::json_basic::JsonNode;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, LinkedList, VecDeque};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::num::{
+    NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
+    NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize, Wrapping,
+};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Types that can describe the JSON they convert to and from as a JSON Schema
+/// (Draft 2020-12).
+///
+/// Derived types are stored once in `defs` and referred to with `$ref`, which also
+/// makes recursive types work. Use `schema_for` to get a complete schema document.
+pub trait JsonSchema {
+    fn json_schema(defs: &mut SchemaDefinitions) -> JsonNode;
+}
+
+/// The `$defs` of a schema document being generated.
+#[derive(Debug, Default)]
+pub struct SchemaDefinitions {
+    defs: HashMap<String, JsonNode>,
+    /// The key of each type's definition, by `std::any::type_name`.
+    keys: HashMap<&'static str, String>,
+}
+
+impl SchemaDefinitions {
+    pub fn new() -> SchemaDefinitions {
+        SchemaDefinitions::default()
+    }
+
+    /// Returns a `$ref` to the definition of `T`, building it with `build` the first
+    /// time it is referred to. The definition is stored under `name` unless another
+    /// type already has that name, in which case it is stored under the full path of
+    /// `T`, such as `app.config.Config`.
+    pub fn reference<T: ?Sized>(
+        &mut self,
+        name: &str,
+        build: impl FnOnce(&mut SchemaDefinitions) -> JsonNode,
+    ) -> JsonNode {
+        let type_name = std::any::type_name::<T>();
+        let key = match self.keys.get(type_name) {
+            Some(key) => key.clone(),
+            None => {
+                let key = if self.defs.contains_key(name) {
+                    type_name.replace("::", ".")
+                } else {
+                    name.to_string()
+                };
+                self.keys.insert(type_name, key.clone());
+                // Placeholder so that recursive references stop here.
+                self.defs.insert(key.clone(), JsonNode::Boolean(true));
+                let schema = build(self);
+                self.defs.insert(key.clone(), schema);
+                key
+            }
+        };
+        schema_object(vec![("$ref", JsonNode::String(format!("#/$defs/{}", key)))])
+    }
+
+    pub fn get(&self, name: &str) -> Option<&JsonNode> {
+        self.defs.get(name)
+    }
+
+    pub fn into_json(self) -> JsonNode {
+        JsonNode::Object(self.defs)
+    }
+}
+
+/// The complete schema document for `T`, with `$schema` and `$defs`.
+pub fn schema_for<T: JsonSchema + ?Sized>() -> JsonNode {
+    let mut defs = SchemaDefinitions::new();
+    let mut schema = match T::json_schema(&mut defs) {
+        JsonNode::Boolean(true) => JsonNode::Object(HashMap::new()),
+        JsonNode::Boolean(false) => schema_object(vec![("not", JsonNode::Object(HashMap::new()))]),
+        schema => schema,
+    };
+    schema["$schema"] = JsonNode::String(SCHEMA_DIALECT.to_string());
+    if !defs.defs.is_empty() {
+        schema["$defs"] = defs.into_json();
+    }
+    schema
+}
+
+fn schema_object(members: Vec<(&str, JsonNode)>) -> JsonNode {
+    JsonNode::Object(
+        members
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
+
+fn schema_type(name: &str) -> JsonNode {
+    schema_object(vec![("type", JsonNode::String(name.to_string()))])
+}
+
+fn string_list<'a>(values: impl IntoIterator<Item = &'a str>) -> JsonNode {
+    JsonNode::Array(
+        values
+            .into_iter()
+            .map(|value| JsonNode::String(value.to_string()))
+            .collect(),
+    )
+}
+
+/// Schema of an object with exactly the given properties, all of them required.
+pub fn schema_for_object(properties: Vec<(&str, JsonNode)>, deny_unknown_fields: bool) -> JsonNode {
+    let required = string_list(properties.iter().map(|(key, _)| *key));
+    let mut schema = schema_object(vec![
+        ("type", JsonNode::String("object".to_string())),
+        ("properties", schema_object(properties)),
+        ("required", required),
+    ]);
+    if deny_unknown_fields {
+        schema["additionalProperties"] = JsonNode::Boolean(false);
+    }
+    schema
+}
+
+/// Schema of an array holding exactly `items`, in order.
+pub fn schema_for_tuple(items: Vec<JsonNode>) -> JsonNode {
+    let len = JsonNode::Number(items.len() as f64);
+    schema_object(vec![
+        ("type", JsonNode::String("array".to_string())),
+        ("prefixItems", JsonNode::Array(items)),
+        ("items", JsonNode::Boolean(false)),
+        ("minItems", len.clone()),
+        ("maxItems", len),
+    ])
+}
+
+/// Schema of an enum variant under the `{"type": ..., "value": ...}` encoding.
+pub fn schema_for_variant(
+    name: &str,
+    value: Option<JsonNode>,
+    deny_unknown_fields: bool,
+) -> JsonNode {
+    let mut properties = vec![(
+        "type",
+        schema_object(vec![("const", JsonNode::String(name.to_string()))]),
+    )];
+    if let Some(value) = value {
+        properties.push(("value", value));
+    }
+    schema_for_object(properties, deny_unknown_fields)
+}
+
+/// Schema matching exactly one of `schemas`.
+pub fn schema_one_of(schemas: Vec<JsonNode>) -> JsonNode {
+    schema_object(vec![("oneOf", JsonNode::Array(schemas))])
+}
+
+/// Adds an annotation such as `title` or `description` to a schema.
+pub fn schema_annotate(schema: JsonNode, keyword: &str, text: &str) -> JsonNode {
+    let mut schema = match schema {
+        JsonNode::Boolean(true) => JsonNode::Object(HashMap::new()),
+        schema => schema,
+    };
+    if let JsonNode::Object(obj) = &mut schema {
+        obj.insert(keyword.to_string(), JsonNode::String(text.to_string()));
+    }
+    schema
+}
+
+/// Adds `minimum` / `maximum` for `#[json(range(...))]`.
+pub fn schema_range(schema: JsonNode, min: Option<f64>, max: Option<f64>) -> JsonNode {
+    constrain(schema, &mut |obj| {
+        if let Some(min) = min {
+            obj.insert("minimum".to_string(), JsonNode::Number(min));
+        }
+        if let Some(max) = max {
+            obj.insert("maximum".to_string(), JsonNode::Number(max));
+        }
+    })
+}
+
+/// Adds length bounds for `#[json(length(...))]` and `#[json(non_empty)]`, using the
+/// keywords that fit the type of the schema.
+pub fn schema_length(schema: JsonNode, min: Option<usize>, max: Option<usize>) -> JsonNode {
+    constrain(schema, &mut |obj| {
+        let (min_keyword, max_keyword) = match obj.get("type") {
+            Some(JsonNode::String(t)) if t == "array" => ("minItems", "maxItems"),
+            Some(JsonNode::String(t)) if t == "object" => ("minProperties", "maxProperties"),
+            _ => ("minLength", "maxLength"),
+        };
+        if let Some(min) = min {
+            obj.insert(min_keyword.to_string(), JsonNode::Number(min as f64));
+        }
+        if let Some(max) = max {
+            obj.insert(max_keyword.to_string(), JsonNode::Number(max as f64));
+        }
+    })
+}
+
+/// Adds `pattern` for `#[json(regex = "...")]`.
+pub fn schema_pattern(schema: JsonNode, pattern: &str) -> JsonNode {
+    constrain(schema, &mut |obj| {
+        obj.insert("pattern".to_string(), JsonNode::String(pattern.to_string()));
+    })
+}
+
+/// Applies `add` to the schema, or to the non-null alternative of an `Option` schema,
+/// since validators skip `None`.
+fn constrain(schema: JsonNode, add: &mut dyn FnMut(&mut HashMap<String, JsonNode>)) -> JsonNode {
+    let mut schema = match schema {
+        JsonNode::Boolean(true) => JsonNode::Object(HashMap::new()),
+        schema => schema,
+    };
+    if let JsonNode::Object(obj) = &mut schema {
+        match obj.get_mut("anyOf") {
+            Some(JsonNode::Array(alternatives)) if is_option_schema(alternatives) => {
+                let inner = alternatives.remove(0);
+                alternatives.insert(0, constrain(inner, add));
+            }
+            _ => add(obj),
+        }
+    }
+    schema
+}
+
+fn is_option_schema(alternatives: &[JsonNode]) -> bool {
+    alternatives.len() == 2 && alternatives[1] == schema_type("null")
+}
+
+impl JsonSchema for JsonNode {
+    fn json_schema(_defs: &mut SchemaDefinitions) -> JsonNode {
+        JsonNode::Boolean(true)
+    }
+}
+
+impl JsonSchema for () {
+    fn json_schema(_defs: &mut SchemaDefinitions) -> JsonNode {
+        schema_type("null")
+    }
+}
+
+impl JsonSchema for bool {
+    fn json_schema(_defs: &mut SchemaDefinitions) -> JsonNode {
+        schema_type("boolean")
+    }
+}
+
+impl JsonSchema for str {
+    fn json_schema(_defs: &mut SchemaDefinitions) -> JsonNode {
+        schema_type("string")
+    }
+}
+
+impl JsonSchema for String {
+    fn json_schema(_defs: &mut SchemaDefinitions) -> JsonNode {
+        schema_type("string")
+    }
+}
+
+impl JsonSchema for char {
+    fn json_schema(_defs: &mut SchemaDefinitions) -> JsonNode {
+        schema_length(schema_type("string"), Some(1), Some(1))
+    }
+}
+
+macro_rules! impl_json_schema_for_number {
+    ($type_name:expr, $minimum:expr; $($t:ty),*) => {
+        $(
+            impl JsonSchema for $t {
+                fn json_schema(_defs: &mut SchemaDefinitions) -> JsonNode {
+                    schema_range(schema_type($type_name), $minimum, None)
+                }
+            }
+        )*
+    };
+}
+
+impl_json_schema_for_number!("integer", None; i8, i16, i32, i64, i128, isize);
+impl_json_schema_for_number!("integer", Some(0.0); u8, u16, u32, u64, u128, usize);
+impl_json_schema_for_number!("number", None; f32, f64);
+impl_json_schema_for_number!("integer", None; NonZeroI8, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI128, NonZeroIsize);
+impl_json_schema_for_number!("integer", Some(1.0); NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU128, NonZeroUsize);
+
+impl<T: JsonSchema> JsonSchema for Wrapping<T> {
+    fn json_schema(defs: &mut SchemaDefinitions) -> JsonNode {
+        T::json_schema(defs)
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Option<T> {
+    fn json_schema(defs: &mut SchemaDefinitions) -> JsonNode {
+        schema_object(vec![(
+            "anyOf",
+            JsonNode::Array(vec![T::json_schema(defs), schema_type("null")]),
+        )])
+    }
+}
+
+macro_rules! impl_json_schema_for_pointer {
+    ($($pointer:ident),*) => {
+        $(
+            impl<T: JsonSchema + ?Sized> JsonSchema for $pointer<T> {
+                fn json_schema(defs: &mut SchemaDefinitions) -> JsonNode {
+                    T::json_schema(defs)
+                }
+            }
+        )*
+    };
+}
+
+impl_json_schema_for_pointer!(Box, Rc, Arc);
+
+impl<T: JsonSchema + ?Sized> JsonSchema for &T {
+    fn json_schema(defs: &mut SchemaDefinitions) -> JsonNode {
+        T::json_schema(defs)
+    }
+}
+
+impl<T: JsonSchema + ToOwned + ?Sized> JsonSchema for Cow<'_, T> {
+    fn json_schema(defs: &mut SchemaDefinitions) -> JsonNode {
+        T::json_schema(defs)
+    }
+}
+
+fn array_schema(items: JsonNode) -> JsonNode {
+    let mut schema = schema_type("array");
+    schema["items"] = items;
+    schema
+}
+
+macro_rules! impl_json_schema_for_sequence {
+    ($unique:expr; $($sequence:ident),*) => {
+        $(
+            impl<T: JsonSchema> JsonSchema for $sequence<T> {
+                fn json_schema(defs: &mut SchemaDefinitions) -> JsonNode {
+                    let mut schema = array_schema(T::json_schema(defs));
+                    if $unique {
+                        schema["uniqueItems"] = JsonNode::Boolean(true);
+                    }
+                    schema
+                }
+            }
+        )*
+    };
+}
+
+impl_json_schema_for_sequence!(false; Vec, VecDeque, LinkedList);
+impl_json_schema_for_sequence!(true; HashSet, BTreeSet);
+
+impl<T: JsonSchema> JsonSchema for [T] {
+    fn json_schema(defs: &mut SchemaDefinitions) -> JsonNode {
+        array_schema(T::json_schema(defs))
+    }
+}
+
+impl<T: JsonSchema, const N: usize> JsonSchema for [T; N] {
+    fn json_schema(defs: &mut SchemaDefinitions) -> JsonNode {
+        schema_length(array_schema(T::json_schema(defs)), Some(N), Some(N))
+    }
+}
+
+fn map_schema(values: JsonNode) -> JsonNode {
+    let mut schema = schema_type("object");
+    schema["additionalProperties"] = values;
+    schema
+}
+
+impl<K, V: JsonSchema> JsonSchema for HashMap<K, V> {
+    fn json_schema(defs: &mut SchemaDefinitions) -> JsonNode {
+        map_schema(V::json_schema(defs))
+    }
+}
+
+impl<K, V: JsonSchema> JsonSchema for BTreeMap<K, V> {
+    fn json_schema(defs: &mut SchemaDefinitions) -> JsonNode {
+        map_schema(V::json_schema(defs))
+    }
+}
+
+/// Schema of a map written by `map_to_json_pairs`.
+pub fn schema_for_pairs<M, K, V>(defs: &mut SchemaDefinitions) -> JsonNode
+where
+    for<'a> &'a M: IntoIterator<Item = (&'a K, &'a V)>,
+    K: JsonSchema,
+    V: JsonSchema,
+{
+    array_schema(<(K, V)>::json_schema(defs))
+}
+
+macro_rules! impl_json_schema_for_tuple {
+    ($(($($t:ident),+)),*) => {
+        $(
+            impl<$($t: JsonSchema),+> JsonSchema for ($($t,)+) {
+                fn json_schema(defs: &mut SchemaDefinitions) -> JsonNode {
+                    schema_for_tuple(vec![$($t::json_schema(defs)),+])
+                }
+            }
+        )*
+    };
+}
+
+impl_json_schema_for_tuple!(
+    (T0),
+    (T0, T1),
+    (T0, T1, T2),
+    (T0, T1, T2, T3),
+    (T0, T1, T2, T3, T4),
+    (T0, T1, T2, T3, T4, T5),
+    (T0, T1, T2, T3, T4, T5, T6),
+    (T0, T1, T2, T3, T4, T5, T6, T7),
+    (T0, T1, T2, T3, T4, T5, T6, T7, T8),
+    (T0, T1, T2, T3, T4, T5, T6, T7, T8, T9),
+    (T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10),
+    (T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11)
+);
+
+impl JsonSchema for Duration {
+    fn json_schema(defs: &mut SchemaDefinitions) -> JsonNode {
+        schema_for_object(
+            vec![
+                ("secs", u64::json_schema(defs)),
+                ("nanos", u32::json_schema(defs)),
+            ],
+            false,
+        )
+    }
+}
+
+macro_rules! impl_json_schema_for_string {
+    ($($t:ty => $format:expr),*) => {
+        $(
+            impl JsonSchema for $t {
+                fn json_schema(_defs: &mut SchemaDefinitions) -> JsonNode {
+                    let mut schema = schema_type("string");
+                    if let Some(format) = $format {
+                        schema["format"] = JsonNode::String(format.to_string());
+                    }
+                    schema
+                }
+            }
+        )*
+    };
+}
+
+impl_json_schema_for_string!(
+    Path => None::<&str>,
+    PathBuf => None::<&str>,
+    IpAddr => None::<&str>,
+    Ipv4Addr => Some("ipv4"),
+    Ipv6Addr => Some("ipv6"),
+    SocketAddr => None::<&str>,
+    SocketAddrV4 => None::<&str>,
+    SocketAddrV6 => None::<&str>
+);
@@ -0,0 +1,1245 @@
+use crate::json_basic::JsonNode;
+use crate::json_error::JsonError;
+use crate::json_schema::{schema_for, JsonSchema};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+const DRAFT_7_DIALECT: &str = "http://json-schema.org/draft-07/schema";
+
+/// Nesting of schemas applied to the same value before validation gives up, so that
+/// schemas such as `{"$ref": "#"}` cannot recurse forever.
+const MAX_SCHEMA_DEPTH: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Draft {
+    Draft7,
+    Draft202012,
+}
+
+impl Draft {
+    /// The draft named by the `$schema` of `schema`, defaulting to 2020-12.
+    pub fn detect(schema: &JsonNode) -> Draft {
+        match schema.get("$schema") {
+            Some(JsonNode::String(uri))
+                if uri.trim_end_matches('#').replacen("https:", "http:", 1) == DRAFT_7_DIALECT =>
+            {
+                Draft::Draft7
+            }
+            _ => Draft::Draft202012,
+        }
+    }
+}
+
+/// A failed check, located both in the instance and in the schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    instance_pointer: String,
+    schema_pointer: String,
+    message: String,
+}
+
+impl ValidationError {
+    /// JSON pointer of the offending value in the instance.
+    pub fn instance_pointer(&self) -> &str {
+        &self.instance_pointer
+    }
+
+    /// JSON pointer of the keyword that failed in the schema.
+    pub fn schema_pointer(&self) -> &str {
+        &self.schema_pointer
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let instance_pointer = if self.instance_pointer.is_empty() {
+            "/"
+        } else {
+            &self.instance_pointer
+        };
+        write!(
+            f,
+            "{}: {} (schema: #{})",
+            instance_pointer, self.message, self.schema_pointer
+        )
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// A JSON Schema compiled for validating `JsonNode` instances.
+///
+/// Supports the validation vocabulary of Draft 2020-12 and Draft 7, `$ref` to
+/// locations and anchors in the same document and the common `format`s. `format` is
+/// asserted, unknown formats are ignored, and `unevaluatedProperties` /
+/// `unevaluatedItems` are not supported.
+#[derive(Debug)]
+pub struct SchemaValidator {
+    draft: Draft,
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Bool(bool, String),
+    Keywords(Vec<(String, Keyword)>),
+}
+
+#[derive(Debug)]
+enum Keyword {
+    Type(Vec<String>),
+    Enum(Vec<JsonNode>),
+    Const(JsonNode),
+    MultipleOf(f64),
+    Minimum(f64),
+    ExclusiveMinimum(f64),
+    Maximum(f64),
+    ExclusiveMaximum(f64),
+    MinLength(usize),
+    MaxLength(usize),
+    Pattern(Regex),
+    Format(String),
+    PrefixItems(Vec<usize>),
+    Items {
+        skip: usize,
+        schema: usize,
+    },
+    MinItems(usize),
+    MaxItems(usize),
+    UniqueItems,
+    Contains {
+        schema: usize,
+        min: usize,
+        max: Option<usize>,
+    },
+    Required(Vec<String>),
+    Properties(Vec<(String, usize)>),
+    PatternProperties(Vec<(Regex, usize)>),
+    AdditionalProperties {
+        known: Vec<String>,
+        patterns: Vec<Regex>,
+        schema: usize,
+    },
+    PropertyNames(usize),
+    MinProperties(usize),
+    MaxProperties(usize),
+    DependentRequired(Vec<(String, Vec<String>)>),
+    AllOf(Vec<usize>),
+    AnyOf(Vec<usize>),
+    OneOf(Vec<usize>),
+    Not(usize),
+    IfThenElse {
+        condition: usize,
+        then: Option<usize>,
+        otherwise: Option<usize>,
+    },
+    Ref(usize),
+}
+
+impl SchemaValidator {
+    /// Compiles `schema`, detecting the draft from its `$schema`.
+    pub fn compile(schema: &JsonNode) -> Result<SchemaValidator, JsonError> {
+        SchemaValidator::compile_with_draft(schema, Draft::detect(schema))
+    }
+
+    pub fn compile_with_draft(
+        schema: &JsonNode,
+        draft: Draft,
+    ) -> Result<SchemaValidator, JsonError> {
+        let mut compiler = Compiler {
+            root: schema,
+            draft,
+            nodes: Vec::new(),
+            by_pointer: HashMap::new(),
+            anchors: HashMap::new(),
+        };
+        compiler.find_anchors(schema, String::new())?;
+        compiler.compile(schema, String::new())?;
+        Ok(SchemaValidator {
+            draft,
+            nodes: compiler.nodes,
+        })
+    }
+
+    /// A validator for the schema of `T`, as produced by `schema_for`.
+    pub fn for_type<T: JsonSchema + ?Sized>() -> SchemaValidator {
+        SchemaValidator::compile(&schema_for::<T>()).expect("generated schemas are valid")
+    }
+
+    pub fn draft(&self) -> Draft {
+        self.draft
+    }
+
+    /// Validates `instance`, returning every error found.
+    pub fn validate(&self, instance: &JsonNode) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        self.validate_node(0, instance, "", 0, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn is_valid(&self, instance: &JsonNode) -> bool {
+        self.validate(instance).is_ok()
+    }
+
+    fn matches(&self, node: usize, instance: &JsonNode, depth: usize) -> bool {
+        let mut errors = Vec::new();
+        self.validate_node(node, instance, "", depth, &mut errors);
+        errors.is_empty()
+    }
+
+    fn validate_node(
+        &self,
+        node: usize,
+        instance: &JsonNode,
+        instance_pointer: &str,
+        depth: usize,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let keywords = match &self.nodes[node] {
+            Node::Bool(true, _) => return,
+            Node::Bool(false, schema_pointer) => {
+                errors.push(ValidationError {
+                    instance_pointer: instance_pointer.to_string(),
+                    schema_pointer: schema_pointer.clone(),
+                    message: "No value is allowed here".to_string(),
+                });
+                return;
+            }
+            Node::Keywords(keywords) => keywords,
+        };
+        for (schema_pointer, keyword) in keywords {
+            if depth > MAX_SCHEMA_DEPTH {
+                errors.push(ValidationError {
+                    instance_pointer: instance_pointer.to_string(),
+                    schema_pointer: schema_pointer.clone(),
+                    message: "Schema nesting is too deep".to_string(),
+                });
+                return;
+            }
+            let mut error = |message: String| {
+                errors.push(ValidationError {
+                    instance_pointer: instance_pointer.to_string(),
+                    schema_pointer: schema_pointer.clone(),
+                    message,
+                })
+            };
+            self.validate_keyword(keyword, instance, depth, &mut error);
+            if let Some(nested) = self.nested_errors(keyword, instance, instance_pointer, depth) {
+                errors.extend(nested);
+            }
+        }
+    }
+
+    /// Checks reported at the keyword itself; `anyOf`, `oneOf`, `not` and `contains`
+    /// only report whether their subschemas matched.
+    fn validate_keyword(
+        &self,
+        keyword: &Keyword,
+        instance: &JsonNode,
+        depth: usize,
+        error: &mut dyn FnMut(String),
+    ) {
+        match (keyword, instance) {
+            (Keyword::Type(types), _) if !types.iter().any(|t| has_type(instance, t)) => {
+                error(format!(
+                    "Expected {}, found {}",
+                    quoted_list(types, "or"),
+                    type_name(instance)
+                ))
+            }
+            (Keyword::Enum(values), _)
+                if !values.iter().any(|value| json_equal(value, instance)) =>
+            {
+                error(format!(
+                    "Value {} is not one of the allowed values",
+                    instance
+                ))
+            }
+            (Keyword::Const(value), _) if !json_equal(value, instance) => {
+                error(format!("Expected {}, found {}", value, instance))
+            }
+            (Keyword::MultipleOf(divisor), JsonNode::Number(n)) => {
+                let quotient = n / divisor;
+                if (quotient - quotient.round()).abs() > 1e-9 * quotient.abs().max(1.0) {
+                    error(format!("Value {} is not a multiple of {}", n, divisor));
+                }
+            }
+            (Keyword::Minimum(min), JsonNode::Number(n)) if n < min => {
+                error(format!("Value {} is less than the minimum {}", n, min))
+            }
+            (Keyword::ExclusiveMinimum(min), JsonNode::Number(n)) if n <= min => error(format!(
+                "Value {} is not greater than the exclusive minimum {}",
+                n, min
+            )),
+            (Keyword::Maximum(max), JsonNode::Number(n)) if n > max => {
+                error(format!("Value {} is greater than the maximum {}", n, max))
+            }
+            (Keyword::ExclusiveMaximum(max), JsonNode::Number(n)) if n >= max => error(format!(
+                "Value {} is not less than the exclusive maximum {}",
+                n, max
+            )),
+            (Keyword::MinLength(min), JsonNode::String(s)) if s.chars().count() < *min => {
+                error(format!(
+                    "Length {} is less than the minimum {}",
+                    s.chars().count(),
+                    min
+                ))
+            }
+            (Keyword::MaxLength(max), JsonNode::String(s)) if s.chars().count() > *max => {
+                error(format!(
+                    "Length {} is greater than the maximum {}",
+                    s.chars().count(),
+                    max
+                ))
+            }
+            (Keyword::Pattern(regex), JsonNode::String(s)) if !regex.is_match(s) => error(format!(
+                "Value \"{}\" does not match the pattern \"{}\"",
+                s,
+                regex.as_str()
+            )),
+            (Keyword::Format(format), JsonNode::String(s)) if !check_format(format, s) => {
+                error(format!("Value \"{}\" is not a valid {}", s, format))
+            }
+            (Keyword::MinItems(min), JsonNode::Array(arr)) if arr.len() < *min => error(format!(
+                "Array has {} items, fewer than the minimum {}",
+                arr.len(),
+                min
+            )),
+            (Keyword::MaxItems(max), JsonNode::Array(arr)) if arr.len() > *max => error(format!(
+                "Array has {} items, more than the maximum {}",
+                arr.len(),
+                max
+            )),
+            (Keyword::UniqueItems, JsonNode::Array(arr)) => {
+                for (i, a) in arr.iter().enumerate() {
+                    if let Some(j) = arr[i + 1..].iter().position(|b| json_equal(a, b)) {
+                        error(format!("Items at {} and {} are equal", i, i + 1 + j));
+                        break;
+                    }
+                }
+            }
+            (Keyword::Contains { schema, min, max }, JsonNode::Array(arr)) => {
+                let count = arr
+                    .iter()
+                    .filter(|item| self.matches(*schema, item, depth + 1))
+                    .count();
+                if count < *min {
+                    error(format!(
+                        "Array contains {} matching items, fewer than the minimum {}",
+                        count, min
+                    ));
+                } else if let Some(max) = max.filter(|max| count > *max) {
+                    error(format!(
+                        "Array contains {} matching items, more than the maximum {}",
+                        count, max
+                    ));
+                }
+            }
+            (Keyword::Required(required), JsonNode::Object(obj)) => {
+                for key in required.iter().filter(|key| !obj.contains_key(*key)) {
+                    error(format!("Missing required property \"{}\"", key));
+                }
+            }
+            (Keyword::MinProperties(min), JsonNode::Object(obj)) if obj.len() < *min => {
+                error(format!(
+                    "Object has {} properties, fewer than the minimum {}",
+                    obj.len(),
+                    min
+                ))
+            }
+            (Keyword::MaxProperties(max), JsonNode::Object(obj)) if obj.len() > *max => {
+                error(format!(
+                    "Object has {} properties, more than the maximum {}",
+                    obj.len(),
+                    max
+                ))
+            }
+            (Keyword::DependentRequired(dependencies), JsonNode::Object(obj)) => {
+                for (key, required) in dependencies.iter().filter(|(key, _)| obj.contains_key(key))
+                {
+                    for missing in required.iter().filter(|r| !obj.contains_key(*r)) {
+                        error(format!(
+                            "Missing property \"{}\", required by \"{}\"",
+                            missing, key
+                        ));
+                    }
+                }
+            }
+            (Keyword::AnyOf(schemas), _)
+                if !schemas
+                    .iter()
+                    .any(|s| self.matches(*s, instance, depth + 1)) =>
+            {
+                error("Value does not match any of the schemas".to_string())
+            }
+            (Keyword::OneOf(schemas), _) => {
+                let matching = schemas
+                    .iter()
+                    .filter(|s| self.matches(**s, instance, depth + 1))
+                    .count();
+                if matching != 1 {
+                    error(format!(
+                        "Value matches {} of the schemas, expected exactly one",
+                        matching
+                    ));
+                }
+            }
+            (Keyword::Not(schema), _) if self.matches(*schema, instance, depth + 1) => {
+                error("Value matches a schema it must not match".to_string())
+            }
+            _ => {}
+        }
+    }
+
+    /// Errors from subschemas applied in place or to children of the instance, or
+    /// `None` if the keyword has no such subschemas.
+    fn nested_errors(
+        &self,
+        keyword: &Keyword,
+        instance: &JsonNode,
+        instance_pointer: &str,
+        depth: usize,
+    ) -> Option<Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        match (keyword, instance) {
+            (Keyword::PrefixItems(schemas), JsonNode::Array(arr)) => {
+                for (idx, (schema, item)) in schemas.iter().zip(arr).enumerate() {
+                    let pointer = format!("{}/{}", instance_pointer, idx);
+                    self.validate_node(*schema, item, &pointer, depth + 1, &mut errors);
+                }
+            }
+            (Keyword::Items { skip, schema }, JsonNode::Array(arr)) => {
+                for (idx, item) in arr.iter().enumerate().skip(*skip) {
+                    let pointer = format!("{}/{}", instance_pointer, idx);
+                    self.validate_node(*schema, item, &pointer, depth + 1, &mut errors);
+                }
+            }
+            (Keyword::Properties(properties), JsonNode::Object(obj)) => {
+                for (key, schema) in properties {
+                    if let Some(value) = obj.get(key) {
+                        let pointer = child_pointer(instance_pointer, key);
+                        self.validate_node(*schema, value, &pointer, depth + 1, &mut errors);
+                    }
+                }
+            }
+            (Keyword::PatternProperties(patterns), JsonNode::Object(obj)) => {
+                for (key, value) in sorted_members(obj) {
+                    for (_, schema) in patterns.iter().filter(|(regex, _)| regex.is_match(key)) {
+                        let pointer = child_pointer(instance_pointer, key);
+                        self.validate_node(*schema, value, &pointer, depth + 1, &mut errors);
+                    }
+                }
+            }
+            (
+                Keyword::AdditionalProperties {
+                    known,
+                    patterns,
+                    schema,
+                },
+                JsonNode::Object(obj),
+            ) => {
+                for (key, value) in sorted_members(obj) {
+                    if known.contains(key) || patterns.iter().any(|regex| regex.is_match(key)) {
+                        continue;
+                    }
+                    let pointer = child_pointer(instance_pointer, key);
+                    self.validate_node(*schema, value, &pointer, depth + 1, &mut errors);
+                }
+            }
+            (Keyword::PropertyNames(schema), JsonNode::Object(obj)) => {
+                for (key, _) in sorted_members(obj) {
+                    let pointer = child_pointer(instance_pointer, key);
+                    let name = JsonNode::String(key.clone());
+                    self.validate_node(*schema, &name, &pointer, depth + 1, &mut errors);
+                }
+            }
+            (Keyword::AllOf(schemas), _) => {
+                for schema in schemas {
+                    self.validate_node(*schema, instance, instance_pointer, depth + 1, &mut errors);
+                }
+            }
+            (
+                Keyword::IfThenElse {
+                    condition,
+                    then,
+                    otherwise,
+                },
+                _,
+            ) => {
+                let branch = if self.matches(*condition, instance, depth + 1) {
+                    then
+                } else {
+                    otherwise
+                };
+                if let Some(branch) = branch {
+                    self.validate_node(*branch, instance, instance_pointer, depth + 1, &mut errors);
+                }
+            }
+            (Keyword::Ref(schema), _) => {
+                self.validate_node(*schema, instance, instance_pointer, depth + 1, &mut errors);
+            }
+            _ => return None,
+        }
+        Some(errors)
+    }
+}
+
+struct Compiler<'a> {
+    root: &'a JsonNode,
+    draft: Draft,
+    nodes: Vec<Node>,
+    by_pointer: HashMap<String, usize>,
+    /// The pointer of the schema each plain-name fragment (`#name`) refers to.
+    anchors: HashMap<String, String>,
+}
+
+impl<'a> Compiler<'a> {
+    /// Compiles the schema at `pointer`, once, returning its index in `nodes`.
+    fn compile(&mut self, schema: &'a JsonNode, pointer: String) -> Result<usize, JsonError> {
+        if let Some(index) = self.by_pointer.get(&pointer) {
+            return Ok(*index);
+        }
+        let index = self.nodes.len();
+        self.nodes.push(Node::Bool(true, pointer.clone()));
+        self.by_pointer.insert(pointer.clone(), index);
+
+        let node = match schema {
+            JsonNode::Boolean(allowed) => Node::Bool(*allowed, pointer),
+            JsonNode::Object(obj) => Node::Keywords(self.compile_keywords(obj, &pointer)?),
+            _ => {
+                return Err(JsonError::at_pointer(
+                    &pointer,
+                    "A schema must be an object or a boolean",
+                ))
+            }
+        };
+        self.nodes[index] = node;
+        Ok(index)
+    }
+
+    fn compile_keywords(
+        &mut self,
+        obj: &'a HashMap<String, JsonNode>,
+        pointer: &str,
+    ) -> Result<Vec<(String, Keyword)>, JsonError> {
+        let mut keywords = Vec::new();
+        let at = |keyword: &str| child_pointer(pointer, keyword);
+
+        if let Some(reference) = obj.get("$ref") {
+            let target = self.resolve_ref(reference, &at("$ref"))?;
+            keywords.push((at("$ref"), Keyword::Ref(target)));
+            // Before 2019-09, keywords next to `$ref` are ignored.
+            if self.draft == Draft::Draft7 {
+                return Ok(keywords);
+            }
+        }
+
+        // Keywords are compiled in a fixed order so that errors are reported in a stable order.
+        let mut names: Vec<&String> = obj.keys().collect();
+        names.sort_unstable();
+        for name in names {
+            let value = &obj[name];
+            let location = at(name);
+            let keyword = match name.as_str() {
+                "type" => Keyword::Type(match value {
+                    JsonNode::String(t) => vec![t.clone()],
+                    JsonNode::Array(types) => types
+                        .iter()
+                        .map(|t| match t {
+                            JsonNode::String(t) => Ok(t.clone()),
+                            _ => Err(JsonError::at_pointer(&location, "Expected a type name")),
+                        })
+                        .collect::<Result<_, _>>()?,
+                    _ => {
+                        return Err(JsonError::at_pointer(
+                            &location,
+                            "Expected a type name or an array",
+                        ))
+                    }
+                }),
+                "enum" => match value {
+                    JsonNode::Array(values) => Keyword::Enum(values.clone()),
+                    _ => return Err(JsonError::at_pointer(&location, "Expected an array")),
+                },
+                "const" => Keyword::Const(value.clone()),
+                "multipleOf" => match number(value, &location)? {
+                    n if n > 0.0 => Keyword::MultipleOf(n),
+                    _ => {
+                        return Err(JsonError::at_pointer(
+                            &location,
+                            "Expected a positive number",
+                        ))
+                    }
+                },
+                "minimum" => Keyword::Minimum(number(value, &location)?),
+                "maximum" => Keyword::Maximum(number(value, &location)?),
+                "exclusiveMinimum" => Keyword::ExclusiveMinimum(number(value, &location)?),
+                "exclusiveMaximum" => Keyword::ExclusiveMaximum(number(value, &location)?),
+                "minLength" => Keyword::MinLength(count(value, &location)?),
+                "maxLength" => Keyword::MaxLength(count(value, &location)?),
+                "pattern" => Keyword::Pattern(regex(value, &location)?),
+                "format" => match value {
+                    JsonNode::String(format) => Keyword::Format(format.clone()),
+                    _ => return Err(JsonError::at_pointer(&location, "Expected a string")),
+                },
+                "prefixItems" if self.draft == Draft::Draft202012 => {
+                    Keyword::PrefixItems(self.compile_list(value, &location)?)
+                }
+                "items" => match (self.draft, value) {
+                    (Draft::Draft7, JsonNode::Array(_)) => {
+                        Keyword::PrefixItems(self.compile_list(value, &location)?)
+                    }
+                    (Draft::Draft7, _) => Keyword::Items {
+                        skip: 0,
+                        schema: self.compile(value, location.clone())?,
+                    },
+                    (Draft::Draft202012, _) => Keyword::Items {
+                        skip: array_len(obj.get("prefixItems")),
+                        schema: self.compile(value, location.clone())?,
+                    },
+                },
+                "additionalItems" if self.draft == Draft::Draft7 => match obj.get("items") {
+                    Some(JsonNode::Array(items)) => Keyword::Items {
+                        skip: items.len(),
+                        schema: self.compile(value, location.clone())?,
+                    },
+                    _ => continue,
+                },
+                "minItems" => Keyword::MinItems(count(value, &location)?),
+                "maxItems" => Keyword::MaxItems(count(value, &location)?),
+                "uniqueItems" => match value {
+                    JsonNode::Boolean(true) => Keyword::UniqueItems,
+                    JsonNode::Boolean(false) => continue,
+                    _ => return Err(JsonError::at_pointer(&location, "Expected a boolean")),
+                },
+                "contains" => Keyword::Contains {
+                    schema: self.compile(value, location.clone())?,
+                    min: match (self.draft, obj.get("minContains")) {
+                        (Draft::Draft202012, Some(min)) => count(min, &at("minContains"))?,
+                        _ => 1,
+                    },
+                    max: match (self.draft, obj.get("maxContains")) {
+                        (Draft::Draft202012, Some(max)) => Some(count(max, &at("maxContains"))?),
+                        _ => None,
+                    },
+                },
+                "required" => Keyword::Required(strings(value, &location)?),
+                "properties" => Keyword::Properties(self.compile_map(value, &location)?),
+                "patternProperties" => Keyword::PatternProperties(
+                    self.compile_map(value, &location)?
+                        .into_iter()
+                        .map(|(pattern, schema)| {
+                            let regex = Regex::new(&pattern).map_err(|err| {
+                                JsonError::at_pointer(
+                                    &child_pointer(&location, &pattern),
+                                    err.to_string(),
+                                )
+                            })?;
+                            Ok((regex, schema))
+                        })
+                        .collect::<Result<_, JsonError>>()?,
+                ),
+                "additionalProperties" => Keyword::AdditionalProperties {
+                    known: match obj.get("properties") {
+                        Some(JsonNode::Object(properties)) => properties.keys().cloned().collect(),
+                        _ => Vec::new(),
+                    },
+                    patterns: match obj.get("patternProperties") {
+                        Some(JsonNode::Object(patterns)) => patterns
+                            .keys()
+                            .filter_map(|pattern| Regex::new(pattern).ok())
+                            .collect(),
+                        _ => Vec::new(),
+                    },
+                    schema: self.compile(value, location.clone())?,
+                },
+                "propertyNames" => Keyword::PropertyNames(self.compile(value, location.clone())?),
+                "minProperties" => Keyword::MinProperties(count(value, &location)?),
+                "maxProperties" => Keyword::MaxProperties(count(value, &location)?),
+                "dependentRequired" => {
+                    Keyword::DependentRequired(self.dependencies(value, &location)?)
+                }
+                "dependencies" if self.draft == Draft::Draft7 => {
+                    Keyword::DependentRequired(self.dependencies(value, &location)?)
+                }
+                "allOf" => Keyword::AllOf(self.compile_list(value, &location)?),
+                "anyOf" => Keyword::AnyOf(self.compile_list(value, &location)?),
+                "oneOf" => Keyword::OneOf(self.compile_list(value, &location)?),
+                "not" => Keyword::Not(self.compile(value, location.clone())?),
+                "if" => Keyword::IfThenElse {
+                    condition: self.compile(value, location.clone())?,
+                    then: match obj.get("then") {
+                        Some(then) => Some(self.compile(then, at("then"))?),
+                        None => None,
+                    },
+                    otherwise: match obj.get("else") {
+                        Some(otherwise) => Some(self.compile(otherwise, at("else"))?),
+                        None => None,
+                    },
+                },
+                "$defs" | "definitions" => {
+                    // Compiled when referred to; checked here so that errors surface early.
+                    if let JsonNode::Object(defs) = value {
+                        for (name, def) in defs {
+                            self.compile(def, child_pointer(&location, name))?;
+                        }
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            keywords.push((location, keyword));
+        }
+        Ok(keywords)
+    }
+
+    fn compile_list(
+        &mut self,
+        value: &'a JsonNode,
+        pointer: &str,
+    ) -> Result<Vec<usize>, JsonError> {
+        match value {
+            JsonNode::Array(schemas) => schemas
+                .iter()
+                .enumerate()
+                .map(|(idx, schema)| self.compile(schema, format!("{}/{}", pointer, idx)))
+                .collect(),
+            _ => Err(JsonError::at_pointer(
+                pointer,
+                "Expected an array of schemas",
+            )),
+        }
+    }
+
+    fn compile_map(
+        &mut self,
+        value: &'a JsonNode,
+        pointer: &str,
+    ) -> Result<Vec<(String, usize)>, JsonError> {
+        match value {
+            JsonNode::Object(schemas) => {
+                let mut keys: Vec<&String> = schemas.keys().collect();
+                keys.sort_unstable();
+                keys.into_iter()
+                    .map(|key| {
+                        let schema = self.compile(&schemas[key], child_pointer(pointer, key))?;
+                        Ok((key.clone(), schema))
+                    })
+                    .collect()
+            }
+            _ => Err(JsonError::at_pointer(
+                pointer,
+                "Expected an object of schemas",
+            )),
+        }
+    }
+
+    fn dependencies(
+        &mut self,
+        value: &'a JsonNode,
+        pointer: &str,
+    ) -> Result<Vec<(String, Vec<String>)>, JsonError> {
+        match value {
+            JsonNode::Object(dependencies) => {
+                let mut keys: Vec<&String> = dependencies.keys().collect();
+                keys.sort_unstable();
+                keys.into_iter()
+                    .filter(|key| matches!(dependencies[*key], JsonNode::Array(_)))
+                    .map(|key| {
+                        let required = strings(&dependencies[key], &child_pointer(pointer, key))?;
+                        Ok((key.clone(), required))
+                    })
+                    .collect()
+            }
+            _ => Err(JsonError::at_pointer(pointer, "Expected an object")),
+        }
+    }
+
+    /// Records the anchors of `schema` and the schemas in it: `$anchor`, or in Draft 7
+    /// an `$id` that is only a fragment.
+    fn find_anchors(&mut self, schema: &JsonNode, pointer: String) -> Result<(), JsonError> {
+        let obj = match schema {
+            JsonNode::Object(obj) => obj,
+            JsonNode::Array(arr) => {
+                for (idx, item) in arr.iter().enumerate() {
+                    self.find_anchors(item, format!("{}/{}", pointer, idx))?;
+                }
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+        let anchor = match (self.draft, obj.get("$anchor"), obj.get("$id")) {
+            (Draft::Draft202012, Some(JsonNode::String(anchor)), _) => Some(anchor.as_str()),
+            (Draft::Draft7, _, Some(JsonNode::String(id))) => id.strip_prefix('#'),
+            _ => None,
+        };
+        if let Some(anchor) = anchor.filter(|anchor| !anchor.is_empty()) {
+            if self.anchors.contains_key(anchor) {
+                return Err(JsonError::at_pointer(
+                    &pointer,
+                    format!("Duplicate anchor \"{}\"", anchor),
+                ));
+            }
+            self.anchors.insert(anchor.to_string(), pointer.clone());
+        }
+        let mut keys: Vec<&String> = obj.keys().collect();
+        keys.sort_unstable();
+        for key in keys {
+            // Instance values, not schemas.
+            if !matches!(key.as_str(), "const" | "enum" | "default" | "examples") {
+                self.find_anchors(&obj[key], child_pointer(&pointer, key))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Compiles the target of a `$ref` within this document: a JSON pointer fragment
+    /// (`#/$defs/name`) or an anchor (`#name`).
+    fn resolve_ref(&mut self, reference: &JsonNode, pointer: &str) -> Result<usize, JsonError> {
+        let reference = match reference {
+            JsonNode::String(reference) => reference,
+            _ => return Err(JsonError::at_pointer(pointer, "Expected a string")),
+        };
+        let fragment = match reference.strip_prefix('#') {
+            Some(fragment) => percent_decode(fragment),
+            None => {
+                return Err(JsonError::at_pointer(
+                    pointer,
+                    format!(
+                        "Only references within the document are supported, found \"{}\"",
+                        reference
+                    ),
+                ))
+            }
+        };
+        let unresolved = || {
+            JsonError::at_pointer(
+                pointer,
+                format!("Cannot resolve reference \"{}\"", reference),
+            )
+        };
+        let fragment = if fragment.is_empty() || fragment.starts_with('/') {
+            fragment
+        } else {
+            self.anchors
+                .get(&fragment)
+                .cloned()
+                .ok_or_else(unresolved)?
+        };
+        let mut target = self.root;
+        for segment in fragment.split('/').skip(1) {
+            let key = segment.replace("~1", "/").replace("~0", "~");
+            let next = match target {
+                JsonNode::Object(obj) => obj.get(&key),
+                JsonNode::Array(arr) => key.parse::<usize>().ok().and_then(|idx| arr.get(idx)),
+                _ => None,
+            };
+            target = next.ok_or_else(unresolved)?;
+        }
+        self.compile(target, fragment)
+    }
+}
+
+fn child_pointer(pointer: &str, key: &str) -> String {
+    format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"))
+}
+
+fn percent_decode(fragment: &str) -> String {
+    let bytes = fragment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn sorted_members(obj: &HashMap<String, JsonNode>) -> Vec<(&String, &JsonNode)> {
+    let mut members: Vec<(&String, &JsonNode)> = obj.iter().collect();
+    members.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    members
+}
+
+fn array_len(value: Option<&JsonNode>) -> usize {
+    match value {
+        Some(JsonNode::Array(arr)) => arr.len(),
+        _ => 0,
+    }
+}
+
+fn number(value: &JsonNode, pointer: &str) -> Result<f64, JsonError> {
+    match value {
+        JsonNode::Number(n) => Ok(*n),
+        _ => Err(JsonError::at_pointer(pointer, "Expected a number")),
+    }
+}
+
+fn count(value: &JsonNode, pointer: &str) -> Result<usize, JsonError> {
+    match value {
+        JsonNode::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+        _ => Err(JsonError::at_pointer(
+            pointer,
+            "Expected a non-negative integer",
+        )),
+    }
+}
+
+fn strings(value: &JsonNode, pointer: &str) -> Result<Vec<String>, JsonError> {
+    match value {
+        JsonNode::Array(values) => values
+            .iter()
+            .map(|value| match value {
+                JsonNode::String(s) => Ok(s.clone()),
+                _ => Err(JsonError::at_pointer(
+                    pointer,
+                    "Expected an array of strings",
+                )),
+            })
+            .collect(),
+        _ => Err(JsonError::at_pointer(
+            pointer,
+            "Expected an array of strings",
+        )),
+    }
+}
+
+fn regex(value: &JsonNode, pointer: &str) -> Result<Regex, JsonError> {
+    match value {
+        JsonNode::String(pattern) => {
+            Regex::new(pattern).map_err(|err| JsonError::at_pointer(pointer, err.to_string()))
+        }
+        _ => Err(JsonError::at_pointer(pointer, "Expected a string")),
+    }
+}
+
+fn type_name(value: &JsonNode) -> &'static str {
+    match value {
+        JsonNode::Object(_) => "object",
+        JsonNode::Array(_) => "array",
+        JsonNode::String(_) => "string",
+        JsonNode::Number(n) if n.fract() == 0.0 => "integer",
+        JsonNode::Number(_) => "number",
+        JsonNode::Boolean(_) => "boolean",
+        JsonNode::Null => "null",
+    }
+}
+
+fn has_type(value: &JsonNode, name: &str) -> bool {
+    match (name, value) {
+        ("number", JsonNode::Number(_)) => true,
+        (name, value) => type_name(value) == name,
+    }
+}
+
+fn quoted_list(values: &[String], conjunction: &str) -> String {
+    let quoted: Vec<String> = values
+        .iter()
+        .map(|value| format!("\"{}\"", value))
+        .collect();
+    quoted.join(&format!(" {} ", conjunction))
+}
+
+/// Equality as JSON Schema defines it, where `1` and `1.0` are the same number.
+fn json_equal(a: &JsonNode, b: &JsonNode) -> bool {
+    match (a, b) {
+        (JsonNode::Object(a), JsonNode::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, value)| b.get(key).is_some_and(|other| json_equal(value, other)))
+        }
+        (JsonNode::Array(a), JsonNode::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| json_equal(a, b))
+        }
+        _ => a == b,
+    }
+}
+
+fn check_format(format: &str, value: &str) -> bool {
+    match format {
+        "date-time" => {
+            let (date, time) = match value.split_once(['T', 't']) {
+                Some(parts) => parts,
+                None => return false,
+            };
+            is_date(date) && is_time(time)
+        }
+        "date" => is_date(value),
+        "time" => is_time(value),
+        "email" => match value.rsplit_once('@') {
+            Some((local, domain)) => {
+                !local.is_empty() && !local.contains(char::is_whitespace) && is_hostname(domain)
+            }
+            None => false,
+        },
+        "hostname" => is_hostname(value),
+        "ipv4" => value.parse::<Ipv4Addr>().is_ok(),
+        "ipv6" => value.parse::<Ipv6Addr>().is_ok(),
+        "uri" => is_uri(value),
+        "uuid" => is_uuid(value),
+        "regex" => Regex::new(value).is_ok(),
+        "json-pointer" => value.is_empty() || value.starts_with('/'),
+        _ => true,
+    }
+}
+
+fn digits(value: &str, len: usize) -> Option<u32> {
+    if value.len() == len && value.bytes().all(|b| b.is_ascii_digit()) {
+        value.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn is_date(value: &str) -> bool {
+    let parts: Vec<&str> = value.split('-').collect();
+    let (year, month, day) = match parts.as_slice() {
+        [year, month, day] => match (digits(year, 4), digits(month, 2), digits(day, 2)) {
+            (Some(year), Some(month), Some(day)) => (year, month, day),
+            _ => return false,
+        },
+        _ => return false,
+    };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days_in_month).contains(&day)
+}
+
+fn is_time(value: &str) -> bool {
+    let (time, offset) = if let Some(time) = value.strip_suffix(['Z', 'z']) {
+        (time, None)
+    } else {
+        match value.rfind(['+', '-']) {
+            Some(idx) => (&value[..idx], Some(&value[idx + 1..])),
+            None => return false,
+        }
+    };
+    if let Some(offset) = offset {
+        match offset.split_once(':') {
+            Some((hours, minutes)) => match (digits(hours, 2), digits(minutes, 2)) {
+                (Some(hours), Some(minutes)) if hours < 24 && minutes < 60 => {}
+                _ => return false,
+            },
+            None => return false,
+        }
+    }
+    let (time, fraction) = match time.split_once('.') {
+        Some((time, fraction)) => (time, Some(fraction)),
+        None => (time, None),
+    };
+    if fraction.is_some_and(|f| f.is_empty() || !f.bytes().all(|b| b.is_ascii_digit())) {
+        return false;
+    }
+    let parts: Vec<&str> = time.split(':').collect();
+    match parts.as_slice() {
+        [hours, minutes, seconds] => {
+            matches!(
+                (digits(hours, 2), digits(minutes, 2), digits(seconds, 2)),
+                (Some(h), Some(m), Some(s)) if h < 24 && m < 60 && s <= 60
+            )
+        }
+        _ => false,
+    }
+}
+
+fn is_hostname(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 253
+        && value.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        })
+}
+
+fn is_uri(value: &str) -> bool {
+    match value.split_once(':') {
+        Some((scheme, rest)) => {
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+                && !rest.contains(|c: char| c.is_whitespace() || c.is_control())
+        }
+        None => false,
+    }
+}
+
+fn is_uuid(value: &str) -> bool {
+    let groups: Vec<&str> = value.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn user_schema() -> JsonNode {
+        json!({
+            "$defs": {
+                "tag": {"type": "string", "pattern": "^[a-z]+$"}
+            },
+            "type": "object",
+            "properties": {
+                "id": {"type": "integer", "minimum": 1},
+                "email": {"type": "string", "format": "email"},
+                "tags": {"type": "array", "items": {"$ref": "#/$defs/tag"}, "uniqueItems": true},
+                "kind": {"enum": ["a", "b"]},
+                "size": {"oneOf": [{"type": "integer"}, {"type": "null"}]},
+                "note": {"not": {"type": "null"}}
+            },
+            "required": ["id", "email"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn validates_draft_2020_12_keywords() {
+        let validator = SchemaValidator::compile(&user_schema()).unwrap();
+        assert_eq!(validator.draft(), Draft::Draft202012);
+        assert!(validator.is_valid(&json!({
+            "id": 3,
+            "email": "a@example.com",
+            "tags": ["x", "y"],
+            "kind": "a",
+            "size": null,
+            "note": "hi"
+        })));
+    }
+
+    #[test]
+    fn reports_instance_and_schema_pointers() {
+        let validator = SchemaValidator::compile(&user_schema()).unwrap();
+        let errors = validator
+            .validate(&json!({
+                "id": 0.5,
+                "tags": ["ok", "Bad", "ok"],
+                "kind": "c",
+                "note": null,
+                "extra": true
+            }))
+            .unwrap_err();
+        let errors: Vec<(&str, &str)> = errors
+            .iter()
+            .map(|err| (err.instance_pointer(), err.schema_pointer()))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                ("/extra", "/additionalProperties"),
+                ("/id", "/properties/id/minimum"),
+                ("/id", "/properties/id/type"),
+                ("/kind", "/properties/kind/enum"),
+                ("/note", "/properties/note/not"),
+                ("/tags/1", "/$defs/tag/pattern"),
+                ("/tags", "/properties/tags/uniqueItems"),
+                ("", "/required"),
+            ]
+        );
+    }
+
+    #[test]
+    fn validates_draft_7_keywords() {
+        let draft_7 = json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "definitions": {"positive": {"type": "number", "exclusiveMinimum": 0}},
+            "type": "array",
+            "items": [{"type": "string", "format": "date"}],
+            "additionalItems": {"$ref": "#/definitions/positive"}
+        });
+        let validator = SchemaValidator::compile(&draft_7).unwrap();
+        assert_eq!(validator.draft(), Draft::Draft7);
+        assert!(validator.is_valid(&json!(["2024-02-29", 1, 2.5])));
+        let errors = validator.validate(&json!(["2023-02-29", 0])).unwrap_err();
+        let errors: Vec<(&str, &str)> = errors
+            .iter()
+            .map(|err| (err.instance_pointer(), err.schema_pointer()))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                ("/1", "/definitions/positive/exclusiveMinimum"),
+                ("/0", "/items/0/format"),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_keyword_values() {
+        let err =
+            SchemaValidator::compile(&json!({"properties": {"a": {"minimum": "1"}}})).unwrap_err();
+        assert_eq!(err.pointer(), "/properties/a/minimum");
+    }
+
+    #[test]
+    fn resolves_anchors() {
+        let anchored = json!({
+            "$defs": {"x": {"$anchor": "foo", "type": "string"}},
+            "properties": {"p": {"$ref": "#foo"}}
+        });
+        let validator = SchemaValidator::compile(&anchored).unwrap();
+        assert!(validator.is_valid(&json!({"p": "s"})));
+        assert!(!validator.is_valid(&json!({"p": {}})));
+    }
+
+    #[test]
+    fn resolves_draft_7_fragment_ids() {
+        let draft_7_anchored = json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "definitions": {"x": {"$id": "#foo", "type": "string"}},
+            "properties": {"p": {"$ref": "#foo"}}
+        });
+        let validator = SchemaValidator::compile(&draft_7_anchored).unwrap();
+        assert!(validator.is_valid(&json!({"p": "s"})));
+        assert!(!validator.is_valid(&json!({"p": 1})));
+    }
+
+    #[test]
+    fn rejects_unresolvable_and_duplicate_anchors() {
+        let err = SchemaValidator::compile(&json!({"$ref": "#bar"})).unwrap_err();
+        assert_eq!(err.message(), "Cannot resolve reference \"#bar\"");
+        let err = SchemaValidator::compile(&json!({"$ref": "other.json#foo"})).unwrap_err();
+        assert_eq!(err.pointer(), "/$ref");
+        let err = SchemaValidator::compile(&json!({
+            "$defs": {"a": {"$anchor": "foo"}, "b": {"$anchor": "foo"}}
+        }))
+        .unwrap_err();
+        assert_eq!(err.pointer(), "/$defs/b");
+    }
+}
@@ -0,0 +1,102 @@
+use crate::json_basic::{JsonNode, ToJson};
+use std::collections::HashMap;
+
+/// Values that can be spliced into an object with `..#value` in `json!`.
+///
+/// Owned values are moved into the object; references are cloned. `None` and
+/// `JsonNode::Null` add nothing.
+pub trait JsonObjectSpread {
+    fn spread_into_object(self, object: &mut HashMap<String, JsonNode>);
+}
+
+/// Values that can be spliced into an array with `..#value` in `json!`.
+///
+/// Owned values are moved into the array; references are cloned. `None` and
+/// `JsonNode::Null` add nothing.
+pub trait JsonArraySpread {
+    fn spread_into_array(self, array: &mut Vec<JsonNode>);
+}
+
+impl JsonObjectSpread for JsonNode {
+    fn spread_into_object(self, object: &mut HashMap<String, JsonNode>) {
+        match self {
+            JsonNode::Object(members) => object.extend(members),
+            JsonNode::Null => {}
+            _ => panic!("Cannot spread non-object type into an object"),
+        }
+    }
+}
+
+impl JsonObjectSpread for &JsonNode {
+    fn spread_into_object(self, object: &mut HashMap<String, JsonNode>) {
+        match self {
+            JsonNode::Object(members) => object.extend(
+                members
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone())),
+            ),
+            JsonNode::Null => {}
+            _ => panic!("Cannot spread non-object type into an object"),
+        }
+    }
+}
+
+impl JsonObjectSpread for HashMap<String, JsonNode> {
+    fn spread_into_object(self, object: &mut HashMap<String, JsonNode>) {
+        object.extend(self);
+    }
+}
+
+impl<T: JsonObjectSpread> JsonObjectSpread for Option<T> {
+    fn spread_into_object(self, object: &mut HashMap<String, JsonNode>) {
+        if let Some(value) = self {
+            value.spread_into_object(object);
+        }
+    }
+}
+
+impl JsonArraySpread for JsonNode {
+    fn spread_into_array(self, array: &mut Vec<JsonNode>) {
+        match self {
+            JsonNode::Array(elements) => array.extend(elements),
+            JsonNode::Null => {}
+            _ => panic!("Cannot spread non-array type into an array"),
+        }
+    }
+}
+
+impl JsonArraySpread for &JsonNode {
+    fn spread_into_array(self, array: &mut Vec<JsonNode>) {
+        match self {
+            JsonNode::Array(elements) => array.extend(elements.iter().cloned()),
+            JsonNode::Null => {}
+            _ => panic!("Cannot spread non-array type into an array"),
+        }
+    }
+}
+
+impl<T: ToJson> JsonArraySpread for Vec<T> {
+    fn spread_into_array(self, array: &mut Vec<JsonNode>) {
+        self.as_slice().spread_into_array(array);
+    }
+}
+
+impl<T: ToJson> JsonArraySpread for &Vec<T> {
+    fn spread_into_array(self, array: &mut Vec<JsonNode>) {
+        self.as_slice().spread_into_array(array);
+    }
+}
+
+impl<T: ToJson> JsonArraySpread for &[T] {
+    fn spread_into_array(self, array: &mut Vec<JsonNode>) {
+        array.extend(self.iter().map(T::to_json));
+    }
+}
+
+impl<T: JsonArraySpread> JsonArraySpread for Option<T> {
+    fn spread_into_array(self, array: &mut Vec<JsonNode>) {
+        if let Some(value) = self {
+            value.spread_into_array(array);
+        }
+    }
+}
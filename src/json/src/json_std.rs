@@ -0,0 +1,556 @@
+use crate::json_basic::{FromJson, JsonNode, ToJson};
+use crate::json_error::{collect_results, JsonError};
+use crate::json_key::{collect_object_entries, object_from_entries, JsonKey};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashSet, LinkedList, VecDeque};
+use std::hash::Hash;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::num::{
+    NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
+    NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize, Wrapping,
+};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn array_elements(json: &JsonNode) -> Result<&Vec<JsonNode>, JsonError> {
+    match json {
+        JsonNode::Array(arr) => Ok(arr),
+        _ => Err(JsonError::new("Cannot convert non-array type to array")),
+    }
+}
+
+fn collect_elements<T, C>(json: &JsonNode) -> Result<C, JsonError>
+where
+    T: FromJson,
+    C: FromIterator<T>,
+{
+    collect_results(
+        array_elements(json)?
+            .iter()
+            .enumerate()
+            .map(|(idx, x)| T::try_from_json(x).map_err(|err| err.with_index(idx))),
+    )
+}
+
+impl ToJson for JsonNode {
+    fn to_json(&self) -> JsonNode {
+        self.clone()
+    }
+}
+
+impl FromJson for JsonNode {
+    fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+        Ok(json.clone())
+    }
+}
+
+impl ToJson for () {
+    fn to_json(&self) -> JsonNode {
+        JsonNode::Null
+    }
+}
+
+impl FromJson for () {
+    fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+        match json {
+            JsonNode::Null => Ok(()),
+            _ => Err(JsonError::new("Cannot convert non-null type to unit")),
+        }
+    }
+}
+
+impl ToJson for char {
+    fn to_json(&self) -> JsonNode {
+        JsonNode::String(self.to_string())
+    }
+}
+
+impl FromJson for char {
+    fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+        let s = match json {
+            JsonNode::String(s) => s,
+            _ => return Err(JsonError::new("Cannot convert non-string type to char")),
+        };
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(JsonError::new(
+                "Cannot convert string of length other than one to char",
+            )),
+        }
+    }
+}
+
+impl<T> ToJson for Option<T>
+where
+    T: ToJson,
+{
+    fn to_json(&self) -> JsonNode {
+        match self {
+            Some(value) => value.to_json(),
+            None => JsonNode::Null,
+        }
+    }
+}
+
+impl<T> FromJson for Option<T>
+where
+    T: FromJson,
+{
+    fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+        match json {
+            JsonNode::Null => Ok(None),
+            _ => T::try_from_json(json).map(Some),
+        }
+    }
+}
+
+macro_rules! impl_from_and_to_json_for_pointer {
+    ($($t:ident),*) => {
+        $(
+            impl<T> ToJson for $t<T>
+            where
+                T: ToJson + ?Sized,
+            {
+                fn to_json(&self) -> JsonNode {
+                    (**self).to_json()
+                }
+            }
+
+            impl<T> FromJson for $t<T>
+            where
+                T: FromJson,
+            {
+                fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+                    T::try_from_json(json).map($t::new)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_and_to_json_for_pointer!(Box, Rc, Arc);
+
+impl<T> ToJson for Cow<'_, T>
+where
+    T: ToJson + ToOwned + ?Sized,
+{
+    fn to_json(&self) -> JsonNode {
+        (**self).to_json()
+    }
+}
+
+impl<T> FromJson for Cow<'_, T>
+where
+    T: ToOwned + ?Sized,
+    T::Owned: FromJson,
+{
+    fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+        T::Owned::try_from_json(json).map(Cow::Owned)
+    }
+}
+
+impl<T> ToJson for [T]
+where
+    T: ToJson,
+{
+    fn to_json(&self) -> JsonNode {
+        JsonNode::Array(self.iter().map(|x| x.to_json()).collect())
+    }
+}
+
+impl<T, const N: usize> ToJson for [T; N]
+where
+    T: ToJson,
+{
+    fn to_json(&self) -> JsonNode {
+        self.as_slice().to_json()
+    }
+}
+
+impl<T, const N: usize> FromJson for [T; N]
+where
+    T: FromJson,
+{
+    fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+        let len = array_elements(json)?.len();
+        if len != N {
+            return Err(JsonError::new(format!(
+                "Expected array of length {}, found length {}",
+                N, len
+            )));
+        }
+        let elements: Vec<T> = collect_elements(json)?;
+        match elements.try_into() {
+            Ok(arr) => Ok(arr),
+            Err(_) => unreachable!("length checked above"),
+        }
+    }
+}
+
+macro_rules! impl_from_and_to_json_for_sequence {
+    ($($t:ident<T $(: $bound:path)?>),*) => {
+        $(
+            impl<T> ToJson for $t<T>
+            where
+                T: ToJson,
+            {
+                fn to_json(&self) -> JsonNode {
+                    JsonNode::Array(self.iter().map(|x| x.to_json()).collect())
+                }
+            }
+
+            impl<T> FromJson for $t<T>
+            where
+                T: FromJson $(+ $bound)?,
+            {
+                fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+                    collect_elements(json)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_and_to_json_for_sequence!(VecDeque<T>, LinkedList<T>, BTreeSet<T: Ord>);
+
+impl<T> ToJson for HashSet<T>
+where
+    T: ToJson,
+{
+    fn to_json(&self) -> JsonNode {
+        JsonNode::Array(self.iter().map(|x| x.to_json()).collect())
+    }
+}
+
+impl<T> FromJson for HashSet<T>
+where
+    T: FromJson + Eq + Hash,
+{
+    fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+        collect_elements(json)
+    }
+}
+
+impl<K, V> ToJson for BTreeMap<K, V>
+where
+    K: JsonKey,
+    V: ToJson,
+{
+    fn to_json(&self) -> JsonNode {
+        object_from_entries(self)
+    }
+}
+
+impl<K, V> FromJson for BTreeMap<K, V>
+where
+    K: JsonKey + Ord,
+    V: FromJson,
+{
+    fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+        collect_object_entries(json)
+    }
+}
+
+macro_rules! impl_from_and_to_json_for_tuple {
+    ($(($len:expr, $($idx:tt $t:ident),+)),*) => {
+        $(
+            impl<$($t),+> ToJson for ($($t,)+)
+            where
+                $($t: ToJson),+
+            {
+                fn to_json(&self) -> JsonNode {
+                    JsonNode::Array(vec![$(self.$idx.to_json()),+])
+                }
+            }
+
+            impl<$($t),+> FromJson for ($($t,)+)
+            where
+                $($t: FromJson),+
+            {
+                fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+                    let arr = array_elements(json)?;
+                    if arr.len() != $len {
+                        return Err(JsonError::new(format!(
+                            "Expected array of length {}, found length {}",
+                            $len,
+                            arr.len()
+                        )));
+                    }
+                    let mut violations = Vec::new();
+                    let elements = ($(
+                        match $t::try_from_json(&arr[$idx]) {
+                            Ok(element) => Some(element),
+                            Err(err) => {
+                                violations.extend(err.with_index($idx).into_violations());
+                                None
+                            }
+                        },
+                    )+);
+                    if !violations.is_empty() {
+                        return Err(JsonError::from_violations(violations));
+                    }
+                    Ok(($(elements.$idx.unwrap(),)+))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_and_to_json_for_tuple!(
+    (1, 0 T0),
+    (2, 0 T0, 1 T1),
+    (3, 0 T0, 1 T1, 2 T2),
+    (4, 0 T0, 1 T1, 2 T2, 3 T3),
+    (5, 0 T0, 1 T1, 2 T2, 3 T3, 4 T4),
+    (6, 0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5),
+    (7, 0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6),
+    (8, 0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7),
+    (9, 0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8),
+    (10, 0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8, 9 T9),
+    (11, 0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8, 9 T9, 10 T10),
+    (12, 0 T0, 1 T1, 2 T2, 3 T3, 4 T4, 5 T5, 6 T6, 7 T7, 8 T8, 9 T9, 10 T10, 11 T11)
+);
+
+macro_rules! impl_from_and_to_json_for_non_zero {
+    ($(($t:ty, $inner:ty)),*) => {
+        $(
+            impl ToJson for $t {
+                fn to_json(&self) -> JsonNode {
+                    self.get().to_json()
+                }
+            }
+
+            impl FromJson for $t {
+                fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+                    <$t>::new(<$inner>::try_from_json(json)?)
+                        .ok_or_else(|| JsonError::new("Expected a non-zero number"))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_and_to_json_for_non_zero!(
+    (NonZeroI8, i8),
+    (NonZeroI16, i16),
+    (NonZeroI32, i32),
+    (NonZeroI64, i64),
+    (NonZeroI128, i128),
+    (NonZeroIsize, isize),
+    (NonZeroU8, u8),
+    (NonZeroU16, u16),
+    (NonZeroU32, u32),
+    (NonZeroU64, u64),
+    (NonZeroU128, u128),
+    (NonZeroUsize, usize)
+);
+
+impl<T> ToJson for Wrapping<T>
+where
+    T: ToJson,
+{
+    fn to_json(&self) -> JsonNode {
+        self.0.to_json()
+    }
+}
+
+impl<T> FromJson for Wrapping<T>
+where
+    T: FromJson,
+{
+    fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+        T::try_from_json(json).map(Wrapping)
+    }
+}
+
+/// Encoded as `{"secs": u64, "nanos": u32}`. Seconds above 2^53 are rounded, as
+/// JSON numbers are read and written as `f64`.
+impl ToJson for Duration {
+    fn to_json(&self) -> JsonNode {
+        let mut json = JsonNode::Object(Default::default());
+        json["secs"] = self.as_secs().to_json();
+        json["nanos"] = self.subsec_nanos().to_json();
+        json
+    }
+}
+
+impl FromJson for Duration {
+    fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+        let field = |key: &str| match json.get(key) {
+            Some(value) => Ok(value),
+            None => Err(JsonError::missing_field(key)),
+        };
+        let secs = u64::try_from_json(field("secs")?).map_err(|err| err.with_key("secs"))?;
+        let nanos = u32::try_from_json(field("nanos")?).map_err(|err| err.with_key("nanos"))?;
+        // `Duration::new` panics on nanoseconds that overflow the seconds.
+        if nanos > 999_999_999 {
+            return Err(JsonError::new(format!(
+                "Expected an integer from 0 to 999999999, found {}",
+                nanos
+            ))
+            .with_key("nanos"));
+        }
+        Ok(Duration::new(secs, nanos))
+    }
+}
+
+impl ToJson for Path {
+    fn to_json(&self) -> JsonNode {
+        JsonNode::String(self.to_string_lossy().into_owned())
+    }
+}
+
+impl ToJson for PathBuf {
+    fn to_json(&self) -> JsonNode {
+        self.as_path().to_json()
+    }
+}
+
+impl FromJson for PathBuf {
+    fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+        String::try_from_json(json).map(PathBuf::from)
+    }
+}
+
+macro_rules! impl_from_and_to_json_for_display {
+    ($($t:ty),*) => {
+        $(
+            impl ToJson for $t {
+                fn to_json(&self) -> JsonNode {
+                    JsonNode::String(self.to_string())
+                }
+            }
+
+            impl FromJson for $t {
+                fn try_from_json(json: &JsonNode) -> Result<Self, JsonError> {
+                    String::try_from_json(json)?
+                        .parse()
+                        .map_err(|err| JsonError::new(format!("{}", err)))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_and_to_json_for_display!(
+    IpAddr,
+    Ipv4Addr,
+    Ipv6Addr,
+    SocketAddr,
+    SocketAddrV4,
+    SocketAddrV6
+);
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::time::Duration;
+
+    #[derive(Debug, Clone, PartialEq, JsonType)]
+    struct StdTypes {
+        boxed: Box<i32>,
+        shared: std::rc::Rc<String>,
+        atomic: std::sync::Arc<Vec<u8>>,
+        cow: std::borrow::Cow<'static, str>,
+        ordered: std::collections::BTreeMap<String, bool>,
+        set: std::collections::BTreeSet<i64>,
+        queue: std::collections::VecDeque<char>,
+        list: std::collections::LinkedList<()>,
+        fixed: [u16; 3],
+        pair: (String, f64),
+        triple: (u8, Option<u8>, Option<u8>),
+        non_zero: std::num::NonZeroU32,
+        wrapping: std::num::Wrapping<u8>,
+        duration: Duration,
+        path: std::path::PathBuf,
+        ip: std::net::IpAddr,
+        socket: std::net::SocketAddr,
+        raw: JsonNode,
+    }
+
+    #[test]
+    fn std_types_round_trip() {
+        let value = StdTypes {
+            boxed: Box::new(1),
+            shared: std::rc::Rc::new("rc".to_string()),
+            atomic: std::sync::Arc::new(vec![1, 2]),
+            cow: std::borrow::Cow::Borrowed("cow"),
+            ordered: [("a".to_string(), true)].into_iter().collect(),
+            set: [3, 1, 2].into_iter().collect(),
+            queue: ['x', 'y'].into_iter().collect(),
+            list: [(), ()].into_iter().collect(),
+            fixed: [7, 8, 9],
+            pair: ("left".to_string(), 0.5),
+            triple: (1, Some(2), None),
+            non_zero: std::num::NonZeroU32::new(5).unwrap(),
+            wrapping: std::num::Wrapping(255),
+            duration: Duration::new(3, 500),
+            path: std::path::PathBuf::from("/tmp/file.json"),
+            ip: "::1".parse().unwrap(),
+            socket: "127.0.0.1:8080".parse().unwrap(),
+            raw: json!({"nested": [1, null]}),
+        };
+
+        let value_json = value.to_json();
+        assert_eq!(value_json["set"], json!([1, 2, 3]));
+        assert_eq!(value_json["list"], json!([null, null]));
+        assert_eq!(value_json["triple"], json!([1, 2, null]));
+        assert_eq!(value_json["duration"], json!({"secs": 3, "nanos": 500}));
+        assert_eq!(value_json["socket"], json!("127.0.0.1:8080"));
+        assert_eq!(StdTypes::from_json(&value_json), value);
+    }
+
+    #[test]
+    fn fixed_arrays_check_length() {
+        let err = <[u16; 3]>::try_from_json(&json!([1, 2])).unwrap_err();
+        assert_eq!(err.message(), "Expected array of length 3, found length 2");
+    }
+
+    #[test]
+    fn tuples_report_the_failing_element() {
+        let err = <(i32, String)>::try_from_json(&json!([1, 2])).unwrap_err();
+        assert_eq!(err.pointer(), "/1");
+    }
+
+    #[test]
+    fn slices_encode_as_arrays() {
+        assert_eq!([1, 2][..].to_json(), json!([1, 2]));
+    }
+
+    #[test]
+    fn char_requires_a_single_character() {
+        assert_eq!(char::try_from_json(&json!("a")), Ok('a'));
+        assert!(char::try_from_json(&json!("ab")).is_err());
+    }
+
+    #[test]
+    fn non_zero_rejects_zero_and_out_of_range_numbers() {
+        use std::num::NonZeroU8;
+        assert_eq!(
+            NonZeroU8::try_from_json(&json!(200)),
+            Ok(NonZeroU8::new(200).unwrap())
+        );
+        assert!(NonZeroU8::try_from_json(&json!(0)).is_err());
+        assert!(NonZeroU8::try_from_json(&json!(1000)).is_err());
+        assert!(NonZeroU8::try_from_json(&json!(1.5)).is_err());
+    }
+
+    #[test]
+    fn duration_rejects_invalid_members() {
+        let err = Duration::try_from_json(&json!({"secs": 1e20, "nanos": 2e9})).unwrap_err();
+        assert_eq!(err.pointer(), "/secs");
+        let err = Duration::try_from_json(&json!({"secs": 1, "nanos": 2e9})).unwrap_err();
+        assert_eq!(err.pointer(), "/nanos");
+        assert_eq!(
+            err.message(),
+            "Expected an integer from 0 to 999999999, found 2000000000"
+        );
+        assert!(Duration::try_from_json(&json!({"secs": -1, "nanos": 0})).is_err());
+        assert!(Duration::try_from_json(&json!({"secs": 1.5, "nanos": 0})).is_err());
+        let err = Duration::try_from_json(&json!({"secs": 1})).unwrap_err();
+        assert_eq!(err.to_string(), "Missing field \"nanos\"");
+    }
+}
@@ -0,0 +1,137 @@
+use crate::json_basic::JsonNode;
+use crate::json_error::ParseError;
+use crate::json_lexer::{JsonLexer, JsonSource, ReadSource, StrSource};
+use crate::json_options::ParseOptions;
+use crate::json_parser::JsonParser;
+use std::io::Read;
+use std::ops::Range;
+
+/// Reads successive root values from one input: values that simply follow each other
+/// (`{...}{...}[...]`, with or without whitespace between them), or the texts of an
+/// RFC 7464 JSON text sequence, each started by an RS character. Every value comes with
+/// the range of bytes it spans in the input.
+///
+/// The stream ends at the first error, since there is no telling where the next value
+/// starts.
+///
+/// ```ignore
+/// for document in JsonStream::new(input) {
+///     let (value, range) = document?;
+/// }
+/// ```
+pub struct JsonStream<S> {
+    parser: JsonParser<S>,
+    done: bool,
+}
+
+impl<'a> JsonStream<StrSource<'a>> {
+    pub fn new(input: &'a str) -> JsonStream<StrSource<'a>> {
+        JsonStream::from_lexer(JsonLexer::new(input))
+    }
+}
+
+impl<R: Read> JsonStream<ReadSource<R>> {
+    pub fn from_reader(reader: R) -> JsonStream<ReadSource<R>> {
+        JsonStream::from_lexer(JsonLexer::from_source(ReadSource::new(reader)))
+    }
+}
+
+impl<S: JsonSource> JsonStream<S> {
+    fn from_lexer(mut lexer: JsonLexer<S>) -> JsonStream<S> {
+        lexer.record_separators = true;
+        JsonStream {
+            parser: JsonParser::new(lexer),
+            done: false,
+        }
+    }
+
+    pub fn with_options(mut self, options: ParseOptions) -> JsonStream<S> {
+        self.parser = self.parser.with_options(options);
+        self
+    }
+}
+
+impl<S: JsonSource> Iterator for JsonStream<S> {
+    type Item = Result<(JsonNode, Range<usize>), ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.parser.parse_next().transpose();
+        self.done = !matches!(result, Some(Ok(_)));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::ops::Range;
+
+    const CONCATENATED: &str = "{\"a\": 1}{\"b\": [true]}[] 12 \"s\"\n-3.5 null";
+
+    #[test]
+    fn stream_yields_concatenated_values() {
+        let values: Vec<JsonNode> = JsonStream::new(CONCATENATED)
+            .map(|document| document.map(|(value, _)| value))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            values,
+            [
+                json!({"a": 1}),
+                json!({"b": [true]}),
+                json!([]),
+                json!(12),
+                json!("s"),
+                json!(-3.5),
+                json!(null)
+            ]
+        );
+    }
+
+    #[test]
+    fn stream_reports_byte_ranges() {
+        let texts: Vec<&str> = JsonStream::new(CONCATENATED)
+            .map(|document| &CONCATENATED[document.unwrap().1])
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                "{\"a\": 1}",
+                "{\"b\": [true]}",
+                "[]",
+                "12",
+                "\"s\"",
+                "-3.5",
+                "null"
+            ]
+        );
+    }
+
+    #[test]
+    fn stream_skips_record_separators() {
+        let sequence = "\u{1e}{\"id\": 1}\n\u{1e}[\"\u{e9}\"]\n\u{1e}\u{1e}2\n";
+        let documents: Vec<(JsonNode, Range<usize>)> = JsonStream::from_reader(sequence.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(documents.len(), 3);
+        assert_eq!(documents[1], (json!(["\u{e9}"]), 12..18));
+        assert_eq!(&sequence[documents[2].1.clone()], "2");
+    }
+
+    #[test]
+    fn stream_ends_after_an_error() {
+        let mut stream = JsonStream::new("[1] [2, \u{1e}] [3]");
+        assert!(stream.next().unwrap().is_ok());
+        let err = stream.next().unwrap().unwrap_err();
+        assert_eq!(err.position().offset, 8);
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn stream_of_whitespace_is_empty() {
+        assert!(JsonStream::new(" \n ").next().is_none());
+    }
+}
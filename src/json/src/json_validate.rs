@@ -0,0 +1,209 @@
+use crate::json_error::JsonError;
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::sync::OnceLock;
+
+/// A regular expression compiled on first use, for `#[json(regex = "...")]`.
+pub struct Pattern {
+    source: &'static str,
+    regex: OnceLock<Regex>,
+}
+
+impl Pattern {
+    pub const fn new(source: &'static str) -> Pattern {
+        Pattern {
+            source,
+            regex: OnceLock::new(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.source
+    }
+
+    /// Panics if the pattern is not a valid regex; the derive checks this at compile time.
+    pub fn is_match(&self, value: &str) -> bool {
+        self.regex
+            .get_or_init(|| Regex::new(self.source).expect("invalid regex"))
+            .is_match(value)
+    }
+}
+
+/// Values with a length, checked by `#[json(length(...))]` and `#[json(non_empty)]`.
+/// `None` means there is nothing to check, as for a missing `Option`.
+pub trait ValidateLength {
+    fn validate_length(&self) -> Option<usize>;
+}
+
+/// Numbers checked by `#[json(range(...))]`.
+pub trait ValidateRange {
+    fn validate_range(&self) -> Option<f64>;
+}
+
+/// Strings checked by `#[json(regex = "...")]`.
+pub trait ValidatePattern {
+    fn validate_pattern(&self) -> Option<&str>;
+}
+
+impl ValidateLength for str {
+    fn validate_length(&self) -> Option<usize> {
+        Some(self.chars().count())
+    }
+}
+
+impl ValidateLength for String {
+    fn validate_length(&self) -> Option<usize> {
+        self.as_str().validate_length()
+    }
+}
+
+impl ValidateLength for Cow<'_, str> {
+    fn validate_length(&self) -> Option<usize> {
+        self.as_ref().validate_length()
+    }
+}
+
+impl<T> ValidateLength for [T] {
+    fn validate_length(&self) -> Option<usize> {
+        Some(self.len())
+    }
+}
+
+macro_rules! impl_validate_length_for_collection {
+    ($($collection:ident<$($param:ident),*>),*) => {
+        $(
+            impl<$($param),*> ValidateLength for $collection<$($param),*> {
+                fn validate_length(&self) -> Option<usize> {
+                    Some(self.len())
+                }
+            }
+        )*
+    };
+}
+
+impl_validate_length_for_collection!(
+    Vec<T>,
+    VecDeque<T>,
+    HashSet<T>,
+    BTreeSet<T>,
+    HashMap<K, V>,
+    BTreeMap<K, V>
+);
+
+impl<T: ValidateLength> ValidateLength for Option<T> {
+    fn validate_length(&self) -> Option<usize> {
+        self.as_ref().and_then(T::validate_length)
+    }
+}
+
+macro_rules! impl_validate_range_for_number {
+    ($($t:ty),*) => {
+        $(
+            impl ValidateRange for $t {
+                fn validate_range(&self) -> Option<f64> {
+                    Some(*self as f64)
+                }
+            }
+        )*
+    };
+}
+
+impl_validate_range_for_number!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64
+);
+
+impl<T: ValidateRange> ValidateRange for Option<T> {
+    fn validate_range(&self) -> Option<f64> {
+        self.as_ref().and_then(T::validate_range)
+    }
+}
+
+impl ValidatePattern for str {
+    fn validate_pattern(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl ValidatePattern for String {
+    fn validate_pattern(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl ValidatePattern for Cow<'_, str> {
+    fn validate_pattern(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl<T: ValidatePattern> ValidatePattern for Option<T> {
+    fn validate_pattern(&self) -> Option<&str> {
+        self.as_ref().and_then(T::validate_pattern)
+    }
+}
+
+pub fn validate_range<T: ValidateRange + ?Sized>(
+    value: &T,
+    min: Option<f64>,
+    max: Option<f64>,
+) -> Result<(), JsonError> {
+    let value = match value.validate_range() {
+        Some(value) => value,
+        None => return Ok(()),
+    };
+    match (min, max) {
+        (Some(min), _) if value < min => Err(JsonError::new(format!(
+            "Value {} is less than the minimum {}",
+            value, min
+        ))),
+        (_, Some(max)) if value > max => Err(JsonError::new(format!(
+            "Value {} is greater than the maximum {}",
+            value, max
+        ))),
+        _ => Ok(()),
+    }
+}
+
+pub fn validate_length<T: ValidateLength + ?Sized>(
+    value: &T,
+    min: Option<usize>,
+    max: Option<usize>,
+) -> Result<(), JsonError> {
+    let length = match value.validate_length() {
+        Some(length) => length,
+        None => return Ok(()),
+    };
+    match (min, max) {
+        (Some(min), _) if length < min => Err(JsonError::new(format!(
+            "Length {} is less than the minimum {}",
+            length, min
+        ))),
+        (_, Some(max)) if length > max => Err(JsonError::new(format!(
+            "Length {} is greater than the maximum {}",
+            length, max
+        ))),
+        _ => Ok(()),
+    }
+}
+
+pub fn validate_non_empty<T: ValidateLength + ?Sized>(value: &T) -> Result<(), JsonError> {
+    match value.validate_length() {
+        Some(0) => Err(JsonError::new("Value must not be empty")),
+        _ => Ok(()),
+    }
+}
+
+pub fn validate_pattern<T: ValidatePattern + ?Sized>(
+    value: &T,
+    pattern: &Pattern,
+) -> Result<(), JsonError> {
+    match value.validate_pattern() {
+        Some(value) if !pattern.is_match(value) => Err(JsonError::new(format!(
+            "Value \"{}\" does not match the pattern \"{}\"",
+            value,
+            pattern.as_str()
+        ))),
+        _ => Ok(()),
+    }
+}
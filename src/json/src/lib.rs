@@ -1,29 +1,106 @@
 mod json_basic;
+mod json_borrowed;
+mod json_cst;
 mod json_dumper;
+mod json_error;
 mod json_impl;
+mod json_infer;
+mod json_key;
 mod json_lexer;
+mod json_ndjson;
+mod json_options;
 mod json_parser;
+mod json_push;
+mod json_reader;
+mod json_schema;
+mod json_schema_validator;
+mod json_spread;
+mod json_std;
+mod json_stream;
+mod json_validate;
 
-use json_lexer::parse_all as lex_string_to_tokens;
-use json_parser::parse_all as parse_tokens_to_json;
 extern crate macros;
 
 pub use json_basic::FromJson;
 pub use json_basic::JsonNode;
 pub use json_basic::ToJson;
-pub use json_dumper::dump_json_node;
+pub use json_borrowed::{parse_borrowed, parse_borrowed_with_options, JsonValue};
+pub use json_cst::JsonDocument;
+pub use json_dumper::{dump_json_node, dump_json_node_compact};
+pub use json_error::{JsonError, ParseError, ParseErrorKind, Position};
+pub use json_infer::SchemaInference;
+pub use json_key::{map_to_json_pairs, map_try_from_json_pairs, JsonKey};
+pub use json_ndjson::{NdjsonReader, NdjsonValues, NdjsonWriter};
+pub use json_options::{DuplicateKeyPolicy, ParseOptions};
+pub use json_push::{JsonPushParser, PushStatus};
+pub use json_reader::{
+    parse_buf_reader, parse_buf_reader_with_options, parse_reader, parse_reader_with_options,
+    JsonEvent, JsonEventReader, JsonScalar,
+};
+pub use json_schema::{
+    schema_annotate, schema_for, schema_for_object, schema_for_pairs, schema_for_tuple,
+    schema_for_variant, schema_length, schema_one_of, schema_pattern, schema_range, JsonSchema,
+    SchemaDefinitions, SCHEMA_DIALECT,
+};
+pub use json_schema_validator::{Draft, SchemaValidator, ValidationError};
+pub use json_spread::{JsonArraySpread, JsonObjectSpread};
+pub use json_stream::JsonStream;
+pub use json_validate::{
+    validate_length, validate_non_empty, validate_pattern, validate_range, Pattern, ValidateLength,
+    ValidatePattern, ValidateRange,
+};
+pub use macros::include_json;
 pub use macros::json;
 pub use macros::json_array;
 pub use macros::json_object;
+pub use macros::json_str;
+pub use macros::FromJson;
+pub use macros::JsonKey;
+pub use macros::JsonSchema;
 pub use macros::JsonType;
+pub use macros::ToJson;
 
+/// Parses the first JSON value in `input`, ignoring any text after it.
 pub fn parse_json(input: &str) -> Option<json_basic::JsonNode> {
-    let tokens = lex_string_to_tokens(input);
-    parse_tokens_to_json(tokens?)
+    json_parser::JsonParser::new(json_lexer::JsonLexer::new(input))
+        .parse_value()
+        .ok()
+}
+
+/// Parses `input`, accepting the extensions to JSON that `options` turns on.
+pub fn parse_json_with_options(
+    input: &str,
+    options: ParseOptions,
+) -> Result<json_basic::JsonNode, ParseError> {
+    json_parser::parse_str_with_options(input, options)
 }
 
 impl JsonNode {
     pub fn dump(&self, indent: usize, escape_string: bool) -> String {
         dump_json_node(self, indent, escape_string)
     }
+
+    pub fn dump_compact(&self, escape_string: bool) -> String {
+        dump_json_node_compact(self, escape_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_json_ignores_text_after_the_first_value() {
+        assert_eq!(parse_json(r#"{"a": 1} {"b": 2}"#), Some(json!({"a": 1})));
+        assert_eq!(parse_json("[1, 2] trailing"), Some(json!([1, 2])));
+        assert_eq!(parse_json(" [1] "), Some(json!([1])));
+        assert_eq!(parse_json("[1, 2"), None);
+    }
+
+    #[test]
+    fn parse_json_rejects_invalid_values() {
+        for invalid in ["[1,]", "{\"a\" 1}", "01", "1.", "\"\\x\"", "nul", "[1"] {
+            assert_eq!(parse_json(invalid), None, "{}", invalid);
+        }
+    }
 }
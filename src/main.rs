@@ -2,4 +2,4 @@ mod application;
 
 fn main() {
     application::main();
-}
\ No newline at end of file
+}
@@ -0,0 +1,9 @@
+use std::collections::HashMap;
+
+#[derive(yiran_json::JsonType)]
+struct Grid {
+    #[json(as_pairs, with = "cells")]
+    cells: HashMap<(i32, i32), String>,
+}
+
+fn main() {}
@@ -0,0 +1,5 @@
+#[derive(yiran_json::JsonType)]
+#[json(deny_unknown_fields)]
+struct Pair(i32, i32);
+
+fn main() {}
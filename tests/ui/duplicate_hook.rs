@@ -0,0 +1,7 @@
+#[derive(yiran_json::JsonType)]
+struct Config {
+    #[json(with = "hooks", to_json_with = "hooks::to_json")]
+    name: String,
+}
+
+fn main() {}
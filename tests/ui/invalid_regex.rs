@@ -0,0 +1,7 @@
+#[derive(yiran_json::JsonType)]
+struct Account {
+    #[json(regex = "[a-z")]
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+#[derive(yiran_json::JsonKey)]
+enum Key {
+    Name(String),
+}
+
+fn main() {}
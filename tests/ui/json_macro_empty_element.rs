@@ -0,0 +1,5 @@
+use yiran_json::json;
+
+fn main() {
+    let _ = json!([1, , 2]);
+}
@@ -0,0 +1,8 @@
+use yiran_json::json;
+
+fn main() {
+    let _ = json!({
+        #[allow(unused)]
+        "a": 1,
+    });
+}
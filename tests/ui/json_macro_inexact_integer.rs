@@ -0,0 +1,9 @@
+use yiran_json::{json, json_str};
+
+fn main() {
+    let _ = json!(9007199254740993);
+    let _ = json!(300u8);
+    let _ = json!(-1u32);
+    let _ = json!(1usize2);
+    let _ = json_str!("[1, 9007199254740993]");
+}
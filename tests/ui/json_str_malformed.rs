@@ -0,0 +1,8 @@
+use yiran_json::json_str;
+
+fn main() {
+    let _ = json_str!(r#"{
+        "name": "service",
+        "port": 80,
+    }"#);
+}
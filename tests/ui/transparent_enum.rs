@@ -0,0 +1,7 @@
+#[derive(yiran_json::JsonType)]
+#[json(transparent)]
+enum Id {
+    Number(u64),
+}
+
+fn main() {}
@@ -0,0 +1,5 @@
+#[derive(yiran_json::JsonType)]
+#[json(transparent)]
+struct Point(i32, i32);
+
+fn main() {}
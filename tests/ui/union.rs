@@ -0,0 +1,7 @@
+#[derive(yiran_json::JsonType)]
+union Bits {
+    int: u32,
+    float: f32,
+}
+
+fn main() {}
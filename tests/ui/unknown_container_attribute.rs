@@ -0,0 +1,7 @@
+#[derive(yiran_json::JsonType)]
+#[json(deny_unknown_field)]
+struct Config {
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[derive(yiran_json::JsonType)]
+struct Config {
+    #[json(rename = "other")]
+    name: String,
+}
+
+fn main() {}